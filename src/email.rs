@@ -0,0 +1,143 @@
+use std::path::PathBuf;
+
+use lsp_types::{Position, PositionEncodingKind, Range};
+
+use crate::{
+    m2::{M2Item, M2Path},
+    php,
+    state::State,
+    ts,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmailCompletion {
+    pub text: String,
+    pub range: Range,
+}
+
+/// Email templates live in `email` dir of module `view/<area>` or theme module dir
+pub fn is_email_template(path: &PathBuf) -> bool {
+    matches!(path.get_ext().as_str(), "html" | "txt") && path.has_components(&["email"])
+}
+
+pub fn get_item_from_position(state: &State, path: &PathBuf, pos: Position) -> Option<M2Item> {
+    let content = state.get_file_or_read(path)?;
+    get_item_from_pos(state, &content, pos)
+}
+
+// `{{view url="Vendor_Module::images/logo.png"}}` points at module web asset
+fn get_item_from_pos(state: &State, content: &str, pos: Position) -> Option<M2Item> {
+    let offset = ts::position_to_offset(content, pos, state.position_encoding());
+    let start = directive_start(content, offset)?;
+    let end = offset + content[offset..].find("}}")?;
+    let args = view_directive_args(&content[start..end])?;
+    php::text_to_view_file(state, directive_param(args, "url")?)
+}
+
+pub fn get_completion_item(
+    content: &str,
+    pos: Position,
+    encoding: &PositionEncodingKind,
+) -> Option<EmailCompletion> {
+    let offset = ts::position_to_offset(content, pos, encoding);
+    let start = directive_start(content, offset)?;
+    let args = view_directive_args(&content[start..offset])?;
+    let (_, value) = args.rsplit_once("url=")?;
+    let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let text = &value[1..];
+    if text.contains(quote) {
+        return None;
+    }
+    Some(EmailCompletion {
+        text: text.into(),
+        range: Range {
+            start: ts::offset_to_position(content, offset - text.len(), encoding),
+            end: pos,
+        },
+    })
+}
+
+// directive is still open at the offset, closing braces may not be typed yet
+fn directive_start(content: &str, offset: usize) -> Option<usize> {
+    let start = content[..offset].rfind("{{")? + 2;
+    (!content[start..offset].contains("}}")).then_some(start)
+}
+
+fn view_directive_args(directive: &str) -> Option<&str> {
+    directive
+        .trim_start()
+        .strip_prefix("view")
+        .filter(|args| args.starts_with(char::is_whitespace))
+}
+
+fn directive_param<'a>(args: &'a str, name: &str) -> Option<&'a str> {
+    let value = args
+        .split_whitespace()
+        .find_map(|arg| arg.strip_prefix(name)?.strip_prefix('='))?;
+    let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    value[1..].split(quote).next()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::get_test_item;
+
+    #[test]
+    fn test_view_url_in_email_template() {
+        let item = get_test_item(
+            r#"<p>Hello</p>
+            <img src="{{view url="Some_Module::images/lo|go.svg"}}" alt="Logo"/>"#,
+            "/a/b/c/view/frontend/email/order_new.html",
+        );
+
+        assert_eq!(
+            item,
+            Some(M2Item::ModHtml(
+                "Some_Module".into(),
+                "images/logo.svg".into(),
+                PathBuf::from("/a/b/c/Some_Module")
+            ))
+        );
+    }
+
+    #[test]
+    fn test_view_url_outside_of_directive() {
+        let item = get_test_item(
+            r#"<img src="{{view url='Some_Module::images/logo.svg'}}" alt="Lo|go"/>"#,
+            "/a/b/c/view/frontend/email/order_new.html",
+        );
+
+        assert_eq!(item, None);
+    }
+
+    #[test]
+    fn test_completion_item_in_view_directive() {
+        let content = "<img src=\"{{view url='Some_Module::ima\"/>";
+
+        assert_eq!(
+            get_completion_item(content, Position::new(0, 38), &PositionEncodingKind::UTF16),
+            Some(EmailCompletion {
+                text: "Some_Module::ima".into(),
+                range: Range {
+                    start: Position::new(0, 22),
+                    end: Position::new(0, 38),
+                },
+            })
+        );
+        assert_eq!(
+            get_completion_item(content, Position::new(0, 12), &PositionEncodingKind::UTF16),
+            None
+        );
+    }
+
+    #[test]
+    fn test_view_url_only_in_email_templates() {
+        let item = get_test_item(
+            r#"<img src="{{view url="Some_Module::images/lo|go.svg"}}"/>"#,
+            "/a/b/c/view/frontend/web/template/logo.html",
+        );
+
+        assert_eq!(item, None);
+    }
+}
@@ -1,13 +1,14 @@
 use std::path::{Path, PathBuf};
 
 use glob::glob;
-use lsp_types::{Position, Range};
-use tree_sitter::{Node, QueryCursor};
+use lsp_types::{Position, PositionEncodingKind, Range};
+use tree_sitter::{Node, QueryCursor, Tree};
 
 use crate::{
     m2::{M2Area, M2Item, M2Path},
+    progress::Progress,
     queries,
-    state::{ArcState, State},
+    state::{self, ArcState, State},
     ts::{self, node_at_position},
 };
 
@@ -31,39 +32,35 @@ pub struct JsCompletion {
     pub kind: JsCompletionType,
 }
 
-pub fn update_index(state: &ArcState, path: &PathBuf) {
-    // if current workspace is magento module
-    process_glob(state, &path.append(&["view", "*", "requirejs-config.js"]));
-    // if current workspace is magento installation
-    process_glob(
-        state,
-        &path.append(&["vendor", "*", "*", "view", "*", "requirejs-config.js"]),
-    );
-    process_glob(
-        state,
-        &path.append(&["vendor", "*", "*", "Magento_Theme", "requirejs-config.js"]),
-    );
-    process_glob(
-        state,
-        &path.append(&["app", "code", "*", "*", "view", "*", "requirejs-config.js"]),
-    );
-    process_glob(
-        state,
-        &path.append(&["app", "design", "**", "requirejs-config.js"]),
-    );
+pub fn update_index(state: &ArcState, path: &PathBuf, progress: &Progress) {
+    let glob_paths = [
+        // if current workspace is magento module
+        path.append(&["view", "*", "requirejs-config.js"]),
+        // if current workspace is magento installation
+        path.append(&["vendor", "*", "*", "view", "*", "requirejs-config.js"]),
+        path.append(&["vendor", "*", "*", "Magento_Theme", "requirejs-config.js"]),
+        path.append(&["app", "code", "*", "*", "view", "*", "requirejs-config.js"]),
+        path.append(&["app", "design", "**", "requirejs-config.js"]),
+    ];
+    for (done, glob_path) in glob_paths.iter().enumerate() {
+        process_glob(state, glob_path);
+        progress.report(done + 1, glob_paths.len());
+    }
 }
 
-pub fn maybe_index_file(state: &mut State, content: &str, file_path: &PathBuf) {
+pub fn maybe_index_file(state: &mut State, content: &str, tree: &Tree, file_path: &PathBuf) {
     if file_path.to_path_str().ends_with("requirejs-config.js") {
-        update_index_from_config(state, content, file_path);
+        update_index_from_config(state, content, tree, file_path);
     }
 }
 
 fn index_file(state: &ArcState, file_path: &PathBuf) {
-    let content =
-        std::fs::read_to_string(file_path).expect("Should have been able to read the file");
+    let Some(content) = state::read_indexed_file(file_path) else {
+        return;
+    };
+    let tree = tree_sitter_parsers::parse(&content, "javascript");
 
-    update_index_from_config(&mut state.lock(), &content, file_path);
+    update_index_from_config(&mut state.lock(), &content, &tree, file_path);
 }
 
 fn process_glob(state: &ArcState, glob_path: &PathBuf) {
@@ -72,31 +69,34 @@ fn process_glob(state: &ArcState, glob_path: &PathBuf) {
         .filter_map(Result::ok);
 
     for file_path in modules {
+        if state.lock().options().is_excluded(&file_path) {
+            continue;
+        }
         index_file(state, &file_path);
     }
 }
 
-pub fn get_completion_item(content: &str, pos: Position) -> Option<JsCompletion> {
-    let tree = tree_sitter_parsers::parse(content, "javascript");
+pub fn get_completion_item(
+    tree: &Tree,
+    content: &str,
+    pos: Position,
+    encoding: &PositionEncodingKind,
+) -> Option<JsCompletion> {
     let query = queries::js_completion_definition_item();
     let mut cursor = QueryCursor::new();
     let matches = cursor.matches(query, tree.root_node(), content.as_bytes());
 
     for m in matches {
         let node = m.captures[1].node;
-        if node_at_position(node, pos) {
-            let mut text = ts::get_node_text_before_pos(node, content, pos);
+        if node_at_position(node, content, pos, encoding) {
+            let mut text = ts::get_node_text_before_pos(node, content, pos, encoding);
             if text.is_empty() {
                 return None;
             }
             text = text[1..].to_string();
-            let range = Range {
-                start: Position {
-                    line: node.start_position().row as u32,
-                    character: 1 + node.start_position().column as u32,
-                },
-                end: pos,
-            };
+            let mut start = ts::get_range_from_node(node, content, encoding).start;
+            start.character += 1;
+            let range = Range { start, end: pos };
 
             return Some(JsCompletion {
                 text,
@@ -110,8 +110,9 @@ pub fn get_completion_item(content: &str, pos: Position) -> Option<JsCompletion>
 }
 
 pub fn get_item_from_position(state: &State, path: &PathBuf, pos: Position) -> Option<M2Item> {
-    let content = state.get_file(path)?;
-    get_item_from_pos(state, content, path, pos)
+    let content = state.get_file_or_read(path)?;
+    let tree = state.get_tree(path, &content)?;
+    get_item_from_pos(state, &content, &tree, path, pos)
 }
 
 pub fn text_to_component(state: &State, text: &str, path: &Path) -> Option<M2Item> {
@@ -121,17 +122,33 @@ pub fn text_to_component(state: &State, text: &str, path: &Path) -> Option<M2Ite
     }
     let text = &resolve_paths(state, text, &path.to_path_buf().get_area())?;
     let text = resolve_maps(state, text, &path.to_path_buf().get_area())?;
-    return resolved_text_to_component(state, text, path);
+    resolved_text_to_component(state, text, path)
+}
+
+pub fn text_to_template(state: &State, text: &str, path: &Path) -> Option<M2Item> {
+    // knockout template loader looks for `Module_Name/template/path/to/file.html`
+    let mut parts = text.splitn(2, '/');
+    let module = parts.next()?;
+    let template = parts.next()?.trim_end_matches(".html");
+    let text = format!("{}/template/{}.html", module, template);
+
+    // `ui/template` is mapped to `Magento_Ui/templates` by Magento_Ui requirejs config paths
+    text_to_component(state, &text, path).filter(|item| matches!(item, M2Item::ModHtml(..)))
 }
 
-fn get_item_from_pos(state: &State, content: &str, path: &Path, pos: Position) -> Option<M2Item> {
-    let tree = tree_sitter_parsers::parse(content, "javascript");
+fn get_item_from_pos(
+    state: &State,
+    content: &str,
+    tree: &Tree,
+    path: &Path,
+    pos: Position,
+) -> Option<M2Item> {
     let query = queries::js_item_from_pos();
     let mut cursor = QueryCursor::new();
     let matches = cursor.matches(query, tree.root_node(), content.as_bytes());
 
     for m in matches {
-        if node_at_position(m.captures[0].node, pos) {
+        if node_at_position(m.captures[0].node, content, pos, state.position_encoding()) {
             let text = get_node_text(m.captures[0].node, content);
             return text_to_component(state, text, path);
         }
@@ -141,16 +158,21 @@ fn get_item_from_pos(state: &State, content: &str, path: &Path, pos: Position) -
 }
 
 fn resolve_paths(state: &State, text: &str, area: &M2Area) -> Option<String> {
-    let mut result = String::from(text);
     let paths = state.get_component_paths_for_area(area);
-    for path in paths {
-        let path_slash = path.clone() + "/";
-        if text == path || text.starts_with(&path_slash) {
-            let new_path = state.get_component_path(&path, area)?;
-            result = result.replacen(&path, new_path, 1);
-        };
+    // most specific path wins
+    let matching = paths
+        .iter()
+        .filter(|path| text == *path || text.starts_with(&format!("{}/", path)))
+        .max_by_key(|path| path.len());
+    if let Some(path) = matching {
+        let new_path = state.get_component_path(path, area)?;
+        return Some(text.replacen(path.as_str(), new_path, 1));
     }
-    Some(result)
+    // paths from lower area (base) are also available
+    area.lower_area().map_or_else(
+        || Some(text.to_string()),
+        |lower_area| resolve_paths(state, text, &lower_area),
+    )
 }
 
 fn resolve_maps<'a>(state: &'a State, text: &'a str, area: &M2Area) -> Option<&'a str> {
@@ -187,15 +209,17 @@ fn resolved_text_to_component(state: &State, text: &str, path: &Path) -> Option<
             parts.next()?.into(),
             mod_path,
         ))
+    } else if state.get_module_path(text).is_some() {
+        // bare module name has no file to point to, so module itself is the target
+        Some(M2Item::Module(text.into()))
     } else {
         Some(M2Item::Component(text.into()))
     }
 }
 
-fn update_index_from_config(state: &mut State, content: &str, file_path: &PathBuf) {
+fn update_index_from_config(state: &mut State, content: &str, tree: &Tree, file_path: &PathBuf) {
     state.set_source_file(file_path);
     let area = &file_path.get_area();
-    let tree = tree_sitter_parsers::parse(content, "javascript");
     let query = queries::js_require_config();
 
     let mut cursor = QueryCursor::new();
@@ -243,6 +267,7 @@ mod test {
     use std::path::PathBuf;
 
     use super::*;
+    use crate::test_utils::get_test_item;
 
     #[test]
     fn test_update_index_from_config() {
@@ -273,7 +298,8 @@ mod test {
         "#;
 
         let arc_state = state.into_arc();
-        update_index_from_config(&mut arc_state.lock(), content, &PathBuf::from(""));
+        let tree = tree_sitter_parsers::parse(content, "javascript");
+        update_index_from_config(&mut arc_state.lock(), content, &tree, &PathBuf::from(""));
 
         let mut result = State::new();
         result.add_component_path(
@@ -329,7 +355,7 @@ mod test {
                 'Some_Module/some/vie|w',
             ], function (someView) {})
             "#,
-            "/a/b/c",
+            "/a/b/c/some.js",
         );
         assert_eq!(
             item,
@@ -349,11 +375,24 @@ mod test {
                 'jqu|ery',
             ], function ($) {})
             "#,
-            "/a/b/c",
+            "/a/b/c/some.js",
         );
         assert_eq!(item, Some(M2Item::Component("jquery".into())));
     }
 
+    #[test]
+    fn get_item_from_pos_bare_module_name() {
+        let item = get_test_item(
+            r#"
+            define([
+                'Some_Mod|ule',
+            ], function (someModule) {})
+            "#,
+            "/a/b/c/some.js",
+        );
+        assert_eq!(item, Some(M2Item::Module("Some_Module".into())));
+    }
+
     #[test]
     fn get_item_from_pos_component_with_slashes() {
         let item = get_test_item(
@@ -362,7 +401,7 @@ mod test {
                 'jqu|ery-ui-modules/widget',
             ], function (widget) {})
             "#,
-            "/a/b/c",
+            "/a/b/c/some.js",
         );
         assert_eq!(
             item,
@@ -370,21 +409,103 @@ mod test {
         );
     }
 
-    fn get_test_item(xml: &str, path: &str) -> Option<M2Item> {
-        let win_path = format!("c:{}", path.replace('/', "\\"));
-        let mut character = 0;
-        let mut line = 0;
-        for l in xml.lines() {
-            if l.contains('|') {
-                character = l.find('|').expect("Test has to have a | character") as u32;
-                break;
-            }
-            line += 1;
-        }
-        let pos = Position { line, character };
-        let uri = PathBuf::from(if cfg!(windows) { &win_path } else { path });
+    #[test]
+    fn get_item_from_pos_mod_component_with_deep_subpath() {
+        let mut state = State::new();
+        state.add_module_path("Magento_Ui", PathBuf::from("/a/b/Magento/Ui"));
+        let content = "define(['Magento_Ui/js/lib/collapsible'], function (collapsible) {})";
+
+        let item = get_item_from_pos(
+            &state,
+            content,
+            &tree_sitter_parsers::parse(content, "javascript"),
+            &PathBuf::from("/a/b/c/view/frontend/web/js/some.js"),
+            Position {
+                line: 0,
+                character: 20,
+            },
+        );
+
+        assert_eq!(
+            item,
+            Some(M2Item::ModComponent(
+                "Magento_Ui".into(),
+                "js/lib/collapsible".into(),
+                PathBuf::from("/a/b/Magento/Ui")
+            ))
+        );
+    }
+
+    #[test]
+    fn get_item_from_pos_paths_alias_to_lib_component() {
+        let mut state = State::new();
+        state.add_component_path("someLib", "some/lib-component", &M2Area::Base);
+        let content = "define(['someLib'], function (someLib) {})";
+
+        let item = get_item_from_pos(
+            &state,
+            content,
+            &tree_sitter_parsers::parse(content, "javascript"),
+            &PathBuf::from("/a/b/c/view/frontend/web/js/some.js"),
+            Position {
+                line: 0,
+                character: 12,
+            },
+        );
+
+        assert_eq!(item, Some(M2Item::Component("some/lib-component".into())));
+    }
+
+    #[test]
+    fn get_item_from_pos_map_alias() {
         let mut state = State::new();
         state.add_module_path("Some_Module", PathBuf::from("/a/b/c/Some_Module"));
-        get_item_from_pos(&state, &xml.replace('|', ""), &uri, pos)
+        state.add_component_map("someAlias", "Some_Module/js/component", &M2Area::Base);
+        let content = "define(['someAlias'], function (some) {})";
+
+        let item = get_item_from_pos(
+            &state,
+            content,
+            &tree_sitter_parsers::parse(content, "javascript"),
+            &PathBuf::from("/a/b/c/view/frontend/web/js/some.js"),
+            Position {
+                line: 0,
+                character: 12,
+            },
+        );
+
+        assert_eq!(
+            item,
+            Some(M2Item::ModComponent(
+                "Some_Module".into(),
+                "js/component".into(),
+                PathBuf::from("/a/b/c/Some_Module")
+            ))
+        );
+    }
+
+    #[test]
+    fn get_completion_item_after_multibyte_char() {
+        let content = "define(['ąę', 'Some_Mod'], function () {})";
+
+        let tree = tree_sitter_parsers::parse(content, "javascript");
+        let item = get_completion_item(
+            &tree,
+            content,
+            Position::new(0, 23),
+            &PositionEncodingKind::UTF16,
+        );
+
+        assert_eq!(
+            item,
+            Some(JsCompletion {
+                text: "Some_Mod".into(),
+                range: Range {
+                    start: Position::new(0, 15),
+                    end: Position::new(0, 23),
+                },
+                kind: JsCompletionType::Definition,
+            })
+        );
     }
 }
@@ -0,0 +1,186 @@
+use std::path::{Component, Path, PathBuf};
+
+use lsp_types::Position;
+
+use crate::{
+    m2::{M2Item, M2Path},
+    php,
+    state::State,
+    ts,
+};
+
+const MAGENTO_IMPORT: &str = "//@magento_import";
+
+pub fn get_item_from_position(state: &State, path: &PathBuf, pos: Position) -> Option<M2Item> {
+    let content = state.get_file_or_read(path)?;
+    get_item_from_pos(state, &content, path, pos)
+}
+
+fn get_item_from_pos(state: &State, content: &str, path: &Path, pos: Position) -> Option<M2Item> {
+    let line = content.lines().nth(pos.line as usize)?;
+    if line.trim_start().starts_with(MAGENTO_IMPORT) {
+        return magento_import_item(line, path);
+    }
+
+    let offset = ts::position_to_offset(content, pos, state.position_encoding());
+    let line_start = content[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let url = url_at_column(line, offset - line_start)?;
+    url_item(state, url, path)
+}
+
+// `//@magento_import 'source/_module.less';` collects file of the same path,
+// relative to `web` dir, from every module and theme
+fn magento_import_item(line: &str, path: &Path) -> Option<M2Item> {
+    let args = line.trim_start().strip_prefix(MAGENTO_IMPORT)?;
+    let quote_start = args.find(['\'', '"'])?;
+    let import = args[quote_start + 1..].split(['\'', '"']).next()?;
+    if import.is_empty() {
+        return None;
+    }
+
+    let dir = path.parent()?.to_path_buf();
+    let dir_parts = dir.str_components();
+    let web_pos = dir_parts.iter().rposition(|part| *part == "web")?;
+    let mut parts = dir_parts[web_pos + 1..].to_vec();
+    parts.extend(
+        import
+            .split('/')
+            .filter(|part| !part.is_empty() && *part != "."),
+    );
+
+    let mut file_path = parts.join("/");
+    if !file_path.ends_with(".less") {
+        file_path += ".less";
+    }
+    Some(M2Item::LessImport(file_path))
+}
+
+// target of `url()` under the cursor, without quotes
+fn url_at_column(line: &str, column: usize) -> Option<&str> {
+    let mut search_from = 0;
+    while let Some(found) = line[search_from..].find("url(") {
+        let start = search_from + found + "url(".len();
+        let end = start + line[start..].find(')')?;
+        if (start..=end).contains(&column) {
+            return Some(line[start..end].trim().trim_matches(['\'', '"']));
+        }
+        search_from = end;
+    }
+    None
+}
+
+// `Module_Name::images/file.png` is a module web asset, anything else
+// (but external and absolute urls) is relative to the current file
+fn url_item(state: &State, url: &str, path: &Path) -> Option<M2Item> {
+    let url = url.split(['?', '#']).next()?;
+    if url.contains("::") {
+        return php::text_to_view_file(state, url);
+    }
+    if url.is_empty() || url.starts_with(['/', '@']) || url.contains(':') {
+        return None;
+    }
+
+    let mut file_path = path.parent()?.to_path_buf();
+    for component in Path::new(url).components() {
+        match component {
+            Component::ParentDir => {
+                file_path.pop();
+            }
+            Component::Normal(part) => file_path.push(part),
+            _ => (),
+        }
+    }
+    Some(M2Item::AssetFile(file_path))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::{get_test_item, item_at_cursor};
+
+    #[test]
+    fn test_magento_import_relative_to_web_dir() {
+        let item = get_test_item(
+            "@import 'source/lib/_lib.less';\n//@magento_import 'source/_mod|ule.less';",
+            "/a/b/app/design/frontend/Some/theme/web/css/styles-m.less",
+        );
+
+        assert_eq!(
+            item,
+            Some(M2Item::LessImport("css/source/_module.less".into()))
+        );
+    }
+
+    #[test]
+    fn test_magento_import_with_reference_and_no_extension() {
+        let item = get_test_item(
+            "//@magento_import (reference) \"source/_ext|end\";",
+            "/a/b/app/design/frontend/Some/theme/web/css/styles-m.less",
+        );
+
+        assert_eq!(
+            item,
+            Some(M2Item::LessImport("css/source/_extend.less".into()))
+        );
+    }
+
+    #[test]
+    fn test_plain_import_is_not_resolved() {
+        let item = get_test_item(
+            "@import 'source/_mod|ule.less';",
+            "/a/b/app/design/frontend/Some/theme/web/css/styles-m.less",
+        );
+
+        assert_eq!(item, None);
+    }
+
+    #[test]
+    fn test_relative_url() {
+        let item = get_test_item(
+            ".logo { background: url('../images/lo|go.png?v=1') no-repeat; }",
+            "/a/b/app/design/frontend/Some/theme/web/css/source/_extend.less",
+        );
+
+        assert_eq!(
+            item,
+            Some(M2Item::AssetFile(PathBuf::from(
+                "/a/b/app/design/frontend/Some/theme/web/css/images/logo.png"
+            )))
+        );
+    }
+
+    #[test]
+    fn test_module_url() {
+        let mut state = State::new();
+        state.add_module_path("Some_Module", PathBuf::from("/a/b/Some/Module"));
+
+        let item = item_at_cursor(
+            &mut state,
+            ".icon { background: url(\"Some_Module::images/ic|on.svg\"); }",
+            "/a/b/app/design/frontend/Some/theme/web/css/styles.css",
+        );
+
+        assert_eq!(
+            item,
+            Some(M2Item::ModHtml(
+                "Some_Module".into(),
+                "images/icon.svg".into(),
+                PathBuf::from("/a/b/Some/Module")
+            ))
+        );
+    }
+
+    #[test]
+    fn test_url_outside_cursor_and_external_url() {
+        let path = "/a/b/app/design/frontend/Some/theme/web/css/styles.css";
+
+        assert_eq!(
+            get_test_item(".a { col|or: red; background: url(a.png); }", path),
+            None
+        );
+        assert_eq!(
+            get_test_item(".a { background: url(https://some.com/a.p|ng); }", path),
+            None
+        );
+    }
+}
@@ -1,22 +1,218 @@
 mod completion;
-mod definition;
+pub mod definition;
+mod hover;
+mod references;
+mod signature;
+mod symbol;
 
 use lsp_types::{
-    CompletionParams, CompletionResponse, GotoDefinitionParams, GotoDefinitionResponse,
+    CompletionList, CompletionParams, CompletionResponse, DocumentSymbolParams,
+    DocumentSymbolResponse, GotoDefinitionParams, GotoDefinitionResponse, Hover, HoverParams,
+    Location, ReferenceParams, SignatureHelp, SignatureHelpParams, TextDocumentPositionParams,
+    WorkspaceSymbolParams, WorkspaceSymbolResponse,
 };
+use serde::{Deserialize, Serialize};
 
-use crate::state::State;
+use crate::{
+    state::{ArcState, State},
+    trace::Trace,
+};
+
+use self::{
+    completion::get_completion_from_params,
+    definition::{
+        get_location_from_params, get_targets_from_params, get_type_location_from_params,
+    },
+    hover::get_hover_from_params,
+    references::get_references_from_params,
+    signature::get_signature_help_from_params,
+    symbol::{get_document_symbols, get_workspace_symbols},
+};
+
+pub enum ReindexModule {}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ReindexModuleParams {
+    pub module: String,
+}
+
+impl lsp_types::request::Request for ReindexModule {
+    type Params = ReindexModuleParams;
+    type Result = bool;
+    const METHOD: &'static str = "magento2-ls/reindexModule";
+}
+
+pub enum Targets {}
+
+impl lsp_types::request::Request for Targets {
+    type Params = TextDocumentPositionParams;
+    type Result = Vec<Location>;
+    const METHOD: &'static str = "magento2-ls/targets";
+}
 
-use self::{completion::get_completion_from_params, definition::get_location_from_params};
+/// `workspace/executeCommand` command rebuilding the whole index
+pub const REINDEX_COMMAND: &str = "magento2-ls.reindex";
 
-pub fn completion_handler(state: &State, params: &CompletionParams) -> CompletionResponse {
-    CompletionResponse::Array(
-        get_completion_from_params(state, params).map_or(vec![], |loc_list| loc_list),
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ReindexSummary {
+    pub modules: usize,
+}
+
+pub fn completion_handler(
+    state: &State,
+    params: &CompletionParams,
+    indexing: bool,
+) -> CompletionResponse {
+    let items = get_completion_from_params(state, params).map_or(vec![], |loc_list| loc_list);
+    if indexing {
+        // ask client to request completion again, as index is still being built
+        CompletionResponse::List(CompletionList {
+            is_incomplete: true,
+            items,
+        })
+    } else {
+        CompletionResponse::Array(items)
+    }
+}
+
+pub fn definition_handler(
+    state: &State,
+    params: &GotoDefinitionParams,
+    trace: &mut Trace,
+) -> GotoDefinitionResponse {
+    GotoDefinitionResponse::Array(
+        get_location_from_params(state, params, trace).map_or(vec![], |loc_list| loc_list),
     )
 }
 
-pub fn definition_handler(state: &State, params: &GotoDefinitionParams) -> GotoDefinitionResponse {
+pub fn type_definition_handler(
+    state: &State,
+    params: &GotoDefinitionParams,
+) -> GotoDefinitionResponse {
     GotoDefinitionResponse::Array(
-        get_location_from_params(state, params).map_or(vec![], |loc_list| loc_list),
+        get_type_location_from_params(state, params).map_or(vec![], |loc_list| loc_list),
     )
 }
+
+pub fn hover_handler(state: &State, params: &HoverParams) -> Option<Hover> {
+    get_hover_from_params(state, params)
+}
+
+pub fn references_handler(state: &State, params: &ReferenceParams) -> Vec<Location> {
+    get_references_from_params(state, params).unwrap_or_default()
+}
+
+pub fn signature_help_handler(
+    state: &State,
+    params: &SignatureHelpParams,
+) -> Option<SignatureHelp> {
+    get_signature_help_from_params(state, params)
+}
+
+pub fn document_symbol_handler(
+    state: &State,
+    params: &DocumentSymbolParams,
+) -> DocumentSymbolResponse {
+    DocumentSymbolResponse::Nested(get_document_symbols(state, params))
+}
+
+pub fn workspace_symbol_handler(
+    state: &State,
+    params: &WorkspaceSymbolParams,
+) -> WorkspaceSymbolResponse {
+    WorkspaceSymbolResponse::Flat(get_workspace_symbols(state, params))
+}
+
+pub fn targets_handler(state: &State, params: &TextDocumentPositionParams) -> Vec<Location> {
+    get_targets_from_params(state, params).unwrap_or_default()
+}
+
+pub fn reindex_module_handler(state: &ArcState, params: &ReindexModuleParams) -> bool {
+    State::reindex_module(state, &params.module)
+}
+
+/// Waits for the whole workspace to be indexed again
+pub fn reindex_handler(state: &ArcState) -> ReindexSummary {
+    for thread in State::reindex(state) {
+        thread.join().ok();
+    }
+    ReindexSummary {
+        modules: state.lock().get_modules().len(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use lsp_types::{Position, TextDocumentIdentifier, TextDocumentPositionParams, Url};
+
+    use super::*;
+    use crate::test_utils::TempDir;
+
+    fn completion_params(path: &str, line: u32, character: u32) -> CompletionParams {
+        CompletionParams {
+            text_document_position: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier {
+                    uri: Url::from_file_path(path).unwrap(),
+                },
+                position: Position { line, character },
+            },
+            work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+            partial_result_params: lsp_types::PartialResultParams::default(),
+            context: None,
+        }
+    }
+
+    #[test]
+    fn test_completion_handler_incomplete_while_indexing() {
+        let mut state = State::new();
+        state.add_cache_type("full_page", "Magento\\PageCache\\Model\\Cache\\Type");
+        state.set_file(
+            &PathBuf::from("/a/b/c/etc/cache.xml"),
+            r#"<config><type name=""/></config>"#,
+        );
+        let params = completion_params("/a/b/c/etc/cache.xml", 0, 20);
+
+        let CompletionResponse::List(list) = completion_handler(&state, &params, true) else {
+            panic!("Should return completion list while indexing");
+        };
+        assert!(list.is_incomplete);
+        assert_eq!(list.items[0].label, "full_page");
+
+        assert!(matches!(
+            completion_handler(&state, &params, false),
+            CompletionResponse::Array(_)
+        ));
+    }
+
+    #[test]
+    fn test_reindex_handler_finds_modules_added_since_indexing() {
+        let workspace = TempDir::new("reindex");
+        let registration = |module: &str| {
+            format!("<?php\nComponentRegistrar::register(ComponentRegistrar::MODULE, '{module}', __DIR__);\n")
+        };
+        workspace.write(
+            &["app", "code", "Some", "First", "registration.php"],
+            &registration("Some_First"),
+        );
+        let arc_state = State::new().into_arc();
+        for thread in State::update_index(&arc_state, &workspace.path(&[])) {
+            thread.join().unwrap();
+        }
+        assert_eq!(arc_state.lock().get_modules(), vec!["Some_First"]);
+
+        workspace.write(
+            &["app", "code", "Some", "Second", "registration.php"],
+            &registration("Some_Second"),
+        );
+        let summary = reindex_handler(&arc_state);
+
+        let state = arc_state.lock();
+        let mut modules = state.get_modules();
+        modules.sort_unstable();
+        assert_eq!(summary, ReindexSummary { modules: 2 });
+        assert_eq!(modules, vec!["Some_First", "Some_Second"]);
+        assert_eq!(state.workspace_paths(), vec![workspace.path(&[])]);
+    }
+}
@@ -1,6 +1,9 @@
 mod events;
 
-use std::path::PathBuf;
+use std::{
+    collections::{HashSet, VecDeque},
+    path::{Path, PathBuf},
+};
 
 use glob::glob;
 use lsp_types::{
@@ -9,12 +12,75 @@ use lsp_types::{
 };
 
 use crate::{
+    email,
     js::{self, JsCompletionType},
     m2::{self, M2Area, M2Path, M2Uri},
-    state::State,
+    php::ClassSummary,
+    state::{CompletionCandidate, State},
     xml,
 };
 
+use super::definition;
+
+const OBSERVER_INTERFACE: &str = "Magento\\Framework\\Event\\ObserverInterface";
+
+// form element types known to the element factory, plus the ones added by config form
+const FIELD_TYPES: [&str; 31] = [
+    "allowspecific",
+    "button",
+    "checkbox",
+    "checkboxes",
+    "column",
+    "date",
+    "editablemultiselect",
+    "editor",
+    "export",
+    "fieldset",
+    "file",
+    "gallery",
+    "hidden",
+    "image",
+    "imagefile",
+    "import",
+    "label",
+    "link",
+    "multiline",
+    "multiselect",
+    "note",
+    "obscure",
+    "password",
+    "radio",
+    "radios",
+    "reset",
+    "select",
+    "submit",
+    "text",
+    "textarea",
+    "time",
+];
+
+// assets usually referenced from email templates with `{{view url=""}}`
+const EMAIL_ASSET_EXTENSIONS: [&str; 6] = ["png", "jpg", "jpeg", "gif", "svg", "css"];
+
+// ui component arguments holding system config path (e.g. `<item name="ifconfig"/>`)
+const UI_CONFIG_PATH_ARGUMENTS: &[&str] = &["ifconfig", "canShow", "configPath"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TemplateKind {
+    Phtml,
+    Html,
+}
+
+impl TemplateKind {
+    fn from_path(path: &PathBuf) -> Self {
+        if path.has_components(&["ui_component"]) {
+            Self::Html
+        } else {
+            Self::Phtml
+        }
+    }
+}
+
 pub fn get_completion_from_params(
     state: &State,
     params: &CompletionParams,
@@ -23,24 +89,58 @@ pub fn get_completion_from_params(
         .text_document_position
         .text_document
         .uri
-        .to_path_buf();
+        .to_path_buf()?;
     let pos = params.text_document_position.position;
 
     match path.get_ext().as_str() {
         "xml" => xml_completion_handler(state, &path, pos),
         "js" => js_completion_handler(state, &path, pos),
+        "html" | "txt" if email::is_email_template(&path) => {
+            email_completion_handler(state, &path, pos)
+        }
         _ => None,
     }
 }
 
+fn email_completion_handler(
+    state: &State,
+    path: &PathBuf,
+    pos: Position,
+) -> Option<Vec<CompletionItem>> {
+    let at_position = email::get_completion_item(
+        &state.get_file_or_read(path)?,
+        pos,
+        state.position_encoding(),
+    )?;
+    let area = path.get_area();
+    if !at_position.text.contains("::") {
+        return completion_for_web_asset(state, &at_position.text, at_position.range, &area, &[]);
+    }
+
+    // every extension separately, so none of them is renamed like less to css
+    let mut items = vec![];
+    for ext in EMAIL_ASSET_EXTENSIONS {
+        items.extend(
+            completion_for_web_asset(state, &at_position.text, at_position.range, &area, &[ext])
+                .unwrap_or_default(),
+        );
+    }
+    Some(items)
+}
+
 fn js_completion_handler(
     state: &State,
     path: &PathBuf,
     pos: Position,
 ) -> Option<Vec<CompletionItem>> {
-    let at_position = js::get_completion_item(state.get_file(path)?, pos)?;
+    let content = state.get_file_or_read(path)?;
+    let tree = state.get_tree(path, &content)?;
+    let at_position = js::get_completion_item(&tree, &content, pos, state.position_encoding())?;
 
     match at_position.kind {
+        JsCompletionType::Definition if is_relative_component(&at_position.text) => {
+            completion_for_relative_component(&at_position.text, at_position.range, path)
+        }
         JsCompletionType::Definition => completion_for_component(
             state,
             &at_position.text,
@@ -50,76 +150,427 @@ fn js_completion_handler(
     }
 }
 
+fn is_relative_component(text: &str) -> bool {
+    let text = text.strip_prefix("text!").unwrap_or(text);
+    text.starts_with("./") || text.starts_with("../")
+}
+
+fn completion_for_relative_component(
+    text: &str,
+    range: Range,
+    path: &PathBuf,
+) -> Option<Vec<CompletionItem>> {
+    let text_plugin = text.starts_with("text!");
+    let text = text.strip_prefix("text!").unwrap_or(text);
+    let prefix = &text[..=text.rfind('/')?];
+    let dir = path.parent()?.join(prefix);
+    let mut files = vec![];
+    for ext in ["js", "html"] {
+        // templates are loaded with `text!` plugin, scripts without it
+        if text_plugin != (ext == "html") {
+            continue;
+        }
+        let glob_path = dir.append(&[&format!("*.{}", ext)]);
+        for file in glob::glob(glob_path.to_path_str())
+            .ok()?
+            .filter_map(Result::ok)
+        {
+            if &file == path {
+                continue;
+            }
+            let name = file.file_name()?.to_str()?;
+            files.push(match ext {
+                // requirejs adds `.js` extension by itself
+                "js" => prefix.to_string() + name.trim_end_matches(".js"),
+                _ => format!("text!{prefix}{name}"),
+            });
+        }
+    }
+    Some(string_vec_and_range_to_completion_list(
+        files,
+        range,
+        CompletionItemKind::FILE,
+    ))
+}
+
 fn xml_completion_handler(
     state: &State,
     path: &PathBuf,
     pos: Position,
 ) -> Option<Vec<CompletionItem>> {
-    let at_position = xml::get_current_position_path(state.get_file(path)?, pos)?;
+    let content = state.get_file_or_read(path)?;
+    let tree = state.get_tree(path, &content)?;
+    let at_position =
+        xml::get_current_position_path(&tree, &content, pos, state.position_encoding())?;
+    let scope = module_scope(state, path);
+    let scope = scope.as_deref();
     match at_position {
-        x if x.match_path("[@template]") => {
-            completion_for_template(state, &x.text, x.range, &path.get_area())
+        x if x.match_path("[@template]") => completion_for_template(
+            state,
+            &x.text,
+            x.range,
+            &path.get_area(),
+            TemplateKind::Phtml,
+        ),
+        x if x.attribute_eq("xsi:type", "string")
+            && x.attribute_in("name", &["template", "templateFile", "defaultTemplate"]) =>
+        {
+            completion_for_template(
+                state,
+                &x.text,
+                x.range,
+                &path.get_area(),
+                TemplateKind::from_path(path),
+            )
         }
-        x if x.attribute_eq("xsi:type", "string") && x.attribute_eq("name", "template") => {
-            completion_for_template(state, &x.text, x.range, &path.get_area())
+        x if x.attribute_eq("xsi:type", "string") && x.attribute_eq("name", "elementTmpl") => {
+            completion_for_template(
+                state,
+                &x.text,
+                x.range,
+                &path.get_area(),
+                TemplateKind::Html,
+            )
         }
         x if x.attribute_eq("xsi:type", "string") && x.attribute_eq("name", "component") => {
             completion_for_component(state, &x.text, x.range, &path.get_area())
         }
         x if x.match_path("/config/event[@name]") && path.ends_with("events.xml") => {
-            Some(events::get_completion_items(x.range))
+            Some(events::get_completion_items(state, x.range))
+        }
+        x if x.match_path("/config/type[@name]") && path.ends_with("cache.xml") => {
+            Some(string_vec_and_range_to_completion_list(
+                state.get_cache_types(),
+                x.range,
+                CompletionItemKind::VALUE,
+            ))
+        }
+        // custom element types are given as class name
+        x if x.match_path("/group/field[@type]") && path.ends_with("system.xml") => {
+            if x.text.contains('\\') {
+                completion_for_classes(state, scope, &x.text, x.range)
+            } else {
+                let types = FIELD_TYPES.iter().map(ToString::to_string).collect();
+                Some(string_vec_and_range_to_completion_list(
+                    types,
+                    x.range,
+                    CompletionItemKind::VALUE,
+                ))
+            }
+        }
+        x if x.match_path("[@ifconfig]")
+            && (path.has_components(&["layout"]) || path.has_components(&["ui_component"])) =>
+        {
+            Some(string_vec_and_range_to_completion_list(
+                state.get_config_paths(),
+                x.range,
+                CompletionItemKind::VALUE,
+            ))
+        }
+        x if x.attribute_eq("xsi:type", "string")
+            && x.attribute_in("name", UI_CONFIG_PATH_ARGUMENTS)
+            && path.has_components(&["ui_component"]) =>
+        {
+            Some(string_vec_and_range_to_completion_list(
+                state.get_config_paths(),
+                x.range,
+                CompletionItemKind::VALUE,
+            ))
+        }
+        x if x.match_path("/resources/resource[@ref]") && path.ends_with("webapi.xml") => {
+            Some(string_vec_and_range_to_completion_list(
+                state.get_acl_resources(),
+                x.range,
+                CompletionItemKind::VALUE,
+            ))
+        }
+        x if x.match_path("/head/css[@src]") || x.match_path("/head/link[@src]") => {
+            // css is usually compiled from less source with the same name
+            completion_for_web_asset(state, &x.text, x.range, &path.get_area(), &["css", "less"])
+        }
+        x if x.match_path("/head/script[@src]") => {
+            completion_for_web_asset(state, &x.text, x.range, &path.get_area(), &["js"])
+        }
+        x if x.match_path("/theme/parent[$text]") && path.ends_with("theme.xml") => {
+            completion_for_theme_parent(state, x.range, &path.get_area())
+        }
+        x if x.match_path("/config/type/plugin[@name]") && path.ends_with("di.xml") => {
+            let type_name =
+                xml::get_parent_type_name(&tree, &content, pos, state.position_encoding())?;
+            let names = state.get_enabled_plugin_names(&path.get_area(), &type_name);
+            Some(string_vec_and_range_to_completion_list(
+                names,
+                x.range,
+                CompletionItemKind::VALUE,
+            ))
+        }
+        // only constructor params, argument tag can have `xsi:type="object"` as well
+        x if x.match_path("/config/type/arguments/argument[@name]") => {
+            let type_name =
+                xml::get_parent_type_name(&tree, &content, pos, state.position_encoding())?;
+            let class = definition::php::get_php_class_from_class_name(state, &type_name)?;
+            Some(string_vec_and_range_to_completion_list(
+                class.constructor_params,
+                x.range,
+                CompletionItemKind::VARIABLE,
+            ))
+        }
+        // `<service/>`, `<job/>`, `<observer/>` and alike
+        x if x.match_path("[@method]") => {
+            let class = x.attribute("class").or_else(|| x.attribute("instance"))?;
+            completion_for_methods(state, class, x.range)
         }
         x if x.match_path("/config/preference[@for]") && path.ends_with("di.xml") => {
-            completion_for_classes(state, &x.text, x.range)
+            completion_for_classes(state, scope, &x.text, x.range)
         }
         x if x.match_path("/config/preference[@type]") && path.ends_with("di.xml") => {
-            completion_for_classes(state, &x.text, x.range)
+            completion_for_classes(state, scope, &x.text, x.range)
         }
         x if x.match_path("/virtualType[@type]") && path.ends_with("di.xml") => {
-            completion_for_classes(state, &x.text, x.range)
+            completion_for_classes(state, scope, &x.text, x.range)
+        }
+        x if x.match_path("/dataProvider[@class]") && path.has_components(&["ui_component"]) => {
+            let mut result = completion_for_classes(state, scope, &x.text, x.range)?;
+            boost_data_provider_classes(&mut result);
+            Some(result)
+        }
+        // `ifconfig` takes config path, visibility condition is decided by a class
+        x if x.match_path("/visibilityCondition[@className]")
+            && path.has_components(&["layout"]) =>
+        {
+            completion_for_classes(state, scope, &x.text, x.range)
+        }
+        x if x.match_path("/observer[@instance]") && path.ends_with("events.xml") => {
+            completion_for_observer_classes(state, scope, &x.text, x.range)
         }
         x if x.match_path("[@class]") || x.match_path("[@instance]") => {
-            completion_for_classes(state, &x.text, x.range)
+            completion_for_classes(state, scope, &x.text, x.range)
+        }
+        x if x.attribute_eq("xsi:type", "object") && path.ends_with("di.xml") => {
+            let mut result =
+                completion_for_classes(state, scope, &x.text, x.range).unwrap_or_default();
+            result.extend(completion_for_proxy_classes(state, scope, &x.text, x.range));
+            let existing = result
+                .iter()
+                .map(|item| item.label.clone())
+                .collect::<Vec<_>>();
+            let mut types = state.get_virtual_types();
+            types.retain(|name| !existing.contains(name));
+            result.extend(string_vec_and_range_to_completion_list(
+                types,
+                x.range,
+                CompletionItemKind::CLASS,
+            ));
+            Some(result)
         }
         x if x.attribute_in("xsi:type", &["object", "const", "init_parameter"]) => {
-            completion_for_classes(state, &x.text, x.range)
+            completion_for_classes(state, scope, &x.text, x.range)
+        }
+        x if x.match_path("/type[@name]") => {
+            let mut result = completion_for_classes(state, scope, &x.text, x.range)?;
+            if path.ends_with("di.xml") {
+                let existing = result
+                    .iter()
+                    .map(|item| item.label.clone())
+                    .collect::<Vec<_>>();
+                let mut types = state.get_di_types();
+                types.retain(|name| !existing.contains(name));
+                result.extend(string_vec_and_range_to_completion_list(
+                    types,
+                    x.range,
+                    CompletionItemKind::CLASS,
+                ));
+            }
+            Some(result)
         }
-        x if x.match_path("/type[@name]") => completion_for_classes(state, &x.text, x.range),
         // Should be /source_model[$text], but html parser dont like undersores
         x if x.match_path("/source[$text]") && x.attribute_eq("_model", "") => {
-            completion_for_classes(state, &x.text, x.range)
+            completion_for_classes(state, scope, &x.text, x.range)
         }
         // Should be /backend_model[$text], but html parser dont like undersores
         x if x.match_path("/backend[$text]") && x.attribute_eq("_model", "") => {
-            completion_for_classes(state, &x.text, x.range)
+            completion_for_classes(state, scope, &x.text, x.range)
         }
         // Should be /frontend_model[$text], but html parser dont like undersores
         x if x.match_path("/frontend[$text]") && x.attribute_eq("_model", "") => {
-            completion_for_classes(state, &x.text, x.range)
+            completion_for_classes(state, scope, &x.text, x.range)
         }
         _ => None,
     }
 }
 
-fn completion_for_classes(state: &State, text: &str, range: Range) -> Option<Vec<CompletionItem>> {
+// only classes of the module owning the file, when user asked for it
+fn module_scope(state: &State, path: &Path) -> Option<String> {
+    if state.options().scope_completion_to_module {
+        state.get_module_class_prefix_for_path(path)
+    } else {
+        None
+    }
+}
+
+fn completion_for_classes(
+    state: &State,
+    scope: Option<&str>,
+    text: &str,
+    range: Range,
+) -> Option<Vec<CompletionItem>> {
     let text = text.trim_start_matches('\\');
+    if let Some((class, _)) = text.split_once("::") {
+        return completion_for_constants(state, class, range);
+    }
     if text.is_empty() || (m2::is_part_of_class_name(text) && text.matches('\\').count() == 0) {
-        Some(completion_for_classes_prefix(state, range))
+        Some(completion_for_classes_prefix(state, scope, range))
     } else if text.matches('\\').count() >= 1 {
-        let mut result = completion_for_classes_prefix(state, range);
-        result.extend(completion_for_classes_full(state, text, range));
+        let mut result = completion_for_classes_prefix(state, scope, range);
+        result.extend(completion_for_classes_full(
+            state,
+            scope,
+            text,
+            range,
+            |_, _| true,
+        ));
         Some(result)
     } else {
         None
     }
 }
 
-fn completion_for_classes_prefix(state: &State, range: Range) -> Vec<CompletionItem> {
-    let module_prefixes = state.get_module_class_prefixes();
-    string_vec_and_range_to_completion_list(module_prefixes, range)
+fn completion_for_constants(
+    state: &State,
+    class: &str,
+    range: Range,
+) -> Option<Vec<CompletionItem>> {
+    let phpclass = definition::php::get_php_class_from_class_name(state, class)?;
+    let mut constants = phpclass.constants.into_keys().collect::<Vec<_>>();
+    constants.sort_unstable();
+    Some(
+        constants
+            .into_iter()
+            .map(|name| {
+                // whole `Class::CONST` is replaced, so it has to be matched against it as well
+                let new_text = format!("{class}::{name}");
+                CompletionItem {
+                    label: name,
+                    filter_text: Some(new_text.clone()),
+                    text_edit: Some(CompletionTextEdit::Edit(TextEdit { range, new_text })),
+                    kind: Some(CompletionItemKind::CONSTANT),
+                    ..CompletionItem::default()
+                }
+            })
+            .collect(),
+    )
+}
+
+fn completion_for_observer_classes(
+    state: &State,
+    scope: Option<&str>,
+    text: &str,
+    range: Range,
+) -> Option<Vec<CompletionItem>> {
+    let text = text.trim_start_matches('\\');
+    if text.matches('\\').count() == 0 {
+        return completion_for_classes(state, scope, text, range);
+    }
+    let observers = completion_for_classes_full(state, scope, text, range, |state, summary| {
+        summary.is_some_and(|summary| is_descendant_of(state, summary, OBSERVER_INTERFACE))
+    });
+    if observers.is_empty() {
+        // nothing implements the interface yet, do not hide classes then
+        return completion_for_classes(state, scope, text, range);
+    }
+    let mut result = completion_for_classes_prefix(state, scope, range);
+    result.extend(observers);
+    Some(result)
+}
+
+// interface can be implemented by any class up the parent chain
+fn is_descendant_of(state: &State, summary: &ClassSummary, class: &str) -> bool {
+    let mut visited = HashSet::new();
+    let mut queue: VecDeque<String> = summary.parents.iter().cloned().collect();
+    while let Some(parent) = queue.pop_front() {
+        if parent == class {
+            return true;
+        }
+        if !visited.insert(parent.clone()) {
+            continue;
+        }
+        let parent_summary = definition::php::find_class_files(state, &parent)
+            .first()
+            .and_then(|path| state.get_class_summary(path));
+        if let Some(parent_summary) = parent_summary {
+            queue.extend(parent_summary.parents);
+        }
+    }
+    false
 }
 
-fn completion_for_classes_full(state: &State, text: &str, range: Range) -> Vec<CompletionItem> {
+fn completion_for_proxy_classes(
+    state: &State,
+    scope: Option<&str>,
+    text: &str,
+    range: Range,
+) -> Vec<CompletionItem> {
+    let text = text.trim_start_matches('\\');
+    if !text.contains('\\') {
+        return vec![];
+    }
+    let proxies = completion_for_classes_full(state, scope, text, range, |_, _| true)
+        .into_iter()
+        .map(|item| item.label + "\\Proxy")
+        .collect();
+    let mut result =
+        string_vec_and_range_to_completion_list(proxies, range, CompletionItemKind::CLASS);
+    // proxies after the classes they wrap
+    for item in &mut result {
+        item.sort_text = Some(format!("2{}", item.label));
+    }
+    result
+}
+
+fn boost_data_provider_classes(items: &mut [CompletionItem]) {
+    for item in items {
+        let rank = if item.label.contains("\\Ui\\DataProvider") {
+            0
+        } else {
+            1
+        };
+        item.sort_text = Some(format!("{}{}", rank, item.label));
+    }
+}
+
+fn completion_for_classes_prefix(
+    state: &State,
+    scope: Option<&str>,
+    range: Range,
+) -> Vec<CompletionItem> {
+    let mut module_prefixes = state.get_module_class_prefixes();
+    if let Some(scope) = scope {
+        module_prefixes.retain(|prefix| prefix == scope);
+    }
+    string_vec_and_range_to_completion_list(module_prefixes, range, CompletionItemKind::MODULE)
+}
+
+fn module_class_names(prefix: &str, module_path: &PathBuf) -> Vec<String> {
+    let mut classes = vec![];
+    let candidates = glob(module_path.append(&["**", "*.php"]).to_path_str())
+        .expect("Failed to read glob pattern");
+    for path in candidates.filter_map(Result::ok) {
+        let rel_path = path.relative_to(module_path).str_components().join("\\");
+        let class = format!("{}\\{}", prefix, rel_path.trim_end_matches(".php"));
+        if !class.ends_with("\\registration") {
+            classes.push(class);
+        }
+    }
+    classes
+}
+
+fn completion_for_classes_full(
+    state: &State,
+    scope: Option<&str>,
+    text: &str,
+    range: Range,
+    filter: fn(&State, Option<&ClassSummary>) -> bool,
+) -> Vec<CompletionItem> {
     let mut classes = vec![];
     let mut index = 0;
     let splits: Vec<usize> = text
@@ -134,31 +585,109 @@ fn completion_for_classes_full(state: &State, text: &str, range: Range) -> Vec<C
         })
         .collect();
 
+    let mut api_classes = HashSet::new();
+    let ignore_case = state.options().case_insensitive_completion;
     for spllit in splits {
-        let prefix = &text[..spllit - 1];
-        if let Some(module_path) = state.get_module_path(prefix) {
-            let candidates = glob(module_path.append(&["**", "*.php"]).to_path_str())
-                .expect("Failed to read glob pattern");
-            for p in candidates {
-                let path = p.map_or_else(|_| std::path::PathBuf::new(), |p| p);
-                let rel_path = path.relative_to(&module_path).str_components().join("\\");
-                let class_suffix = rel_path.trim_end_matches(".php");
-                let class = format!("{}\\{}", prefix, class_suffix);
-
-                if class.ends_with("\\registration") {
+        if let Some((prefix, module_path)) = get_module_path(state, &text[..spllit - 1]) {
+            if scope.is_some_and(|scope| scope != prefix) {
+                continue;
+            }
+            let module_classes =
+                state.get_completion_candidates(&M2Area::Base, &format!("{}\\", prefix), || {
+                    module_class_names(&prefix, &module_path)
+                        .into_iter()
+                        .map(CompletionCandidate::from)
+                        .collect()
+                });
+            for CompletionCandidate { label: class, .. } in module_classes {
+                if !starts_with(&class, &text[..index - 1], ignore_case) {
                     continue;
                 }
 
-                if !class.starts_with(&text[..index - 1]) {
+                let class_suffix = class[prefix.len() + 1..].split('\\').collect::<Vec<_>>();
+                let path = module_path.append(&class_suffix).append_ext("php");
+                let summary = state.get_class_summary(&path);
+                if !filter(state, summary.as_ref()) {
                     continue;
                 }
-
+                if summary.is_some_and(|summary| summary.api) {
+                    api_classes.insert(class.clone());
+                }
                 classes.push(class);
             }
         }
     }
 
-    string_vec_and_range_to_completion_list(classes, range)
+    let mut result =
+        string_vec_and_range_to_completion_list(classes, range, CompletionItemKind::CLASS);
+    // stable `@api` classes first
+    for item in &mut result {
+        let rank = if api_classes.contains(&item.label) {
+            0
+        } else {
+            1
+        };
+        item.sort_text = Some(format!("{}{}", rank, item.label));
+    }
+    result
+}
+
+fn module_template_files(
+    state: &State,
+    module_name: &str,
+    path: &PathBuf,
+    area: &M2Area,
+) -> Vec<CompletionCandidate> {
+    let mut view_paths = vec![];
+    for area_string in area.path_candidates() {
+        view_paths.push(path.append(&["view", area_string, "templates"]));
+    }
+    for theme_path in state.list_themes_paths(area) {
+        view_paths.push(theme_path.append(&[module_name, "templates"]));
+    }
+    view_paths.push(path.append(&[module_name, "templates"]));
+
+    let mut files = vec![];
+    for view_path in view_paths {
+        let glob_path = view_path.append(&["**", "*.phtml"]);
+        for file in glob(glob_path.to_path_str())
+            .into_iter()
+            .flatten()
+            .filter_map(Result::ok)
+        {
+            let path = file.relative_to(&view_path).str_components().join("/");
+            files.push(CompletionCandidate::new(
+                String::from(module_name) + "::" + &path,
+                &view_path,
+            ));
+        }
+    }
+    files
+}
+
+fn completion_for_methods(state: &State, class: &str, range: Range) -> Option<Vec<CompletionItem>> {
+    let class = class.trim_start_matches('\\');
+    let phpclass = definition::php::get_php_class_from_class_name(state, class)?;
+    let mut methods = phpclass
+        .methods
+        .into_keys()
+        .filter(|name| !name.starts_with("__"))
+        .collect::<Vec<_>>();
+    methods.sort_unstable();
+    Some(
+        methods
+            .into_iter()
+            .map(|name| CompletionItem {
+                label: name.clone(),
+                text_edit: Some(CompletionTextEdit::Edit(TextEdit {
+                    range,
+                    new_text: name,
+                })),
+                kind: Some(CompletionItemKind::METHOD),
+                ..CompletionItem::default()
+            })
+            .collect(),
+    )
 }
 
 fn completion_for_template(
@@ -166,42 +695,144 @@ fn completion_for_template(
     text: &str,
     range: Range,
     area: &M2Area,
+    kind: TemplateKind,
 ) -> Option<Vec<CompletionItem>> {
+    if kind == TemplateKind::Html {
+        return completion_for_html_template(state, text, range, area);
+    }
     if text.is_empty() || m2::is_part_of_module_name(text) {
         let modules = state.get_modules();
-        Some(string_vec_and_range_to_completion_list(modules, range))
+        Some(string_vec_and_range_to_completion_list(
+            modules,
+            range,
+            CompletionItemKind::MODULE,
+        ))
     } else if text.contains("::") {
         let module_name = text.split("::").next()?;
-        let path = state.get_module_path(module_name)?;
-        let mut theme_paths = state.list_themes_paths(&area);
-        theme_paths.push(&path);
+        if !m2::is_part_of_module_name(module_name) {
+            return None;
+        }
+        let (module_name, path) = get_module_path(state, module_name)?;
+        let files = state.get_completion_candidates(area, &format!("{}::", module_name), || {
+            module_template_files(state, &module_name, &path, area)
+        });
+        Some(candidates_to_completion_list(
+            state,
+            files,
+            range,
+            CompletionItemKind::FILE,
+        ))
+    } else {
+        None
+    }
+}
 
-        let mut files = vec![];
+fn completion_for_html_template(
+    state: &State,
+    text: &str,
+    range: Range,
+    area: &M2Area,
+) -> Option<Vec<CompletionItem>> {
+    if text.is_empty() || m2::is_part_of_module_name(text) {
+        let modules = state.get_modules();
+        Some(string_vec_and_range_to_completion_list(
+            modules,
+            range,
+            CompletionItemKind::MODULE,
+        ))
+    } else if text.contains('/') {
+        let module_name = text.split('/').next()?;
+        if !m2::is_part_of_module_name(module_name) {
+            return None;
+        }
+        let (module_name, path) = get_module_path(state, module_name)?;
+        let module_name = module_name.as_str();
+        let mut view_paths = vec![];
         for area_string in area.path_candidates() {
-            let view_path = path.append(&["view", area_string, "templates"]);
-            let glob_path = view_path.append(&["**", "*.phtml"]);
-            files.extend(glob::glob(glob_path.to_path_str()).ok()?.map(|file| {
-                let path = file
-                    .unwrap_or_default()
-                    .relative_to(&view_path)
-                    .str_components()
-                    .join("/");
-                String::from(module_name) + "::" + &path
-            }));
+            view_paths.push(path.append(&["view", area_string, "web", "template"]));
         }
-        for theme_path in theme_paths {
-            let view_path = theme_path.append(&[module_name, "templates"]);
-            let glob_path = view_path.append(&["**", "*.phtml"]);
+        for theme_path in state.list_themes_paths(area) {
+            view_paths.push(theme_path.append(&[module_name, "web", "template"]));
+        }
+
+        let mut files = vec![];
+        for view_path in view_paths {
+            let glob_path = view_path.append(&["**", "*.html"]);
             files.extend(glob::glob(glob_path.to_path_str()).ok()?.map(|file| {
                 let path = file
                     .unwrap_or_default()
                     .relative_to(&view_path)
                     .str_components()
                     .join("/");
-                String::from(module_name) + "::" + &path
+                let label = String::from(module_name) + "/" + path.trim_end_matches(".html");
+                CompletionCandidate::new(label, &view_path)
             }));
         }
-        Some(string_vec_and_range_to_completion_list(files, range))
+        Some(candidates_to_completion_list(
+            state,
+            files,
+            range,
+            CompletionItemKind::FILE,
+        ))
+    } else {
+        None
+    }
+}
+
+fn completion_for_web_asset(
+    state: &State,
+    text: &str,
+    range: Range,
+    area: &M2Area,
+    extensions: &[&str],
+) -> Option<Vec<CompletionItem>> {
+    if text.is_empty() || m2::is_part_of_module_name(text) {
+        let modules = state.get_modules();
+        Some(string_vec_and_range_to_completion_list(
+            modules,
+            range,
+            CompletionItemKind::MODULE,
+        ))
+    } else if text.contains("::") {
+        let module_name = text.split("::").next()?;
+        if !m2::is_part_of_module_name(module_name) {
+            return None;
+        }
+        let (module_name, path) = get_module_path(state, module_name)?;
+        let module_name = module_name.as_str();
+        let mut view_paths = vec![];
+        for area_string in area.path_candidates() {
+            view_paths.push(path.append(&["view", area_string, "web"]));
+        }
+        for theme_path in state.list_themes_paths(area) {
+            view_paths.push(theme_path.append(&[module_name, "web"]));
+        }
+
+        let mut files = vec![];
+        for view_path in view_paths {
+            // source and compiled file (less and css) with the same name are listed once
+            let mut labels = HashSet::new();
+            for ext in extensions {
+                let glob_path = view_path.append(&["**", &format!("*.{}", ext)]);
+                for file in glob::glob(glob_path.to_path_str())
+                    .ok()?
+                    .filter_map(Result::ok)
+                {
+                    let path = file.relative_to(&view_path).str_components().join("/");
+                    let path = path.strip_suffix(ext).unwrap_or(&path).to_string() + extensions[0];
+                    let label = String::from(module_name) + "::" + &path;
+                    if labels.insert(label.clone()) {
+                        files.push(CompletionCandidate::new(label, &view_path));
+                    }
+                }
+            }
+        }
+        Some(candidates_to_completion_list(
+            state,
+            files,
+            range,
+            CompletionItemKind::FILE,
+        ))
     } else {
         None
     }
@@ -216,7 +847,8 @@ fn completion_for_component(
     if text.contains('/') {
         let module_name = text.split('/').next()?;
         let mut files = vec![];
-        if let Some(path) = state.get_module_path(module_name) {
+        if let Some((module_name, path)) = get_module_path(state, module_name) {
+            let module_name = module_name.as_str();
             for area in area.path_candidates() {
                 let view_path = path.append(&["view", area, "web"]);
                 let glob_path = view_path.append(&["**", "*.js"]);
@@ -227,7 +859,7 @@ fn completion_for_component(
                         .str_components()
                         .join("/");
                     let path = path.trim_end_matches(".js");
-                    String::from(module_name) + "/" + path
+                    CompletionCandidate::new(String::from(module_name) + "/" + path, &view_path)
                 }));
             }
         }
@@ -241,22 +873,34 @@ fn completion_for_component(
                     .relative_to(&view_path)
                     .str_components()
                     .join("/");
-                path.trim_end_matches(".js").to_string()
+                CompletionCandidate::new(path.trim_end_matches(".js").into(), &view_path)
             }));
         }
 
-        files.extend(state.get_component_maps_for_area(area));
-        if let Some(lower_area) = area.lower_area() {
-            files.extend(state.get_component_maps_for_area(&lower_area));
-        }
-        Some(string_vec_and_range_to_completion_list(files, range))
+        files.extend(
+            component_maps_for_area(state, area)
+                .into_iter()
+                .map(CompletionCandidate::from),
+        );
+        Some(candidates_to_completion_list(
+            state,
+            files,
+            range,
+            CompletionItemKind::FILE,
+        ))
     } else {
         let mut modules = vec![];
-        modules.extend(state.get_modules());
-        modules.extend(state.get_component_maps_for_area(area));
-        if let Some(lower_area) = area.lower_area() {
-            modules.extend(state.get_component_maps_for_area(&lower_area));
-        }
+        modules.extend(
+            state
+                .get_modules()
+                .into_iter()
+                .map(CompletionCandidate::from),
+        );
+        modules.extend(
+            component_maps_for_area(state, area)
+                .into_iter()
+                .map(CompletionCandidate::from),
+        );
         let workspaces = state.workspace_paths();
         for path in workspaces {
             let view_path = path.append(&["lib", "web"]);
@@ -267,16 +911,108 @@ fn completion_for_component(
                     .relative_to(&view_path)
                     .str_components()
                     .join("/");
-                path.trim_end_matches(".js").to_string()
+                CompletionCandidate::new(path.trim_end_matches(".js").into(), &view_path)
             }));
         }
-        Some(string_vec_and_range_to_completion_list(modules, range))
+        Some(candidates_to_completion_list(
+            state,
+            modules,
+            range,
+            CompletionItemKind::FILE,
+        ))
+    }
+}
+
+fn completion_for_theme_parent(
+    state: &State,
+    range: Range,
+    area: &M2Area,
+) -> Option<Vec<CompletionItem>> {
+    // themes are registered as area/Vendor/theme, parent skips the area part
+    let themes = state
+        .list_themes_names(area)
+        .iter()
+        .filter_map(|name| name.split_once('/').map(|(_, theme)| theme.to_string()))
+        .collect();
+    Some(string_vec_and_range_to_completion_list(
+        themes,
+        range,
+        CompletionItemKind::MODULE,
+    ))
+}
+
+// typed module name can differ in case from canonical one, if enabled in options
+fn get_module_path(state: &State, module_name: &str) -> Option<(String, PathBuf)> {
+    if state.options().case_insensitive_completion {
+        state.get_module_path_ignore_case(module_name)
+    } else {
+        Some((module_name.into(), state.get_module_path(module_name)?))
+    }
+}
+
+fn starts_with(candidate: &str, typed: &str, ignore_case: bool) -> bool {
+    if ignore_case {
+        candidate
+            .get(..typed.len())
+            .is_some_and(|start| start.eq_ignore_ascii_case(typed))
+    } else {
+        candidate.starts_with(typed)
     }
 }
 
+// same fallback as maps resolution for go to definition
+fn component_maps_for_area(state: &State, area: &M2Area) -> Vec<String> {
+    let mut maps = state.get_component_maps_for_area(area);
+    let mut current_area = area.clone();
+    while let Some(lower_area) = current_area.lower_area() {
+        maps.extend(state.get_component_maps_for_area(&lower_area));
+        current_area = lower_area;
+    }
+    maps
+}
+
+// same label can come from module and theme overriding it, detail tells them apart
+fn candidates_to_completion_list(
+    state: &State,
+    mut candidates: Vec<CompletionCandidate>,
+    range: Range,
+    kind: CompletionItemKind,
+) -> Vec<CompletionItem> {
+    candidates.sort_unstable();
+    candidates.dedup();
+    let workspaces = state.workspace_paths();
+    candidates
+        .into_iter()
+        .map(|candidate| CompletionItem {
+            text_edit: Some(CompletionTextEdit::Edit(TextEdit {
+                range,
+                new_text: candidate.label.clone(),
+            })),
+            detail: candidate
+                .source
+                .map(|source| source_detail(&workspaces, &source)),
+            label: candidate.label,
+            kind: Some(kind),
+            ..CompletionItem::default()
+        })
+        .collect()
+}
+
+// directory relative to workspace it is in
+fn source_detail(workspaces: &[PathBuf], source: &Path) -> String {
+    workspaces
+        .iter()
+        .find_map(|workspace| source.strip_prefix(workspace).ok())
+        .map_or_else(
+            || source.display().to_string(),
+            |rel_path| rel_path.to_path_buf().str_components().join("/"),
+        )
+}
+
 fn string_vec_and_range_to_completion_list(
     mut strings: Vec<String>,
     range: Range,
+    kind: CompletionItemKind,
 ) -> Vec<CompletionItem> {
     strings.sort_unstable();
     strings.dedup();
@@ -289,9 +1025,1053 @@ fn string_vec_and_range_to_completion_list(
                 new_text: label.clone(),
             })),
             label_details: None,
-            kind: Some(CompletionItemKind::FILE),
+            kind: Some(kind),
             detail: None,
             ..CompletionItem::default()
         })
         .collect()
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::{fixture_path, module_path, split_cursor, state_with_module, TempDir};
+
+    fn get_test_completion(state: &mut State, xml: &str, path: &str) -> Vec<String> {
+        let (content, pos) = split_cursor(xml);
+        let path = PathBuf::from(path);
+        state.set_file(&path, content);
+        xml_completion_handler(state, &path, pos)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|item| item.label)
+            .collect()
+    }
+
+    #[test]
+    fn test_completion_when_file_not_opened() {
+        let module_path = module_path(&[]);
+        let mut state = State::new();
+        state
+            .add_module("Some_Module")
+            .add_module_path("Some\\Module", module_path.clone());
+        let path = module_path.append(&["etc", "di.xml"]);
+
+        let result = xml_completion_handler(
+            &state,
+            &path,
+            Position {
+                line: 2,
+                character: 33,
+            },
+        )
+        .expect("Completion should read file from disk");
+
+        assert!(result.iter().any(|item| item.label == "Some\\Module"));
+        assert!(result.iter().any(|item| item.label == "Some\\Module\\Test"));
+    }
+
+    #[test]
+    fn test_completion_when_file_does_not_exist() {
+        let state = State::new();
+        let path = module_path(&["etc", "missing.xml"]);
+
+        let result = xml_completion_handler(&state, &path, Position::default());
+
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn test_completion_for_cache_type_name() {
+        let mut state = State::new();
+        state.add_cache_type("full_page", "Magento\\PageCache\\Model\\Cache\\Type");
+        state.add_cache_type("config", "Magento\\Framework\\App\\Cache\\Type\\Config");
+
+        let labels = get_test_completion(
+            &mut state,
+            r#"<?xml version="1.0"?>
+            <config>
+                <type name="|"/>
+            </config>
+            "#,
+            "/a/b/c/etc/cache.xml",
+        );
+
+        assert_eq!(labels, vec!["config", "full_page"]);
+    }
+
+    #[test]
+    fn test_completion_for_template_with_placeholder() {
+        let mut state = state_with_module();
+
+        let labels = get_test_completion(
+            &mut state,
+            r#"<?xml version="1.0"?>
+            <page>
+                <block template="{{module}}::|"/>
+            </page>
+            "#,
+            "/a/b/c/view/frontend/layout/default.xml",
+        );
+
+        assert!(labels.is_empty());
+    }
+
+    #[test]
+    fn test_completion_for_html_template_in_ui_component() {
+        let mut state = state_with_module();
+
+        let labels = get_test_completion(
+            &mut state,
+            r#"<?xml version="1.0"?>
+            <listing>
+                <argument name="data" xsi:type="array">
+                    <item name="template" xsi:type="string">Some_Module/|</item>
+                </argument>
+            </listing>
+            "#,
+            "/a/b/c/view/adminhtml/ui_component/some_listing.xml",
+        );
+
+        assert_eq!(labels, vec!["Some_Module/grid/listing"]);
+    }
+
+    #[test]
+    fn test_completion_for_phtml_template_outside_ui_component() {
+        let mut state = state_with_module();
+
+        let labels = get_test_completion(
+            &mut state,
+            r#"<?xml version="1.0"?>
+            <page>
+                <block>
+                    <arguments>
+                        <argument name="template" xsi:type="string">Some_Module::|</argument>
+                    </arguments>
+                </block>
+            </page>
+            "#,
+            "/a/b/c/view/adminhtml/layout/default.xml",
+        );
+
+        assert_eq!(labels, vec!["Some_Module::admin.phtml"]);
+    }
+
+    #[test]
+    fn test_completion_for_template_file_and_default_template_arguments() {
+        let mut state = state_with_module();
+
+        for name in ["templateFile", "defaultTemplate"] {
+            let labels = get_test_completion(
+                &mut state,
+                &format!(
+                    r#"<?xml version="1.0"?>
+                    <page>
+                        <block>
+                            <arguments>
+                                <argument name="{name}" xsi:type="string">Some_Module::|</argument>
+                            </arguments>
+                        </block>
+                    </page>
+                    "#
+                ),
+                "/a/b/c/view/adminhtml/layout/default.xml",
+            );
+
+            assert_eq!(labels, vec!["Some_Module::admin.phtml"], "{name}");
+        }
+    }
+
+    #[test]
+    fn test_completion_for_component_includes_map_alias() {
+        let mut state = State::new();
+        state.add_module("Some_Module");
+        state.add_component_map("someAlias", "Some_Module/js/component", &M2Area::Base);
+        state.add_component_map("adminAlias", "Some_Module/js/admin", &M2Area::Adminhtml);
+        let path = PathBuf::from("/a/b/c/view/frontend/web/js/some.js");
+        state.set_file(&path, "define(['some'], function (some) {})");
+
+        let labels: Vec<String> = js_completion_handler(
+            &state,
+            &path,
+            Position {
+                line: 0,
+                character: 12,
+            },
+        )
+        .unwrap_or_default()
+        .into_iter()
+        .map(|item| item.label)
+        .collect();
+
+        assert_eq!(labels, vec!["Some_Module", "someAlias"]);
+    }
+
+    #[test]
+    fn test_completion_for_theme_parent() {
+        let mut state = State::new();
+        state.add_front_theme_path("frontend/Magento/luma", PathBuf::from("/a/luma"));
+        state.add_front_theme_path("frontend/Magento/blank", PathBuf::from("/a/blank"));
+        state.add_admin_theme_path("adminhtml/Magento/backend", PathBuf::from("/a/backend"));
+
+        let labels = get_test_completion(
+            &mut state,
+            r#"<?xml version="1.0"?>
+            <theme xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
+                <title>Some Theme</title>
+                <parent>Magento/|</parent>
+            </theme>
+            "#,
+            "/a/b/app/design/frontend/Some/theme/theme.xml",
+        );
+
+        assert_eq!(labels, vec!["Magento/blank", "Magento/luma"]);
+    }
+
+    #[test]
+    fn test_completion_for_ifconfig_in_layout() {
+        let mut state = State::new();
+        let location = lsp_types::Location {
+            uri: lsp_types::Url::from_file_path("/a/b/c/etc/adminhtml/system.xml").unwrap(),
+            range: Range::default(),
+        };
+        state.add_config_path("some_section/general/enabled", location.clone());
+        state.add_config_path("some_section/general/debug", location);
+
+        let labels = get_test_completion(
+            &mut state,
+            r#"<?xml version="1.0"?>
+            <page>
+                <body>
+                    <block class="Some\Block" name="some.block" ifconfig="some_|"/>
+                </body>
+            </page>
+            "#,
+            "/a/b/c/view/frontend/layout/default.xml",
+        );
+
+        assert_eq!(
+            labels,
+            vec!["some_section/general/debug", "some_section/general/enabled"]
+        );
+    }
+
+    #[test]
+    fn test_completion_for_config_path_in_ui_component() {
+        let mut state = State::new();
+        let location = lsp_types::Location {
+            uri: lsp_types::Url::from_file_path("/a/b/c/etc/adminhtml/system.xml").unwrap(),
+            range: Range::default(),
+        };
+        state.add_config_path("some_section/general/enabled", location.clone());
+        state.add_config_path("some_section/general/debug", location);
+
+        let labels = get_test_completion(
+            &mut state,
+            r#"<?xml version="1.0"?>
+            <listing>
+                <columns name="some_columns">
+                    <column name="some_column">
+                        <argument name="data" xsi:type="array">
+                            <item name="config" xsi:type="array">
+                                <item name="ifconfig" xsi:type="string">some_|</item>
+                            </item>
+                        </argument>
+                    </column>
+                </columns>
+            </listing>
+            "#,
+            "/a/b/c/view/adminhtml/ui_component/some_listing.xml",
+        );
+
+        assert_eq!(
+            labels,
+            vec!["some_section/general/debug", "some_section/general/enabled"]
+        );
+    }
+
+    #[test]
+    fn test_completion_for_resource_ref_in_webapi() {
+        let mut state = State::new();
+        let location = lsp_types::Location {
+            uri: lsp_types::Url::from_file_path("/a/b/c/etc/acl.xml").unwrap(),
+            range: Range::default(),
+        };
+        state.add_acl_resource("Some_Module::manage", location.clone());
+        state.add_acl_resource("Magento_Backend::admin", location);
+
+        let labels = get_test_completion(
+            &mut state,
+            r#"<?xml version="1.0"?>
+            <routes>
+                <route url="/V1/some" method="GET">
+                    <resources>
+                        <resource ref="Some_|"/>
+                    </resources>
+                </route>
+            </routes>
+            "#,
+            "/a/b/c/etc/webapi.xml",
+        );
+
+        assert_eq!(
+            labels,
+            vec!["Magento_Backend::admin", "Some_Module::manage"]
+        );
+    }
+
+    #[test]
+    fn test_completion_for_service_method_in_webapi() {
+        let mut state = state_with_module();
+
+        let labels = get_test_completion(
+            &mut state,
+            r#"<?xml version="1.0"?>
+            <routes>
+                <route url="/V1/some" method="GET">
+                    <service class="\Some\Module\Api\TestInterface" method="|"/>
+                </route>
+            </routes>
+            "#,
+            "/a/b/c/etc/webapi.xml",
+        );
+
+        assert_eq!(labels, vec!["getBar", "getFoo"]);
+    }
+
+    #[test]
+    fn test_completion_for_job_method_from_instance() {
+        let mut state = state_with_module();
+        let xml = r#"<?xml version="1.0"?>
+            <config>
+                <group id="default">
+                    <job name="some_job" instance="Some\Module\Model\Config" method="get|"/>
+                </group>
+            </config>
+            "#;
+        let (content, pos) = split_cursor(xml);
+        let path = PathBuf::from("/a/b/c/etc/crontab.xml");
+        state.set_file(&path, content);
+
+        let items = xml_completion_handler(&state, &path, pos).unwrap_or_default();
+
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].label, "getParam");
+        assert_eq!(items[0].kind, Some(CompletionItemKind::METHOD));
+    }
+
+    #[test]
+    fn test_completion_for_method_with_unknown_class() {
+        let labels = get_test_completion(
+            &mut State::new(),
+            r#"<?xml version="1.0"?><service class="Unknown\Model" method="|"/>"#,
+            "/a/b/c/etc/webapi.xml",
+        );
+
+        assert!(labels.is_empty());
+    }
+
+    #[test]
+    fn test_completion_for_const_argument_after_class() {
+        let mut state = state_with_module();
+        let xml = r#"<?xml version="1.0"?>
+            <config>
+                <type name="Some\Module\Block\Widget">
+                    <arguments>
+                        <argument name="limit" xsi:type="const">\Some\Module\Model\Config::|</argument>
+                    </arguments>
+                </type>
+            </config>
+            "#;
+        let (content, pos) = split_cursor(xml);
+        let path = PathBuf::from("/a/b/c/etc/di.xml");
+        state.set_file(&path, content);
+
+        let items = xml_completion_handler(&state, &path, pos).unwrap_or_default();
+
+        let labels = items.iter().map(|i| i.label.as_str()).collect::<Vec<_>>();
+        assert_eq!(labels, vec!["LIMIT", "PARAM"]);
+        assert_eq!(items[0].kind, Some(CompletionItemKind::CONSTANT));
+        let Some(CompletionTextEdit::Edit(edit)) = &items[0].text_edit else {
+            panic!("Constant should have text edit");
+        };
+        assert_eq!(edit.new_text, "Some\\Module\\Model\\Config::LIMIT");
+    }
+
+    #[test]
+    fn test_completion_for_const_argument_with_unknown_class() {
+        let labels = get_test_completion(
+            &mut State::new(),
+            r#"<?xml version="1.0"?>
+            <config>
+                <argument name="limit" xsi:type="const">Unknown\Model::|</argument>
+            </config>
+            "#,
+            "/a/b/c/etc/di.xml",
+        );
+
+        assert!(labels.is_empty());
+    }
+
+    #[test]
+    fn test_completion_for_system_field_type() {
+        let labels = get_test_completion(
+            &mut State::new(),
+            r#"<?xml version="1.0"?>
+            <config>
+                <system>
+                    <section id="some_section">
+                        <group id="general">
+                            <field id="enabled" type="|"/>
+                        </group>
+                    </section>
+                </system>
+            </config>
+            "#,
+            "/a/b/c/etc/adminhtml/system.xml",
+        );
+
+        assert_eq!(labels.len(), FIELD_TYPES.len());
+        for field_type in ["multiselect", "password", "select", "text", "textarea"] {
+            assert!(labels.contains(&field_type.to_string()), "{field_type}");
+        }
+    }
+
+    #[test]
+    fn test_completion_for_plugin_name_reflects_area() {
+        let mut state = State::new();
+        let type_name = "Some\\Module\\Model\\Service";
+        state.add_plugin(
+            &M2Area::Base,
+            type_name,
+            "some_plugin",
+            crate::state::DiPlugin {
+                class: Some("Some\\Module\\Plugin\\ServicePlugin".into()),
+                disabled: Some(true),
+            },
+        );
+        state.add_plugin(
+            &M2Area::Frontend,
+            type_name,
+            "some_plugin",
+            crate::state::DiPlugin {
+                class: None,
+                disabled: Some(false),
+            },
+        );
+        let xml = r#"<?xml version="1.0"?>
+            <config>
+                <type name="Some\Module\Model\Service">
+                    <plugin name="|"/>
+                </type>
+            </config>
+            "#;
+
+        let frontend = get_test_completion(&mut state, xml, "/a/b/c/etc/frontend/di.xml");
+        let adminhtml = get_test_completion(&mut state, xml, "/a/b/c/etc/adminhtml/di.xml");
+
+        assert_eq!(frontend, vec!["some_plugin"]);
+        assert!(adminhtml.is_empty());
+    }
+
+    #[test]
+    fn test_completion_for_type_name_includes_declared_types() {
+        let module_path = module_path(&[]);
+        let mut state = State::new();
+        state
+            .add_module("Some_Module")
+            .add_module_path("Some\\Module", module_path);
+        state.add_di_type("someVirtualType", "Some\\Module\\Model\\Config");
+
+        let labels = get_test_completion(
+            &mut state,
+            r#"<?xml version="1.0"?>
+            <config>
+                <type name="Some\Module\Model\|"/>
+            </config>
+            "#,
+            "/a/b/c/etc/di.xml",
+        );
+
+        assert!(labels.contains(&"Some\\Module\\Model\\Config".to_string()));
+        assert!(labels.contains(&"someVirtualType".to_string()));
+    }
+
+    #[test]
+    fn test_completion_for_event_name_includes_workspace_events() {
+        let mut state = State::new();
+        state.set_source_file(&PathBuf::from("/a/b/Other/etc/events.xml"));
+        state.add_event("some_custom_event");
+
+        let labels = get_test_completion(
+            &mut state,
+            r#"<?xml version="1.0"?>
+            <config>
+                <event name="|"/>
+            </config>
+            "#,
+            "/a/b/c/etc/events.xml",
+        );
+
+        assert!(labels.contains(&"some_custom_event".to_string()));
+        assert!(labels.contains(&"catalog_product_save_after".to_string()));
+    }
+
+    #[test]
+    fn test_completion_case_insensitive_module_name() {
+        let mut state = state_with_module();
+        let xml = r#"<?xml version="1.0"?>
+            <page>
+                <block template="some_module::|"/>
+            </page>
+            "#;
+        let path = "/a/b/c/view/adminhtml/layout/default.xml";
+
+        assert!(get_test_completion(&mut state, xml, path).is_empty());
+
+        state.set_options(crate::options::Options {
+            case_insensitive_completion: true,
+            ..Default::default()
+        });
+        assert_eq!(
+            get_test_completion(&mut state, xml, path),
+            vec!["Some_Module::admin.phtml"]
+        );
+    }
+
+    #[test]
+    fn test_completion_case_insensitive_class_name() {
+        let mut state = state_with_module();
+        state.set_options(crate::options::Options {
+            case_insensitive_completion: true,
+            ..Default::default()
+        });
+
+        let labels = get_test_completion(
+            &mut state,
+            r#"<?xml version="1.0"?>
+            <config>
+                <preference for="some\module\model\con|"/>
+            </config>
+            "#,
+            "/a/b/c/etc/di.xml",
+        );
+
+        assert!(labels.contains(&"Some\\Module\\Model\\Config".to_string()));
+    }
+
+    #[test]
+    fn test_completion_item_kind_per_result_type() {
+        let mut state = state_with_module();
+        let kinds = |state: &mut State, xml: &str| {
+            let (content, pos) = split_cursor(xml);
+            let path = PathBuf::from("/a/b/c/view/adminhtml/layout/default.xml");
+            state.set_file(&path, content);
+            xml_completion_handler(state, &path, pos)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|item| (item.label, item.kind))
+                .collect::<Vec<_>>()
+        };
+
+        let classes = kinds(&mut state, r#"<block class="Some\Module\Model\Con|"/>"#);
+        assert!(classes.contains(&("Some\\Module".into(), Some(CompletionItemKind::MODULE))));
+        assert!(classes.contains(&(
+            "Some\\Module\\Model\\Config".into(),
+            Some(CompletionItemKind::CLASS)
+        )));
+
+        let modules = kinds(&mut state, r#"<block template="|"/>"#);
+        assert_eq!(
+            modules,
+            vec![("Some_Module".into(), Some(CompletionItemKind::MODULE))]
+        );
+
+        let templates = kinds(&mut state, r#"<block template="Some_Module::|"/>"#);
+        assert!(!templates.is_empty());
+        assert!(templates
+            .iter()
+            .all(|(_, kind)| kind == &Some(CompletionItemKind::FILE)));
+    }
+
+    #[test]
+    fn test_completion_for_template_detail_tells_theme_override_apart() {
+        let mut state = State::new();
+        state.add_workspace_path(&fixture_path(&[]));
+        state.add_module_path("Some_Module", module_path(&[]));
+        state.add_admin_theme_path(
+            "adminhtml/Magento/backend",
+            fixture_path(&["vendor", "magento", "theme-adminhtml-backend"]),
+        );
+        let xml = r#"<block template="Some_Module::|"/>"#;
+        let (content, pos) = split_cursor(xml);
+        let path = PathBuf::from("/a/b/c/view/adminhtml/layout/default.xml");
+        state.set_file(&path, content);
+
+        let items = xml_completion_handler(&state, &path, pos)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|item| (item.label, item.detail.unwrap_or_default()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            items,
+            vec![
+                (
+                    "Some_Module::admin.phtml".into(),
+                    "app/code/Some/Module/view/adminhtml/templates".into()
+                ),
+                (
+                    "Some_Module::admin.phtml".into(),
+                    "vendor/magento/theme-adminhtml-backend/Some_Module/templates".into()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_completion_scoped_to_current_module() {
+        let mut state = State::new();
+        state
+            .add_module("Some_Module")
+            .add_module_path("Some\\Module", module_path(&[]));
+        state
+            .add_module("Other_Module")
+            .add_module_path("Other\\Module", module_path(&["Model"]));
+        state.set_options(crate::options::Options {
+            scope_completion_to_module: true,
+            ..Default::default()
+        });
+        let path = module_path(&["etc", "di.xml"]);
+        let path = path.to_str().unwrap();
+
+        let own = get_test_completion(
+            &mut state,
+            r#"<?xml version="1.0"?>
+            <config>
+                <preference for="Some\Module\Model\Con|"/>
+            </config>
+            "#,
+            path,
+        );
+        let other = get_test_completion(
+            &mut state,
+            r#"<?xml version="1.0"?>
+            <config>
+                <preference for="Other\Module\|"/>
+            </config>
+            "#,
+            path,
+        );
+
+        assert_eq!(own, vec!["Some\\Module", "Some\\Module\\Model\\Config"]);
+        assert_eq!(other, vec!["Some\\Module"]);
+    }
+
+    #[test]
+    fn test_completion_for_data_provider_class_ranks_data_providers_first() {
+        let mut state = state_with_module();
+        let xml = r#"<?xml version="1.0"?>
+            <listing>
+                <dataSource name="some_listing_data_source">
+                    <dataProvider class="Some\Module\|" name="some_listing_data_source"/>
+                </dataSource>
+            </listing>
+            "#;
+        let path = PathBuf::from("/a/b/c/view/adminhtml/ui_component/some_listing.xml");
+        let (content, pos) = split_cursor(xml);
+        state.set_file(&path, content);
+
+        let mut items = xml_completion_handler(&state, &path, pos).unwrap();
+        items.sort_by(|a, b| a.sort_text.cmp(&b.sort_text));
+
+        assert_eq!(
+            items[0].label,
+            "Some\\Module\\Ui\\DataProvider\\ListingDataProvider"
+        );
+        assert!(items
+            .iter()
+            .any(|item| item.label == "Some\\Module\\Model\\Config"));
+    }
+
+    #[test]
+    fn test_completion_for_visibility_condition_class_in_layout() {
+        let mut state = State::new();
+        state.add_module_path("Some\\Module", module_path(&[]));
+        let xml = r#"<?xml version="1.0"?>
+            <page>
+                <body>
+                    <referenceBlock name="some.block">
+                        <visibilityCondition name="some_condition" className="Some\Module\Model\|"/>
+                    </referenceBlock>
+                </body>
+            </page>
+            "#;
+        let path = PathBuf::from("/a/b/c/view/frontend/layout/default.xml");
+        let (content, pos) = split_cursor(xml);
+        state.set_file(&path, content);
+
+        let items = xml_completion_handler(&state, &path, pos).unwrap();
+
+        assert!(items
+            .iter()
+            .any(|item| item.label == "Some\\Module\\Model\\Config"));
+        assert!(items
+            .iter()
+            .all(|item| item.kind == Some(CompletionItemKind::CLASS)));
+    }
+
+    #[test]
+    fn test_completion_for_object_in_nested_array_item() {
+        let mut state = State::new();
+        state.add_module_path("Some\\Module", module_path(&[]));
+
+        let labels = get_test_completion(
+            &mut state,
+            r#"<?xml version="1.0"?>
+            <config>
+                <type name="Some\Module\Model\Chain">
+                    <arguments>
+                        <argument name="handlers" xsi:type="array">
+                            <item name="sorting" xsi:type="array">
+                                <item name="first" xsi:type="object">Some\Module\Model\Con|</item>
+                            </item>
+                        </argument>
+                    </arguments>
+                </type>
+            </config>
+            "#,
+            "/a/b/c/etc/di.xml",
+        );
+
+        assert_eq!(
+            labels,
+            vec![
+                "Some\\Module\\Model\\Config",
+                "Some\\Module\\Model\\Config\\Proxy"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_completion_for_element_tmpl() {
+        let mut state = state_with_module();
+
+        let labels = get_test_completion(
+            &mut state,
+            r#"<?xml version="1.0"?>
+            <listing>
+                <item name="elementTmpl" xsi:type="string">Some_Module/|</item>
+            </listing>
+            "#,
+            "/a/b/c/view/adminhtml/ui_component/some_listing.xml",
+        );
+
+        assert_eq!(labels, vec!["Some_Module/grid/listing"]);
+    }
+
+    #[test]
+    fn test_completion_for_argument_name_from_constructor() {
+        let mut state = state_with_module();
+
+        let labels = get_test_completion(
+            &mut state,
+            r#"<?xml version="1.0"?>
+            <config>
+                <type name="Some\Module\Block\Widget">
+                    <arguments>
+                        <argument name="|" xsi:type="object"/>
+                    </arguments>
+                </type>
+            </config>
+            "#,
+            "/a/b/c/etc/di.xml",
+        );
+
+        assert_eq!(labels, vec!["config", "context", "data"]);
+    }
+
+    #[test]
+    fn test_completion_for_argument_name_without_constructor() {
+        let mut state = state_with_module();
+
+        let labels = get_test_completion(
+            &mut state,
+            r#"<?xml version="1.0"?>
+            <config>
+                <type name="Some\Module\Model\Config">
+                    <arguments>
+                        <argument name="Some\|" xsi:type="object"/>
+                    </arguments>
+                </type>
+            </config>
+            "#,
+            "/a/b/c/etc/di.xml",
+        );
+
+        assert!(labels.is_empty());
+    }
+
+    #[test]
+    fn test_completion_for_relative_component() {
+        let mut state = State::new();
+        let path = module_path(&["view", "frontend", "web", "js", "some.js"]);
+        state.set_file(&path, "define(['./'], function (some) {})");
+
+        let labels: Vec<String> = js_completion_handler(
+            &state,
+            &path,
+            Position {
+                line: 0,
+                character: 11,
+            },
+        )
+        .unwrap_or_default()
+        .into_iter()
+        .map(|item| item.label)
+        .collect();
+
+        assert_eq!(labels, vec!["./component"]);
+    }
+
+    #[test]
+    fn test_completion_for_relative_component_lists_templates_with_text_plugin() {
+        let dir = TempDir::new("relative-component");
+        dir.write(&["widget.js"], "");
+        dir.write(&["widget.html"], "");
+        let path = dir.path(&["some.js"]);
+        let labels = |text: &str| {
+            let mut state = State::new();
+            let (content, pos) = split_cursor(&format!("define(['{text}|'], function () {{}})"));
+            state.set_file(&path, content);
+            js_completion_handler(&state, &path, pos)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|item| item.label)
+                .collect::<Vec<_>>()
+        };
+
+        let plain = labels("./");
+        let text = labels("text!./");
+
+        assert_eq!(plain, vec!["./widget"]);
+        assert_eq!(text, vec!["text!./widget.html"]);
+    }
+
+    #[test]
+    fn test_completion_for_classes_ranks_api_classes_first() {
+        let mut state = state_with_module();
+        let xml = r#"<?xml version="1.0"?>
+            <config>
+                <preference for="Some\Module\Model\|"/>
+            </config>
+            "#;
+        let path = PathBuf::from("/a/b/c/etc/di.xml");
+        let (content, pos) = split_cursor(xml);
+        state.set_file(&path, content);
+
+        let mut items = xml_completion_handler(&state, &path, pos).unwrap();
+        items.retain(|item| item.label.starts_with("Some\\Module\\Model\\"));
+        items.sort_by(|a, b| a.sort_text.cmp(&b.sort_text));
+
+        assert_eq!(
+            items
+                .iter()
+                .map(|item| item.label.as_str())
+                .collect::<Vec<_>>(),
+            vec![
+                "Some\\Module\\Model\\Stable",
+                "Some\\Module\\Model\\Child",
+                "Some\\Module\\Model\\Config"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_completion_for_observer_instance_only_observers() {
+        let mut state = state_with_module();
+
+        let labels = get_test_completion(
+            &mut state,
+            r#"<?xml version="1.0"?>
+            <config>
+                <event name="some_event">
+                    <observer name="some_observer" instance="Some\Module\|"/>
+                </event>
+            </config>
+            "#,
+            "/a/b/c/etc/events.xml",
+        );
+
+        assert!(labels.contains(&"Some\\Module\\Observer\\TestObserver".to_string()));
+        // implements the interface through its parent class
+        assert!(labels.contains(&"Some\\Module\\Observer\\ChildObserver".to_string()));
+        assert!(!labels.contains(&"Some\\Module\\Model\\Config".to_string()));
+    }
+
+    #[test]
+    fn test_completion_for_observer_instance_falls_back_to_all_classes() {
+        let mut state = state_with_module();
+
+        let labels = get_test_completion(
+            &mut state,
+            r#"<?xml version="1.0"?>
+            <config>
+                <event name="some_event">
+                    <observer name="some_observer" instance="Some\Module\Model\|"/>
+                </event>
+            </config>
+            "#,
+            "/a/b/c/etc/events.xml",
+        );
+
+        assert!(labels.contains(&"Some\\Module\\Model\\Config".to_string()));
+    }
+
+    #[test]
+    fn test_completion_for_object_argument_includes_proxy() {
+        let mut state = State::new();
+        state.add_module_path("Some\\Module", module_path(&[]));
+
+        let labels = get_test_completion(
+            &mut state,
+            r#"<?xml version="1.0"?>
+            <config>
+                <type name="Some\Module\Block\Widget">
+                    <arguments>
+                        <argument name="config" xsi:type="object">Some\Module\Model\St|</argument>
+                    </arguments>
+                </type>
+            </config>
+            "#,
+            "/a/b/c/etc/di.xml",
+        );
+
+        assert_eq!(
+            labels,
+            vec![
+                "Some\\Module\\Model\\Stable",
+                "Some\\Module\\Model\\Stable\\Proxy"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_completion_for_object_argument_includes_virtual_types() {
+        let mut state = State::new();
+        state.add_di_type("Some\\Module\\Model\\Config", "");
+        state.add_di_type("someVirtualSorter", "Some\\Module\\Model\\Config");
+
+        let labels = get_test_completion(
+            &mut state,
+            r#"<?xml version="1.0"?>
+            <config>
+                <type name="Some\Module\Model\Pool">
+                    <arguments>
+                        <argument name="sorter" xsi:type="object">some|</argument>
+                    </arguments>
+                </type>
+            </config>
+            "#,
+            "/a/b/c/etc/di.xml",
+        );
+
+        assert_eq!(labels, vec!["someVirtualSorter"]);
+    }
+
+    #[test]
+    fn test_completion_for_object_outside_di_has_no_proxy() {
+        let mut state = State::new();
+        state.add_module_path("Some\\Module", module_path(&[]));
+
+        let labels = get_test_completion(
+            &mut state,
+            r#"<?xml version="1.0"?>
+            <page>
+                <argument name="config" xsi:type="object">Some\Module\Model\St|</argument>
+            </page>
+            "#,
+            "/a/b/c/view/frontend/layout/default.xml",
+        );
+
+        assert_eq!(labels, vec!["Some\\Module\\Model\\Stable"]);
+    }
+
+    #[test]
+    fn test_completion_for_head_css_src() {
+        let mut state = state_with_module();
+
+        let labels = get_test_completion(
+            &mut state,
+            r#"<?xml version="1.0"?>
+            <page>
+                <head>
+                    <css src="Some_Module::|"/>
+                </head>
+            </page>
+            "#,
+            "/a/b/c/view/frontend/layout/default.xml",
+        );
+
+        assert_eq!(labels, vec!["Some_Module::css/styles.css"]);
+    }
+
+    #[test]
+    fn test_completion_for_head_css_src_lists_css_with_less_source_once() {
+        let module = TempDir::new("css-less");
+        module.write(&["view", "frontend", "web", "css", "styles.css"], "");
+        module.write(&["view", "frontend", "web", "css", "styles.less"], "");
+        let mut state = State::new();
+        state
+            .add_module("Some_Module")
+            .add_module_path("Some_Module", module.path(&[]));
+
+        let labels = get_test_completion(
+            &mut state,
+            r#"<?xml version="1.0"?>
+            <page>
+                <head>
+                    <css src="Some_Module::|"/>
+                </head>
+            </page>
+            "#,
+            "/a/b/c/view/frontend/layout/default.xml",
+        );
+
+        assert_eq!(labels, vec!["Some_Module::css/styles.css"]);
+    }
+
+    #[test]
+    fn test_completion_for_head_script_src() {
+        let mut state = state_with_module();
+
+        let labels = get_test_completion(
+            &mut state,
+            r#"<?xml version="1.0"?>
+            <page>
+                <head>
+                    <script src="Some_Module::|"/>
+                </head>
+            </page>
+            "#,
+            "/a/b/c/view/frontend/layout/default.xml",
+        );
+
+        assert_eq!(labels, vec!["Some_Module::js/component.js"]);
+    }
+
+    #[test]
+    fn test_completion_for_view_url_in_email_template() {
+        let mut state = state_with_module();
+        let path = PathBuf::from("/a/b/c/view/frontend/email/order_new.html");
+        state.set_file(&path, r#"<img src="{{view url="Some_Module::"}}"/>"#);
+
+        let labels: Vec<String> = email_completion_handler(&state, &path, Position::new(0, 35))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|item| item.label)
+            .collect();
+
+        assert_eq!(labels, vec!["Some_Module::images/logo.svg"]);
+    }
+}
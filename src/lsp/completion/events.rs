@@ -1,4 +1,7 @@
 use lsp_types::{CompletionItem, CompletionItemKind, CompletionTextEdit, Range, TextEdit};
+
+use crate::state::State;
+
 pub const EVENT_LIST: [&str; 344] = [
     "abstract_search_result_load_after",
     "abstract_search_result_load_before",
@@ -346,14 +349,18 @@ pub const EVENT_LIST: [&str; 344] = [
     "{eventPrefix}_validate_before",
 ];
 
-pub fn get_completion_items(range: Range) -> Vec<CompletionItem> {
-    EVENT_LIST
+pub fn get_completion_items(state: &State, range: Range) -> Vec<CompletionItem> {
+    let mut events = state.get_event_names();
+    events.extend(EVENT_LIST.iter().map(|event| (*event).to_string()));
+    events.sort_unstable();
+    events.dedup();
+    events
         .iter()
         .map(|event| CompletionItem {
-            label: (*event).to_string(),
+            label: event.clone(),
             text_edit: Some(CompletionTextEdit::Edit(TextEdit {
                 range,
-                new_text: (*event).to_string(),
+                new_text: event.clone(),
             })),
             label_details: None,
             kind: Some(CompletionItemKind::EVENT),
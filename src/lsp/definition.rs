@@ -1,43 +1,132 @@
 mod component;
-mod php;
-mod phtml;
+mod less;
+pub mod php;
+mod phrase;
+pub mod phtml;
+mod theme;
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use lsp_types::{GotoDefinitionParams, Location, Range, Url};
+use lsp_types::{GotoDefinitionParams, Location, Position, Range, TextDocumentPositionParams, Url};
 
 use crate::{
-    m2::{M2Item, M2Uri},
+    email, js,
+    m2::{M2Item, M2Path, M2Uri},
     state::State,
+    trace::Trace,
+    xml,
 };
 
 pub fn get_location_from_params(
     state: &State,
     params: &GotoDefinitionParams,
+    trace: &mut Trace,
 ) -> Option<Vec<Location>> {
     let path = params
         .text_document_position_params
         .text_document
         .uri
-        .to_path_buf();
+        .to_path_buf()?;
     let pos = params.text_document_position_params.position;
-    let item = state.get_item_from_position(&path, pos)?;
-    Some(match item {
+    let item = resolve_item(state, &path, pos, trace)?;
+    Some(locate_item(state, item, &path))
+}
+
+/// Every location item at the position resolves to, in resolution order
+pub fn get_targets_from_params(
+    state: &State,
+    params: &TextDocumentPositionParams,
+) -> Option<Vec<Location>> {
+    let path = params.text_document.uri.to_path_buf()?;
+    let item = resolve_item(state, &path, params.position, &mut Trace::default())?;
+    let mut result = locate_item(state, item, &path);
+    result.dedup();
+    Some(result)
+}
+
+pub fn get_type_location_from_params(
+    state: &State,
+    params: &GotoDefinitionParams,
+) -> Option<Vec<Location>> {
+    let path = params
+        .text_document_position_params
+        .text_document
+        .uri
+        .to_path_buf()?;
+    let pos = params.text_document_position_params.position;
+    match resolve_item(state, &path, pos, &mut Trace::default())? {
+        M2Item::Class(class) => Some(php::find_parents(state, &class)),
+        _ => None,
+    }
+}
+
+/// Item at the position in the file, resolution steps are recorded in `trace`
+pub fn resolve_item(
+    state: &State,
+    path: &Path,
+    pos: Position,
+    trace: &mut Trace,
+) -> Option<M2Item> {
+    let path = path.to_path_buf();
+    trace.step(|| format!("resolving {:?} at {}:{}", path, pos.line, pos.character));
+    let item = match path.get_ext().as_str() {
+        "js" => js::get_item_from_position(state, &path, pos),
+        "xml" => xml::get_item_from_position(state, &path, pos, trace),
+        "phtml" => crate::php::get_item_from_position(state, &path, pos),
+        "less" | "css" => crate::less::get_item_from_position(state, &path, pos),
+        "php" => crate::php::get_class_item_from_position(state, &path, pos),
+        "html" | "txt" if email::is_email_template(&path) => {
+            email::get_item_from_position(state, &path, pos)
+        }
+        _ => None,
+    };
+    trace.step(|| format!("resolved item: {:?}", item));
+    item
+}
+
+/// Find locations of given item, `path` is the file item was resolved from
+pub fn locate_item(state: &State, item: M2Item, path: &PathBuf) -> Vec<Location> {
+    match item {
         M2Item::ModComponent(mod_name, file_path, mod_path) => {
-            component::mod_location(state, mod_name, &file_path, mod_path, &path)
+            component::mod_location(state, mod_name, &file_path, mod_path, path)
         }
-        M2Item::RelComponent(comp, path) => component::find_rel(comp, &path)?,
+        M2Item::RelComponent(comp, path) => component::find_rel(comp, &path).unwrap_or_default(),
         M2Item::ModHtml(_, file_path, mod_path) => {
-            component::mod_html_location(&file_path, mod_path, &path)
+            component::mod_html_location(&file_path, mod_path, path)
         }
         M2Item::Component(comp) => component::find_plain(state, &comp),
         M2Item::AdminPhtml(mod_name, template) => phtml::find_admin(state, &mod_name, &template),
         M2Item::FrontPhtml(mod_name, template) => phtml::find_front(state, &mod_name, &template),
         M2Item::BasePhtml(mod_name, template) => phtml::find_base(state, &mod_name, &template),
-        M2Item::Class(class) => vec![php::find_class(state, &class)?],
-        M2Item::Method(class, method) => vec![php::find_method(state, &class, &method)?],
-        M2Item::Const(class, constant) => vec![php::find_const(state, &class, &constant)?],
-    })
+        M2Item::Class(class) => php::find_class(state, &state.resolve_virtual_type(&class))
+            .into_iter()
+            .collect(),
+        M2Item::Method(class, method) => php::find_method(state, &class, &method)
+            .into_iter()
+            .collect(),
+        M2Item::PluggedMethod(classes, method) => classes
+            .iter()
+            .filter_map(|class| php::find_method(state, class, &method))
+            .collect(),
+        M2Item::Const(class, constant) => php::find_const(state, &class, &constant)
+            .into_iter()
+            .collect(),
+        M2Item::Theme(area, name) => theme::find(state, &area, &name).into_iter().collect(),
+        M2Item::ConfigPath(config_path) => state.get_config_path_locations(&config_path),
+        M2Item::Phrase(phrase) => phrase::find(&phrase, path, state.position_encoding()),
+        M2Item::LayoutElement(name) => state.get_layout_element_locations(&name),
+        M2Item::LayoutHandle(handle) => state.get_layout_handle_locations(&handle),
+        M2Item::AclResource(id) => state.get_acl_resource_locations(&id),
+        M2Item::LessImport(file_path) => {
+            less::find_magento_import(state, &file_path, &path.get_area())
+        }
+        M2Item::AssetFile(file_path) => path_to_location(&file_path).into_iter().collect(),
+        M2Item::Module(mod_name) => state
+            .get_module_path(&mod_name)
+            .and_then(|mod_path| path_to_location(&mod_path.append(&["registration.php"])))
+            .into_iter()
+            .collect(),
+    }
 }
 
 fn path_to_location(path: &Path) -> Option<Location> {
@@ -50,3 +139,527 @@ fn path_to_location(path: &Path) -> Option<Location> {
         None
     }
 }
+
+#[cfg(test)]
+mod test {
+    use crate::m2::{M2Area, M2Path};
+
+    use super::*;
+    use crate::test_utils::{fixture_path, module_path, state_with_module, TempDir};
+
+    fn state() -> State {
+        let mut state = state_with_module();
+        state.add_workspace_path(&fixture_path(&[]));
+        state
+    }
+
+    fn located_paths(state: &State, item: M2Item, path: &PathBuf) -> Vec<PathBuf> {
+        locate_item(state, item, path)
+            .iter()
+            .map(|l| l.uri.to_file_path().expect("Location should be a file"))
+            .collect()
+    }
+
+    fn frontend_file() -> PathBuf {
+        module_path(&["view", "frontend", "layout", "default.xml"])
+    }
+
+    #[test]
+    fn test_resolve_item() {
+        let mut state = state();
+        let path = PathBuf::from("/a/b/c/etc/di.xml");
+        state.set_file(&path, r#"<config><type name="Some\Module\Test"/></config>"#);
+
+        let item = resolve_item(&state, &path, Position::new(0, 25), &mut Trace::default());
+
+        assert_eq!(item, Some(M2Item::Class("Some\\Module\\Test".into())));
+    }
+
+    #[test]
+    fn test_locate_mod_component() {
+        let item = M2Item::ModComponent(
+            "Some_Module".into(),
+            "js/component".into(),
+            module_path(&[]),
+        );
+
+        assert_eq!(
+            located_paths(&state(), item, &frontend_file()),
+            vec![module_path(&[
+                "view",
+                "frontend",
+                "web",
+                "js",
+                "component.js"
+            ])]
+        );
+    }
+
+    #[test]
+    fn test_locate_rel_component() {
+        let item = M2Item::RelComponent(
+            "./component".into(),
+            module_path(&["view", "frontend", "web", "js"]),
+        );
+
+        assert_eq!(
+            located_paths(&state(), item, &frontend_file()),
+            vec![module_path(&[
+                "view",
+                "frontend",
+                "web",
+                "js",
+                "component.js"
+            ])]
+        );
+    }
+
+    #[test]
+    fn test_locate_mod_html() {
+        let item = M2Item::ModHtml(
+            "Some_Module".into(),
+            "template/grid/listing.html".into(),
+            module_path(&[]),
+        );
+        let path = module_path(&["view", "adminhtml", "ui_component", "listing.xml"]);
+
+        assert_eq!(
+            located_paths(&state(), item, &path),
+            vec![module_path(&[
+                "view",
+                "adminhtml",
+                "web",
+                "template",
+                "grid",
+                "listing.html"
+            ])]
+        );
+    }
+
+    #[test]
+    fn test_locate_module() {
+        let item = M2Item::Module("Some_Module".into());
+
+        assert_eq!(
+            located_paths(&state(), item, &frontend_file()),
+            vec![module_path(&["registration.php"])]
+        );
+    }
+
+    #[test]
+    fn test_locate_asset_file() {
+        let asset = fixture_path(&[
+            "vendor",
+            "magento",
+            "module-ui",
+            "view",
+            "base",
+            "web",
+            "css",
+            "source",
+            "_module.less",
+        ]);
+        let missing = asset.with_file_name("missing.png");
+
+        assert_eq!(
+            located_paths(&state(), M2Item::AssetFile(asset.clone()), &frontend_file()),
+            vec![asset]
+        );
+        assert!(located_paths(&state(), M2Item::AssetFile(missing), &frontend_file()).is_empty());
+    }
+
+    #[test]
+    fn test_locate_component() {
+        let item = M2Item::Component("some/lib-component".into());
+
+        assert_eq!(
+            located_paths(&state(), item, &frontend_file()),
+            vec![fixture_path(&["lib", "web", "some", "lib-component.js"])]
+        );
+    }
+
+    #[test]
+    fn test_locate_admin_phtml() {
+        let item = M2Item::AdminPhtml("Some_Module".into(), "admin.phtml".into());
+
+        assert_eq!(
+            located_paths(&state(), item, &frontend_file()),
+            vec![
+                module_path(&["view", "adminhtml", "templates", "admin.phtml"]),
+                fixture_path(&[
+                    "vendor",
+                    "magento",
+                    "theme-adminhtml-backend",
+                    "Some_Module",
+                    "templates",
+                    "admin.phtml"
+                ])
+            ]
+        );
+    }
+
+    #[test]
+    fn test_locate_front_phtml() {
+        let theme_path = fixture_path(&["app", "design", "frontend", "Some", "parent"]);
+        let mut state = state();
+        state.add_front_theme_path("frontend/Some/parent", theme_path.clone());
+        let item = M2Item::FrontPhtml("Some_Module".into(), "front.phtml".into());
+
+        assert_eq!(
+            located_paths(&state, item, &frontend_file()),
+            vec![theme_path.append(&["Some_Module", "templates", "front.phtml"])]
+        );
+    }
+
+    #[test]
+    fn test_locate_base_phtml() {
+        let item = M2Item::BasePhtml("Some_Module".into(), "admin.phtml".into());
+
+        assert_eq!(
+            located_paths(&state(), item, &frontend_file()),
+            vec![
+                module_path(&["view", "adminhtml", "templates", "admin.phtml"]),
+                fixture_path(&[
+                    "vendor",
+                    "magento",
+                    "theme-adminhtml-backend",
+                    "Some_Module",
+                    "templates",
+                    "admin.phtml"
+                ])
+            ]
+        );
+    }
+
+    #[test]
+    fn test_locate_class() {
+        let item = M2Item::Class("Some\\Module\\Test".into());
+
+        assert_eq!(
+            located_paths(&state(), item, &frontend_file()),
+            vec![module_path(&["Test.php"])]
+        );
+    }
+
+    #[test]
+    fn test_locate_proxy_class_without_generated_file() {
+        let item = M2Item::Class("Some\\Module\\Model\\Config\\Proxy".into());
+
+        assert_eq!(
+            located_paths(&state(), item, &frontend_file()),
+            vec![module_path(&["Model", "Config.php"])]
+        );
+    }
+
+    #[test]
+    fn test_locate_generated_proxy_class() {
+        let generated = TempDir::new("proxy");
+        let proxy_path = generated.write(
+            &["Model", "Config", "Proxy.php"],
+            "<?php\nnamespace Some\\Generated\\Model\\Config;\n\nclass Proxy\n{\n}\n",
+        );
+        let mut state = State::new();
+        state.add_module_path("Some\\Generated", generated.path(&[]));
+        let item = M2Item::Class("Some\\Generated\\Model\\Config\\Proxy".into());
+
+        assert_eq!(
+            located_paths(&state, item, &frontend_file()),
+            vec![proxy_path]
+        );
+    }
+
+    #[test]
+    fn test_locate_virtual_type_through_alias_chain() {
+        let mut state = state();
+        state.add_di_type("someVirtualType", "Some\\Module\\Test");
+        state.add_di_type("otherVirtualType", "someVirtualType");
+        let item = M2Item::Class("otherVirtualType".into());
+
+        assert_eq!(
+            located_paths(&state, item, &frontend_file()),
+            vec![module_path(&["Test.php"])]
+        );
+    }
+
+    #[test]
+    fn test_locate_method() {
+        let item = M2Item::Method(
+            "Some\\Module\\Observer\\TestObserver".into(),
+            "execute".into(),
+        );
+
+        assert_eq!(
+            located_paths(&state(), item, &frontend_file()),
+            vec![module_path(&["Observer", "TestObserver.php"])]
+        );
+    }
+
+    #[test]
+    fn test_locate_plugged_method_in_every_type() {
+        let item = M2Item::PluggedMethod(
+            vec![
+                "Some\\Module\\Observer\\TestObserver".into(),
+                "Some\\Module\\Model\\Config".into(),
+            ],
+            "execute".into(),
+        );
+
+        // class itself when it has no such method
+        assert_eq!(
+            located_paths(&state(), item, &frontend_file()),
+            vec![
+                module_path(&["Observer", "TestObserver.php"]),
+                module_path(&["Model", "Config.php"])
+            ]
+        );
+    }
+
+    #[test]
+    fn test_locate_const() {
+        let item = M2Item::Const("Some\\Module\\Model\\Config".into(), "PARAM".into());
+
+        assert_eq!(
+            located_paths(&state(), item, &frontend_file()),
+            vec![module_path(&["Model", "Config.php"])]
+        );
+    }
+
+    #[test]
+    fn test_locate_theme() {
+        let theme_path = fixture_path(&["app", "design", "frontend", "Some", "parent"]);
+        let mut state = state();
+        state.add_front_theme_path("frontend/Some/parent", theme_path.clone());
+        let item = M2Item::Theme(M2Area::Frontend, "Some/parent".into());
+
+        assert_eq!(
+            located_paths(&state, item, &frontend_file()),
+            vec![theme_path.append(&["theme.xml"])]
+        );
+    }
+
+    #[test]
+    fn test_locate_config_path() {
+        let system_path = module_path(&["etc", "adminhtml", "system.xml"]);
+        let mut state = state();
+        state.add_config_path(
+            "some/group/field",
+            Location {
+                uri: Url::from_file_path(&system_path).unwrap(),
+                range: Range::default(),
+            },
+        );
+        let item = M2Item::ConfigPath("some/group/field".into());
+
+        assert_eq!(
+            located_paths(&state, item, &frontend_file()),
+            vec![system_path]
+        );
+    }
+
+    #[test]
+    fn test_locate_acl_resource() {
+        let acl_path = module_path(&["etc", "acl.xml"]);
+        let mut state = state();
+        state.add_acl_resource(
+            "Some_Module::manage",
+            Location {
+                uri: Url::from_file_path(&acl_path).unwrap(),
+                range: Range::default(),
+            },
+        );
+        let item = M2Item::AclResource("Some_Module::manage".into());
+
+        assert_eq!(
+            located_paths(&state, item, &frontend_file()),
+            vec![acl_path]
+        );
+    }
+
+    #[test]
+    fn test_locate_controller_layout_handle() {
+        let mut state = state();
+        let layout_path = module_path(&["view", "frontend", "layout", "some_route_order_view.xml"]);
+        state.set_file(&layout_path, "<page/>");
+        state.set_file(
+            &module_path(&["etc", "frontend", "routes.xml"]),
+            r#"<config><router id="standard"><route id="some_route" frontName="some">
+                <module name="Some_Module"/>
+            </route></router></config>"#,
+        );
+        let controller_path = module_path(&["Controller", "Order", "View.php"]);
+        state.set_file(
+            &controller_path,
+            "<?php\nnamespace Some\\Module\\Controller\\Order;\n\nclass View\n{\n}\n",
+        );
+
+        let item = resolve_item(
+            &state,
+            &controller_path,
+            Position::new(3, 8),
+            &mut Trace::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            located_paths(&state, item, &controller_path),
+            vec![layout_path]
+        );
+    }
+
+    #[test]
+    fn test_locate_source_model_class() {
+        let mut state = state();
+        let system_path = PathBuf::from("/a/b/c/etc/adminhtml/system.xml");
+        state.set_file(
+            &system_path,
+            "<config><system><section id=\"s\"><group id=\"g\"><field id=\"f\">\n\
+             <source_model>Some\\Module\\Source\\Yesno</source_model>\n\
+             </field></group></section></system></config>",
+        );
+
+        let item = resolve_item(
+            &state,
+            &system_path,
+            Position::new(1, 20),
+            &mut Trace::default(),
+        )
+        .unwrap();
+
+        assert_eq!(item, M2Item::Class("Some\\Module\\Source\\Yesno".into()));
+        assert_eq!(
+            located_paths(&state, item, &system_path),
+            vec![module_path(&["Source", "Yesno.php"])]
+        );
+    }
+
+    #[test]
+    fn test_locate_phrase() {
+        let item = M2Item::Phrase("Save".into());
+
+        let mut result = located_paths(&state(), item, &frontend_file());
+        result.sort();
+
+        assert_eq!(
+            result,
+            vec![
+                module_path(&["i18n", "de_DE.csv"]),
+                module_path(&["i18n", "en_US.csv"])
+            ]
+        );
+    }
+
+    #[test]
+    fn test_locate_missing_class() {
+        let item = M2Item::Class("Some\\Module\\Missing".into());
+
+        assert_eq!(locate_item(&state(), item, &frontend_file()), vec![]);
+    }
+
+    #[test]
+    fn test_locate_plugin_method_on_interface() {
+        let mut state = state();
+        state.add_plugin(
+            &M2Area::Base,
+            "Some\\Module\\Api\\TestInterface",
+            "some_plugin",
+            crate::state::DiPlugin {
+                class: Some("Some\\Module\\Plugin\\TestPlugin".into()),
+                disabled: None,
+            },
+        );
+        let path = PathBuf::from("/a/b/c/Plugin/TestPlugin.php");
+        state.set_file(
+            &path,
+            "<?php\nnamespace Some\\Module\\Plugin;\n\nclass TestPlugin\n{\n    public function aroundGetBar($subject, callable $proceed) {}\n}\n",
+        );
+
+        let item =
+            resolve_item(&state, &path, Position::new(5, 25), &mut Trace::default()).unwrap();
+        let locations = locate_item(&state, item, &path);
+
+        assert_eq!(locations.len(), 1);
+        assert_eq!(
+            locations[0].uri.to_file_path().unwrap(),
+            module_path(&["Api", "TestInterface.php"])
+        );
+        assert_eq!(locations[0].range.start.line, 8);
+    }
+
+    #[test]
+    fn test_locate_reference_block_declaration() {
+        let mut state = state();
+        let declaration = PathBuf::from("/a/b/c/view/frontend/layout/default.xml");
+        state.set_file(
+            &declaration,
+            r#"<?xml version="1.0"?>
+            <page>
+                <container name="some.container">
+                    <block class="Some\Module\Block\Widget" name="some.block"/>
+                </container>
+            </page>
+            "#,
+        );
+        let path = PathBuf::from("/a/b/d/view/frontend/layout/catalog_product_view.xml");
+        state.set_file(
+            &path,
+            r#"<?xml version="1.0"?>
+            <page>
+                <referenceBlock name="some.block" remove="true"/>
+                <referenceContainer name="some.container" display="false"/>
+            </page>
+            "#,
+        );
+
+        let item =
+            resolve_item(&state, &path, Position::new(2, 40), &mut Trace::default()).unwrap();
+        assert_eq!(item, M2Item::LayoutElement("some.block".into()));
+        let locations = locate_item(&state, item, &path);
+        assert_eq!(locations.len(), 1);
+        assert_eq!(locations[0].range.start, Position::new(3, 20));
+
+        let item =
+            resolve_item(&state, &path, Position::new(3, 44), &mut Trace::default()).unwrap();
+        assert_eq!(item, M2Item::LayoutElement("some.container".into()));
+        assert_eq!(
+            located_paths(&state, item, &path),
+            vec![declaration.clone()]
+        );
+    }
+
+    #[test]
+    fn test_targets_for_template_with_overrides() {
+        let mut state = state();
+        let path = module_path(&["view", "adminhtml", "layout", "default.xml"]);
+        state.set_file(
+            &path,
+            r#"<page><block template="Some_Module::admin.phtml"/></page>"#,
+        );
+        let params = TextDocumentPositionParams {
+            text_document: lsp_types::TextDocumentIdentifier {
+                uri: Url::from_file_path(&path).unwrap(),
+            },
+            position: Position::new(0, 35),
+        };
+
+        let targets = get_targets_from_params(&state, &params)
+            .unwrap_or_default()
+            .iter()
+            .map(|l| l.uri.to_file_path().unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            targets,
+            vec![
+                module_path(&["view", "adminhtml", "templates", "admin.phtml"]),
+                fixture_path(&[
+                    "vendor",
+                    "magento",
+                    "theme-adminhtml-backend",
+                    "Some_Module",
+                    "templates",
+                    "admin.phtml"
+                ])
+            ]
+        );
+    }
+}
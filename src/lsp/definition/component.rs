@@ -64,11 +64,198 @@ pub fn mod_html_location(file_path: &str, mod_path: PathBuf, path: &PathBuf) ->
     let mut result = vec![];
     let area = path.get_area();
     for area_path in area.path_candidates() {
-        let comp_path = mod_path.append(&["view", area_path, "web", &file_path]);
+        let comp_path = mod_path.append(&["view", area_path, "web", file_path]);
         if let Some(location) = path_to_location(&comp_path) {
             result.push(location);
+        } else if let Some(less_path) = file_path.strip_suffix(".css") {
+            // css files are compiled from less source during deployment
+            let less_path = mod_path.append(&["view", area_path, "web", less_path]);
+            if let Some(location) = path_to_location(&less_path.append_ext("less")) {
+                result.push(location);
+            }
         }
     }
 
     result
 }
+
+#[cfg(test)]
+mod test {
+    use lsp_types::Url;
+
+    use crate::m2::M2Area;
+
+    use super::*;
+    use crate::test_utils::{fixture_path, module_path};
+
+    #[test]
+    fn test_mod_html_location_for_ui_template() {
+        let mod_path = module_path(&[]);
+
+        let result = mod_html_location(
+            "template/grid/listing.html",
+            mod_path.clone(),
+            &PathBuf::from("/a/b/c/view/adminhtml/ui_component/some_listing.xml"),
+        );
+
+        assert_eq!(
+            result.iter().map(|l| l.uri.clone()).collect::<Vec<_>>(),
+            vec![Url::from_file_path(mod_path.append(&[
+                "view",
+                "adminhtml",
+                "web",
+                "template",
+                "grid",
+                "listing.html"
+            ]))
+            .unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_mod_location_for_mapped_alias() {
+        let mod_path = module_path(&[]);
+        let mut state = State::new();
+        state.add_module_path("Some_Module", mod_path.clone());
+        state.add_component_map(
+            "someModuleComponent",
+            "Some_Module/js/component",
+            &M2Area::Frontend,
+        );
+        let path = PathBuf::from("/a/b/c/view/frontend/web/js/some.js");
+
+        let Some(M2Item::ModComponent(mod_name, file_path, mod_path)) =
+            crate::js::text_to_component(&state, "someModuleComponent", &path)
+        else {
+            panic!("Alias should resolve to module component");
+        };
+        let result = mod_location(&state, mod_name, &file_path, mod_path.clone(), &path);
+
+        assert_eq!(
+            result.iter().map(|l| l.uri.clone()).collect::<Vec<_>>(),
+            vec![Url::from_file_path(mod_path.append(&[
+                "view",
+                "frontend",
+                "web",
+                "js",
+                "component.js"
+            ]))
+            .unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_mod_location_for_deep_subpath() {
+        let mod_path = fixture_path(&["vendor", "magento", "module-ui"]);
+        let state = State::new();
+
+        let result = mod_location(
+            &state,
+            "Magento_Ui".into(),
+            "js/lib/collapsible",
+            mod_path.clone(),
+            &PathBuf::from("/a/b/c/view/frontend/web/js/some.js"),
+        );
+
+        assert_eq!(
+            result.iter().map(|l| l.uri.clone()).collect::<Vec<_>>(),
+            vec![Url::from_file_path(mod_path.append(&[
+                "view",
+                "base",
+                "web",
+                "js",
+                "lib",
+                "collapsible.js"
+            ]))
+            .unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_mod_location_for_base_only_component_from_adminhtml() {
+        let mut state = State::new();
+        state.add_module_path(
+            "Magento_Ui",
+            fixture_path(&["vendor", "magento", "module-ui"]),
+        );
+        let path = PathBuf::from("/a/b/c/view/adminhtml/layout/default.xml");
+
+        let Some(M2Item::ModComponent(mod_name, file_path, mod_path)) =
+            crate::js::text_to_component(&state, "Magento_Ui/js/lib/collapsible", &path)
+        else {
+            panic!("Should resolve to module component");
+        };
+        let result = mod_location(&state, mod_name, &file_path, mod_path.clone(), &path);
+
+        assert_eq!(
+            result.iter().map(|l| l.uri.clone()).collect::<Vec<_>>(),
+            vec![Url::from_file_path(mod_path.append(&[
+                "view",
+                "base",
+                "web",
+                "js",
+                "lib",
+                "collapsible.js"
+            ]))
+            .unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_mod_html_location_for_view_file() {
+        let mod_path = module_path(&[]);
+
+        let result = mod_html_location(
+            "images/logo.svg",
+            mod_path.clone(),
+            &PathBuf::from("/a/b/c/view/frontend/templates/logo.phtml"),
+        );
+
+        assert_eq!(
+            result.iter().map(|l| l.uri.clone()).collect::<Vec<_>>(),
+            vec![Url::from_file_path(
+                mod_path.append(&["view", "frontend", "web", "images", "logo.svg"])
+            )
+            .unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_mod_html_location_for_css_compiled_from_less() {
+        let mod_path = module_path(&[]);
+
+        let result = mod_html_location(
+            "css/styles.css",
+            mod_path.clone(),
+            &PathBuf::from("/a/b/c/view/frontend/layout/default.xml"),
+        );
+
+        assert_eq!(
+            result.iter().map(|l| l.uri.clone()).collect::<Vec<_>>(),
+            vec![Url::from_file_path(mod_path.append(&[
+                "view",
+                "frontend",
+                "web",
+                "css",
+                "styles.less"
+            ]))
+            .unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_find_plain_in_lib_web() {
+        let mut state = State::new();
+        state.add_workspace_path(&fixture_path(&[]));
+
+        let result = find_plain(&state, "some/lib-component");
+
+        assert_eq!(
+            result.iter().map(|l| l.uri.clone()).collect::<Vec<_>>(),
+            vec![
+                Url::from_file_path(fixture_path(&["lib", "web", "some", "lib-component.js"]))
+                    .unwrap()
+            ]
+        );
+    }
+}
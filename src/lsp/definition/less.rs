@@ -0,0 +1,89 @@
+use lsp_types::Location;
+
+use crate::{
+    m2::{M2Area, M2Path},
+    state::State,
+};
+
+use super::path_to_location;
+
+/// Find every module and theme file collected by `//@magento_import`,
+/// `file_path` is relative to `web` dir
+pub fn find_magento_import(state: &State, file_path: &str, area: &M2Area) -> Vec<Location> {
+    let parts: Vec<&str> = file_path.split('/').collect();
+    let theme_paths = state.list_themes_paths(area);
+    let mut result = vec![];
+    for mod_name in state.get_modules() {
+        if let Some(mod_path) = state.get_module_path(&mod_name) {
+            for area_path in area.path_candidates() {
+                let path = mod_path.append(&["view", area_path, "web"]).append(&parts);
+                result.extend(path_to_location(&path));
+            }
+        }
+        for theme_path in &theme_paths {
+            let path = theme_path.append(&[&mod_name, "web"]).append(&parts);
+            result.extend(path_to_location(&path));
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::test_utils::{fixture_path, module_path};
+
+    #[test]
+    fn test_find_magento_import_in_modules_and_themes() {
+        let mut state = State::new();
+        state
+            .add_module("Magento_Ui")
+            .add_module_path(
+                "Magento_Ui",
+                fixture_path(&["vendor", "magento", "module-ui"]),
+            )
+            .add_module("Some_Module")
+            .add_module_path("Some_Module", module_path(&[]));
+        state.add_front_theme_path(
+            "frontend/Some/parent",
+            fixture_path(&["app", "design", "frontend", "Some", "parent"]),
+        );
+
+        let result: Vec<PathBuf> =
+            find_magento_import(&state, "css/source/_module.less", &M2Area::Frontend)
+                .iter()
+                .map(|l| l.uri.to_file_path().unwrap())
+                .collect();
+
+        assert_eq!(
+            result,
+            vec![
+                fixture_path(&[
+                    "vendor",
+                    "magento",
+                    "module-ui",
+                    "view",
+                    "base",
+                    "web",
+                    "css",
+                    "source",
+                    "_module.less"
+                ]),
+                fixture_path(&[
+                    "app",
+                    "design",
+                    "frontend",
+                    "Some",
+                    "parent",
+                    "Magento_Ui",
+                    "web",
+                    "css",
+                    "source",
+                    "_module.less"
+                ]),
+            ]
+        );
+    }
+}
@@ -1,4 +1,9 @@
-use lsp_types::Location;
+use std::{
+    collections::{HashSet, VecDeque},
+    path::PathBuf,
+};
+
+use lsp_types::{Location, Range};
 
 use crate::{
     php::{parse_php_file, PHPClass},
@@ -15,40 +20,298 @@ pub fn find_class(state: &State, class: &str) -> Option<Location> {
 
 pub fn find_method(state: &State, class: &str, method: &str) -> Option<Location> {
     let phpclass = get_php_class_from_class_name(state, class)?;
-    Some(Location {
-        uri: phpclass.uri.clone(),
-        range: phpclass
-            .methods
-            .get(method)
-            .map_or(phpclass.range, |method| method.range),
-    })
+    let lookup = |c: &PHPClass| c.methods.get(method).map(|method| method.range);
+    // implementation first, interface declaration only if there is none
+    find_inherited(state, &phpclass, |c| c.extends.iter().collect(), lookup)
+        .or_else(|| find_inherited(state, &phpclass, |c| c.parents.iter().collect(), lookup))
+        .or_else(|| {
+            Some(Location {
+                uri: phpclass.uri.clone(),
+                range: phpclass.range,
+            })
+        })
 }
 
 pub fn find_const(state: &State, class: &str, constant: &str) -> Option<Location> {
     let phpclass = get_php_class_from_class_name(state, class)?;
-    Some(Location {
-        uri: phpclass.uri.clone(),
-        range: phpclass
-            .constants
-            .get(constant)
-            .map_or(phpclass.range, |method| method.range),
+    let lookup = |c: &PHPClass| c.constants.get(constant).map(|constant| constant.range);
+    find_inherited(
+        state,
+        &phpclass,
+        |c| c.parents.iter().chain(&c.traits).collect(),
+        lookup,
+    )
+    .or_else(|| {
+        Some(Location {
+            uri: phpclass.uri.clone(),
+            range: phpclass.range,
+        })
+    })
+}
+
+// member can be declared in any class up the parent chain,
+// walk stops on cyclic or unresolved parents
+fn find_inherited<F>(
+    state: &State,
+    phpclass: &PHPClass,
+    parents: fn(&PHPClass) -> Vec<&String>,
+    lookup: F,
+) -> Option<Location>
+where
+    F: Fn(&PHPClass) -> Option<Range>,
+{
+    let mut visited = HashSet::new();
+    let mut queue = VecDeque::from([phpclass.clone()]);
+    while let Some(current) = queue.pop_front() {
+        if !visited.insert(current.fqn.clone()) {
+            continue;
+        }
+        if let Some(range) = lookup(&current) {
+            return Some(Location {
+                uri: current.uri.clone(),
+                range,
+            });
+        }
+        queue.extend(
+            parents(&current)
+                .into_iter()
+                .filter(|parent| !visited.contains(*parent))
+                .filter_map(|parent| get_php_class_from_class_name(state, parent)),
+        );
+    }
+    None
+}
+
+pub fn find_parents(state: &State, class: &str) -> Vec<Location> {
+    get_php_class_from_class_name(state, class).map_or_else(Vec::new, |phpclass| {
+        phpclass
+            .parents
+            .iter()
+            .filter_map(|parent| find_class(state, parent))
+            .collect()
     })
 }
 
-fn get_php_class_from_class_name(state: &State, class: &str) -> Option<PHPClass> {
-    let module_path = state.split_class_to_path_and_suffix(class);
-    match module_path {
-        None => None,
-        Some((mut file_path, suffix)) => {
-            for part in suffix {
+pub fn get_php_class_from_class_name(state: &State, class: &str) -> Option<PHPClass> {
+    find_class_files(state, resolve_proxy_class(state, class))
+        .into_iter()
+        .find_map(|file_path| parse_php_file(&file_path, state.position_encoding()))
+}
+
+/// Class wrapped by generated proxy, unless proxy class file exists (e.g. code is compiled)
+pub fn resolve_proxy_class<'a>(state: &State, class: &'a str) -> &'a str {
+    match class.strip_suffix("\\Proxy") {
+        Some(wrapped) if find_class_files(state, class).is_empty() => wrapped,
+        _ => class,
+    }
+}
+
+pub fn find_class_files(state: &State, class: &str) -> Vec<PathBuf> {
+    let Some((module_paths, suffix)) = state.split_class_to_path_and_suffix(class) else {
+        return vec![];
+    };
+    // same module can be registered in more than one workspace root
+    module_paths
+        .into_iter()
+        .map(|mut file_path| {
+            for part in &suffix {
                 file_path.push(part);
             }
             file_path.set_extension("php");
+            file_path
+        })
+        .filter(|file_path| file_path.try_exists().is_ok_and(|exists| exists))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use lsp_types::{Position, Url};
+
+    use crate::m2::{self, M2Item};
+
+    use super::*;
+    use crate::test_utils::{module_path, state_with_module};
+
+    #[test]
+    fn test_find_method_on_observer_execute() {
+        let state = state_with_module();
+
+        let location = find_method(&state, "Some\\Module\\Observer\\TestObserver", "execute")
+            .expect("Observer should be found");
 
-            match file_path.try_exists() {
-                Ok(true) => parse_php_file(&file_path),
-                _ => None,
+        assert_eq!(
+            location.uri,
+            Url::from_file_path(module_path(&["Observer", "TestObserver.php"])).unwrap()
+        );
+        assert_eq!(
+            location.range.start,
+            Position {
+                line: 9,
+                character: 20
             }
-        }
+        );
+    }
+
+    #[test]
+    fn test_find_method_declared_in_parent_class() {
+        let state = state_with_module();
+
+        let location = find_method(&state, "Some\\Module\\Model\\Child", "getParam")
+            .expect("Child should be found");
+
+        assert_eq!(
+            location.uri,
+            Url::from_file_path(module_path(&["Model", "Config.php"])).unwrap()
+        );
+        assert_eq!(location.range.start, Position::new(10, 20));
+    }
+
+    #[test]
+    fn test_find_method_declared_only_in_interface() {
+        let state = state_with_module();
+
+        let location = find_method(&state, "Some\\Module\\Model\\Child", "getFoo")
+            .expect("Child should be found");
+
+        assert_eq!(
+            location.uri,
+            Url::from_file_path(module_path(&["Api", "TestInterface.php"])).unwrap()
+        );
+        assert_eq!(location.range.start, Position::new(10, 13));
+    }
+
+    #[test]
+    fn test_find_method_with_cyclic_parents() {
+        let state = state_with_module();
+
+        let location = find_method(&state, "Some\\Module\\Cycle\\First", "missing")
+            .expect("Class should be found");
+
+        assert_eq!(
+            location.uri,
+            Url::from_file_path(module_path(&["Cycle", "First.php"])).unwrap()
+        );
+        assert_eq!(location.range.start, Position::new(4, 6));
+    }
+
+    #[test]
+    fn test_find_typed_const_with_visibility() {
+        let state = state_with_module();
+
+        let location = find_const(&state, "Some\\Module\\Model\\Config", "LIMIT")
+            .expect("Class should be found");
+
+        assert_eq!(
+            location.uri,
+            Url::from_file_path(module_path(&["Model", "Config.php"])).unwrap()
+        );
+        assert_eq!(location.range.start, Position::new(8, 24));
+    }
+
+    #[test]
+    fn test_find_const_declared_in_implemented_interface() {
+        let state = state_with_module();
+
+        let location =
+            find_const(&state, "Some\\Module\\Model\\Child", "BAR").expect("Child should be found");
+
+        assert_eq!(
+            location.uri,
+            Url::from_file_path(module_path(&["Api", "TestInterface.php"])).unwrap()
+        );
+        assert_eq!(location.range.start, Position::new(6, 10));
+    }
+
+    #[test]
+    fn test_find_const_declared_in_used_trait() {
+        let state = state_with_module();
+
+        let location = find_const(&state, "Some\\Module\\Model\\Child", "MAX_LIMIT")
+            .expect("Child should be found");
+
+        assert_eq!(
+            location.uri,
+            Url::from_file_path(module_path(&["Traits", "HasLimit.php"])).unwrap()
+        );
+        assert_eq!(location.range.start, Position::new(6, 17));
+    }
+
+    #[test]
+    fn test_find_const_on_interface() {
+        let state = state_with_module();
+
+        let location = find_const(&state, "Some\\Module\\Api\\TestInterface", "BAR")
+            .expect("Interface should be found");
+
+        assert_eq!(
+            location.uri,
+            Url::from_file_path(module_path(&["Api", "TestInterface.php"])).unwrap()
+        );
+        assert_eq!(
+            location.range.start,
+            Position {
+                line: 6,
+                character: 10
+            }
+        );
+    }
+
+    #[test]
+    fn test_find_const_for_init_parameter() {
+        let state = state_with_module();
+        let Some(M2Item::Const(class, constant)) =
+            m2::try_const_item_from_str("Some\\Module\\Model\\Config::PARAM")
+        else {
+            panic!("init_parameter should be a const item");
+        };
+
+        let location = find_const(&state, &class, &constant).expect("Const should be found");
+
+        assert_eq!(
+            location.uri,
+            Url::from_file_path(module_path(&["Model", "Config.php"])).unwrap()
+        );
+        assert_eq!(
+            location.range.start,
+            Position {
+                line: 6,
+                character: 17
+            }
+        );
+    }
+
+    #[test]
+    fn test_find_class_in_any_workspace_root() {
+        let mut state = state_with_module();
+        state.add_module_path(
+            "Some\\Module",
+            PathBuf::from("/missing/app/code/Some/Module"),
+        );
+
+        let location =
+            find_class(&state, "Some\\Module\\Model\\Config").expect("Class should be found");
+
+        assert_eq!(
+            location.uri,
+            Url::from_file_path(module_path(&["Model", "Config.php"])).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_find_parents() {
+        let state = state_with_module();
+
+        let locations = find_parents(&state, "Some\\Module\\Model\\Child");
+
+        assert_eq!(
+            locations.iter().map(|l| l.uri.clone()).collect::<Vec<_>>(),
+            vec![
+                Url::from_file_path(module_path(&["Model", "Config.php"])).unwrap(),
+                Url::from_file_path(module_path(&["Api", "TestInterface.php"])).unwrap()
+            ]
+        );
     }
 }
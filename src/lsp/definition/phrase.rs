@@ -0,0 +1,125 @@
+use std::path::Path;
+
+use lsp_types::{Location, Position, PositionEncodingKind, Range, Url};
+
+use crate::{m2::M2Path, ts};
+
+pub fn find(phrase: &str, path: &Path, encoding: &PositionEncodingKind) -> Vec<Location> {
+    let Some(module_path) = find_module_root(path) else {
+        return vec![];
+    };
+    let glob_path = module_path.append(&["i18n", "*.csv"]);
+    let Ok(files) = glob::glob(glob_path.to_path_str()) else {
+        return vec![];
+    };
+
+    let mut result = vec![];
+    for file in files.filter_map(Result::ok) {
+        let Ok(content) = std::fs::read_to_string(&file) else {
+            continue;
+        };
+        for (line, row) in content.lines().enumerate() {
+            if first_csv_field(row).is_some_and(|field| field == phrase) {
+                result.push(Location {
+                    uri: Url::from_file_path(&file).expect("Should be valid Url"),
+                    range: Range {
+                        start: Position {
+                            line: line as u32,
+                            character: 0,
+                        },
+                        end: Position {
+                            line: line as u32,
+                            character: ts::offset_to_position(row, row.len(), encoding).character,
+                        },
+                    },
+                });
+            }
+        }
+    }
+    result
+}
+
+// module (or theme) root is the closest directory with registration.php
+fn find_module_root(path: &Path) -> Option<std::path::PathBuf> {
+    path.ancestors()
+        .find(|dir| dir.join("registration.php").is_file())
+        .map(Path::to_path_buf)
+}
+
+fn first_csv_field(row: &str) -> Option<String> {
+    let Some(quoted) = row.strip_prefix('"') else {
+        return row.split(',').next().map(ToString::to_string);
+    };
+    let mut field = String::new();
+    let mut chars = quoted.chars().peekable();
+    while let Some(char) = chars.next() {
+        if char == '"' {
+            if chars.peek() == Some(&'"') {
+                chars.next();
+            } else {
+                return Some(field);
+            }
+        }
+        field.push(char);
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::*;
+    use crate::test_utils::{module_path, TempDir};
+
+    #[test]
+    fn test_find_phrase_in_module_csv_files() {
+        let module_path = module_path(&[]);
+        let path = module_path.append(&["view", "adminhtml", "templates", "admin.phtml"]);
+
+        let mut result = find("Save", &path, &PositionEncodingKind::UTF16)
+            .into_iter()
+            .map(|l| (l.uri, l.range.start.line))
+            .collect::<Vec<_>>();
+        result.sort_by_key(|(uri, _)| uri.to_string());
+
+        assert_eq!(
+            result,
+            vec![
+                (
+                    Url::from_file_path(module_path.append(&["i18n", "de_DE.csv"])).unwrap(),
+                    0
+                ),
+                (
+                    Url::from_file_path(module_path.append(&["i18n", "en_US.csv"])).unwrap(),
+                    1
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_find_phrase_range_ends_at_line_end_in_client_encoding() {
+        let module = TempDir::new("phrase");
+        let path = module.write(&["registration.php"], "<?php");
+        module.write(&["i18n", "de_DE.csv"], "\"Size\",\"Größe\"\n");
+
+        let utf16 = find("Size", &path, &PositionEncodingKind::UTF16);
+        let utf8 = find("Size", &path, &PositionEncodingKind::UTF8);
+
+        assert_eq!(utf16[0].range.end, Position::new(0, 14));
+        assert_eq!(utf8[0].range.end, Position::new(0, 16));
+    }
+
+    #[test]
+    fn test_first_csv_field() {
+        assert_eq!(
+            first_csv_field(r#""Save","Speichern""#),
+            Some("Save".into())
+        );
+        assert_eq!(
+            first_csv_field(r#""Say ""Hi""","Sag ""Hallo""""#),
+            Some(r#"Say "Hi""#.into())
+        );
+        assert_eq!(first_csv_field("Save,Speichern"), Some("Save".into()));
+    }
+}
@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use lsp_types::Location;
 
 use crate::{
@@ -8,24 +10,47 @@ use crate::{
 use super::path_to_location;
 
 pub fn find_admin(state: &State, mod_name: &str, template: &str) -> Vec<Location> {
-    let mut result = vec![];
-    add_phtml_in_mod_location(state, &mut result, mod_name, template, &M2Area::Adminhtml);
-    add_phtml_in_admin_theme_location(state, &mut result, mod_name, template);
-    result
+    find(state, mod_name, template, &M2Area::Adminhtml)
 }
 
 pub fn find_front(state: &State, mod_name: &str, template: &str) -> Vec<Location> {
+    find(state, mod_name, template, &M2Area::Frontend)
+}
+
+pub fn find_base(state: &State, mod_name: &str, template: &str) -> Vec<Location> {
+    find(state, mod_name, template, &M2Area::Base)
+}
+
+fn find(state: &State, mod_name: &str, template: &str, area: &M2Area) -> Vec<Location> {
+    let mut result = find_in_module(state, mod_name, template, area);
+    result.extend(find_in_themes(state, mod_name, template, area));
+    result
+}
+
+pub fn find_in_module(
+    state: &State,
+    mod_name: &str,
+    template: &str,
+    area: &M2Area,
+) -> Vec<Location> {
     let mut result = vec![];
-    add_phtml_in_mod_location(state, &mut result, mod_name, template, &M2Area::Frontend);
-    add_phtml_in_front_theme_location(state, &mut result, mod_name, template);
+    add_phtml_in_mod_location(state, &mut result, mod_name, template, area);
     result
 }
 
-pub fn find_base(state: &State, mod_name: &str, template: &str) -> Vec<Location> {
+pub fn find_in_themes(
+    state: &State,
+    mod_name: &str,
+    template: &str,
+    area: &M2Area,
+) -> Vec<Location> {
     let mut result = vec![];
-    add_phtml_in_mod_location(state, &mut result, mod_name, template, &M2Area::Base);
-    add_phtml_in_front_theme_location(state, &mut result, mod_name, template);
-    add_phtml_in_admin_theme_location(state, &mut result, mod_name, template);
+    if area != &M2Area::Adminhtml {
+        add_phtml_in_front_theme_location(state, &mut result, mod_name, template);
+    }
+    if area != &M2Area::Frontend {
+        add_phtml_in_admin_theme_location(state, &mut result, mod_name, template);
+    }
     result
 }
 
@@ -53,8 +78,7 @@ fn add_phtml_in_admin_theme_location(
     mod_name: &str,
     template: &str,
 ) {
-    #[allow(clippy::significant_drop_in_scrutinee)]
-    for theme_path in state.list_admin_themes_paths() {
+    for theme_path in list_admin_themes_paths(state) {
         let path = theme_path.append(&[mod_name, "templates", template]);
         if let Some(location) = path_to_location(&path) {
             result.push(location);
@@ -76,3 +100,108 @@ fn add_phtml_in_front_theme_location(
         }
     }
 }
+
+fn list_admin_themes_paths(state: &State) -> Vec<PathBuf> {
+    let mut result: Vec<PathBuf> = state
+        .list_admin_themes_paths()
+        .into_iter()
+        .cloned()
+        .collect();
+
+    // Magento/backend is the default admin theme, include it even if it was not indexed
+    for workspace_path in state.workspace_paths() {
+        for backend_path in [
+            workspace_path.append(&["vendor", "magento", "theme-adminhtml-backend"]),
+            workspace_path.append(&["app", "design", "adminhtml", "Magento", "backend"]),
+        ] {
+            if backend_path.is_dir() && !result.contains(&backend_path) {
+                result.push(backend_path);
+            }
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use lsp_types::Url;
+
+    use crate::m2::{self, M2Item};
+
+    use super::*;
+    use crate::test_utils::{fixture_path, module_path, state_with_module};
+
+    fn location_paths(locations: Vec<Location>) -> Vec<PathBuf> {
+        locations
+            .iter()
+            .map(|l| l.uri.to_file_path().expect("Location should be a file"))
+            .collect()
+    }
+
+    fn module_template() -> PathBuf {
+        module_path(&["view", "adminhtml", "templates", "admin.phtml"])
+    }
+
+    fn backend_theme() -> PathBuf {
+        fixture_path(&["vendor", "magento", "theme-adminhtml-backend"])
+    }
+
+    #[test]
+    fn test_find_admin_from_module_when_no_admin_theme_indexed() {
+        let state = state_with_module();
+
+        let result = location_paths(find_admin(&state, "Some_Module", "admin.phtml"));
+
+        assert_eq!(result, vec![module_template()]);
+    }
+
+    #[test]
+    fn test_find_admin_from_indexed_admin_theme() {
+        let mut state = state_with_module();
+        state.add_admin_theme_path("adminhtml/Magento/backend", backend_theme());
+
+        let result = location_paths(find_admin(&state, "Some_Module", "admin.phtml"));
+
+        assert_eq!(
+            result,
+            vec![
+                module_template(),
+                backend_theme().append(&["Some_Module", "templates", "admin.phtml"])
+            ]
+        );
+    }
+
+    #[test]
+    fn test_find_admin_includes_backend_theme_when_not_indexed() {
+        let mut state = state_with_module();
+        state.add_workspace_path(&fixture_path(&[]));
+
+        let result = find_admin(&state, "Some_Module", "admin.phtml");
+
+        assert_eq!(
+            result.last().map(|l| l.uri.clone()),
+            Url::from_file_path(backend_theme().append(&[
+                "Some_Module",
+                "templates",
+                "admin.phtml"
+            ]))
+            .ok()
+        );
+        assert_eq!(result.len(), 2);
+    }
+
+    #[test]
+    fn test_find_admin_with_messy_template_path() {
+        let state = state_with_module();
+        let Some(M2Item::AdminPhtml(mod_name, template)) =
+            m2::try_phtml_item_from_str("Some_Module::.//admin.phtml", &M2Area::Adminhtml)
+        else {
+            panic!("Template should be parsed");
+        };
+
+        let result = location_paths(find_admin(&state, &mod_name, &template));
+
+        assert_eq!(result, vec![module_template()]);
+    }
+}
@@ -0,0 +1,48 @@
+use lsp_types::Location;
+
+use crate::{
+    m2::{M2Area, M2Path},
+    state::State,
+};
+
+use super::path_to_location;
+
+pub fn find(state: &State, area: &M2Area, name: &str) -> Option<Location> {
+    let theme_path = state.get_theme_path(area, name)?;
+    path_to_location(&theme_path.append(&["theme.xml"]))
+        .or_else(|| path_to_location(&theme_path.append(&["registration.php"])))
+}
+
+#[cfg(test)]
+mod test {
+
+    use lsp_types::Url;
+
+    use super::*;
+    use crate::test_utils::fixture_path;
+
+    #[test]
+    fn test_find_parent_theme() {
+        let theme_path = fixture_path(&["app", "design", "frontend", "Some", "parent"]);
+        let mut state = State::new();
+        state.add_front_theme_path("frontend/Some/parent", theme_path.clone());
+
+        let result = find(&state, &M2Area::Frontend, "Some/parent");
+
+        assert_eq!(
+            result.map(|l| l.uri),
+            Url::from_file_path(theme_path.append(&["theme.xml"])).ok()
+        );
+    }
+
+    #[test]
+    fn test_find_parent_theme_from_other_area() {
+        let mut state = State::new();
+        state.add_front_theme_path(
+            "frontend/Some/parent",
+            fixture_path(&["app", "design", "frontend", "Some", "parent"]),
+        );
+
+        assert_eq!(find(&state, &M2Area::Adminhtml, "Some/parent"), None);
+    }
+}
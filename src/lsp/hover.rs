@@ -0,0 +1,298 @@
+use lsp_types::{Hover, HoverContents, HoverParams, MarkupContent, MarkupKind};
+
+use crate::{
+    m2::{M2Area, M2Item, M2Uri},
+    state::State,
+    trace::Trace,
+};
+
+use super::definition;
+
+pub fn get_hover_from_params(state: &State, params: &HoverParams) -> Option<Hover> {
+    let path = params
+        .text_document_position_params
+        .text_document
+        .uri
+        .to_path_buf()?;
+    let pos = params.text_document_position_params.position;
+    match definition::resolve_item(state, &path, pos, &mut Trace::default())? {
+        M2Item::Class(class) => class_hover(state, &class, None).map(markdown_hover),
+        M2Item::Method(class, method) => {
+            class_hover(state, &class, Some(method + "()")).map(markdown_hover)
+        }
+        M2Item::PluggedMethod(classes, method) => {
+            let values: Vec<String> = classes
+                .iter()
+                .filter_map(|class| class_hover(state, class, Some(method.clone() + "()")))
+                .collect();
+            (!values.is_empty()).then(|| markdown_hover(values.join("\n\n---\n\n")))
+        }
+        M2Item::Const(class, constant) => {
+            class_hover(state, &class, Some(constant)).map(markdown_hover)
+        }
+        M2Item::FrontPhtml(mod_name, template) => {
+            phtml_hover(state, &mod_name, &template, &M2Area::Frontend)
+        }
+        M2Item::AdminPhtml(mod_name, template) => {
+            phtml_hover(state, &mod_name, &template, &M2Area::Adminhtml)
+        }
+        M2Item::BasePhtml(mod_name, template) => {
+            phtml_hover(state, &mod_name, &template, &M2Area::Base)
+        }
+        _ => None,
+    }
+}
+
+fn markdown_hover(value: String) -> Hover {
+    Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value,
+        }),
+        range: None,
+    }
+}
+
+fn class_hover(state: &State, class: &str, member: Option<String>) -> Option<String> {
+    let class = state.resolve_virtual_type(class);
+    let phpclass = definition::php::get_php_class_from_class_name(state, &class)?;
+    let file_path = phpclass.uri.to_file_path().ok()?;
+    let name = member.map_or_else(|| class.clone(), |member| format!("{class}::{member}"));
+
+    let mut value = format!("`{}`\n\n{}", name, file_path.display());
+    // blocks without explicit template use the one set in `$_template` property
+    if let Some(template) = phpclass.template {
+        value += &format!("\n\nDefault template: `{template}`");
+    }
+    // source models are option arrays, link straight to the options
+    if let Some(method) = phpclass.methods.get("toOptionArray") {
+        value += &format!(
+            "\n\nProvides options: [toOptionArray()]({}#L{})",
+            phpclass.uri,
+            method.range.start.line + 1
+        );
+    }
+    Some(value)
+}
+
+fn phtml_hover(state: &State, mod_name: &str, template: &str, area: &M2Area) -> Option<Hover> {
+    let name = format!("{mod_name}::{template}");
+    let module = definition::phtml::find_in_module(state, mod_name, template, area);
+    let themes = definition::phtml::find_in_themes(state, mod_name, template, area);
+
+    let mut value = format!("`{name}`");
+    if module.is_empty() && themes.is_empty() {
+        value += "\n\nTemplate is missing";
+    }
+    // theme templates override the module ones
+    for (title, locations) in [("Module templates", module), ("Theme overrides", themes)] {
+        if locations.is_empty() {
+            continue;
+        }
+        value += &format!("\n\n{title}:\n");
+        for location in locations {
+            if let Ok(path) = location.uri.to_file_path() {
+                value += &format!("\n- {}", path.display());
+            }
+        }
+    }
+    Some(markdown_hover(value))
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use lsp_types::{TextDocumentIdentifier, TextDocumentPositionParams, Url};
+
+    use crate::m2::M2Path;
+
+    use super::*;
+    use crate::test_utils::{fixture_path, module_path, split_cursor, state_with_module};
+
+    fn get_test_hover(state: &mut State, xml: &str, path: &str) -> Option<Hover> {
+        let path = PathBuf::from(path);
+        let (content, pos) = split_cursor(xml);
+        state.set_file(&path, content);
+        let params = HoverParams {
+            text_document_position_params: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier {
+                    uri: Url::from_file_path(&path).unwrap(),
+                },
+                position: pos,
+            },
+            work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+        };
+        get_hover_from_params(state, &params)
+    }
+
+    fn hover_value(hover: Option<Hover>) -> Option<String> {
+        match hover?.contents {
+            HoverContents::Markup(markup) => Some(markup.value),
+            _ => None,
+        }
+    }
+
+    fn module_file(parts: &[&str]) -> String {
+        module_path(&[]).append(parts).display().to_string()
+    }
+
+    #[test]
+    fn test_hover_block_class_default_template() {
+        let hover = get_test_hover(
+            &mut state_with_module(),
+            r#"<?xml version="1.0"?>
+            <page>
+                <block class="Some\Module\Block\Wid|get" name="some.widget"/>
+            </page>
+            "#,
+            "/a/b/c/view/frontend/layout/default.xml",
+        );
+
+        assert_eq!(
+            hover_value(hover),
+            Some(format!(
+                "`Some\\Module\\Block\\Widget`\n\n{}\n\nDefault template: `Some_Module::widget.phtml`",
+                module_file(&["Block", "Widget.php"])
+            ))
+        );
+    }
+
+    #[test]
+    fn test_hover_class_without_default_template() {
+        let hover = get_test_hover(
+            &mut state_with_module(),
+            r#"<?xml version="1.0"?>
+            <page>
+                <block class="Some\Module\Model\Con|fig" name="some.config"/>
+            </page>
+            "#,
+            "/a/b/c/view/frontend/layout/default.xml",
+        );
+
+        assert_eq!(
+            hover_value(hover),
+            Some(format!(
+                "`Some\\Module\\Model\\Config`\n\n{}",
+                module_file(&["Model", "Config.php"])
+            ))
+        );
+    }
+
+    #[test]
+    fn test_hover_object_argument_in_di() {
+        let hover = get_test_hover(
+            &mut state_with_module(),
+            r#"<?xml version="1.0"?>
+            <config>
+                <type name="Some\Module\Block\Widget">
+                    <arguments>
+                        <argument name="config" xsi:type="object">Some\Module\Model\Con|fig</argument>
+                    </arguments>
+                </type>
+            </config>
+            "#,
+            "/a/b/c/etc/di.xml",
+        );
+
+        assert_eq!(
+            hover_value(hover),
+            Some(format!(
+                "`Some\\Module\\Model\\Config`\n\n{}",
+                module_file(&["Model", "Config.php"])
+            ))
+        );
+    }
+
+    #[test]
+    fn test_hover_unresolved_class() {
+        let hover = get_test_hover(
+            &mut state_with_module(),
+            r#"<?xml version="1.0"?>
+            <page>
+                <block class="Some\Module\Model\Miss|ing" name="some.missing"/>
+            </page>
+            "#,
+            "/a/b/c/view/frontend/layout/default.xml",
+        );
+
+        assert_eq!(hover, None);
+    }
+
+    #[test]
+    fn test_hover_admin_template_lists_module_and_theme_files() {
+        let mut state = state_with_module();
+        let backend = fixture_path(&["vendor", "magento", "theme-adminhtml-backend"]);
+        state.add_admin_theme_path("adminhtml/Magento/backend", backend.clone());
+
+        let hover = get_test_hover(
+            &mut state,
+            r#"<?xml version="1.0"?>
+            <page>
+                <block class="Some\Block" template="Some_Module::adm|in.phtml"/>
+            </page>
+            "#,
+            "/a/b/c/view/adminhtml/layout/default.xml",
+        );
+
+        assert_eq!(
+            hover_value(hover),
+            Some(format!(
+                "`Some_Module::admin.phtml`\n\nModule templates:\n\n- {}\n\nTheme overrides:\n\n- {}",
+                module_file(&["view", "adminhtml", "templates", "admin.phtml"]),
+                backend
+                    .append(&["Some_Module", "templates", "admin.phtml"])
+                    .display()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_hover_missing_template() {
+        let hover = get_test_hover(
+            &mut state_with_module(),
+            r#"<?xml version="1.0"?>
+            <page>
+                <block class="Some\Block" template="Some_Module::miss|ing.phtml"/>
+            </page>
+            "#,
+            "/a/b/c/view/frontend/layout/default.xml",
+        );
+
+        assert_eq!(
+            hover_value(hover),
+            Some("`Some_Module::missing.phtml`\n\nTemplate is missing".into())
+        );
+    }
+
+    #[test]
+    fn test_hover_source_model_provides_options() {
+        let hover = get_test_hover(
+            &mut state_with_module(),
+            r#"<?xml version="1.0"?>
+            <config>
+                <system>
+                    <section id="some_section">
+                        <group id="general">
+                            <field id="enabled" type="select">
+                                <source_model>Some\Module\Source\Yes|no</source_model>
+                            </field>
+                        </group>
+                    </section>
+                </system>
+            </config>
+            "#,
+            "/a/b/c/etc/adminhtml/system.xml",
+        );
+        let file_path = module_file(&["Source", "Yesno.php"]);
+
+        assert_eq!(
+            hover_value(hover),
+            Some(format!(
+                "`Some\\Module\\Source\\Yesno`\n\n{}\n\nProvides options: [toOptionArray()]({}#L9)",
+                file_path,
+                Url::from_file_path(&file_path).unwrap()
+            ))
+        );
+    }
+}
@@ -0,0 +1,169 @@
+use lsp_types::{Location, ReferenceParams};
+
+use crate::{
+    m2::{M2Item, M2Path, M2Uri},
+    php,
+    state::State,
+    trace::Trace,
+};
+
+use super::definition;
+
+pub fn get_references_from_params(
+    state: &State,
+    params: &ReferenceParams,
+) -> Option<Vec<Location>> {
+    let path = params
+        .text_document_position
+        .text_document
+        .uri
+        .to_path_buf()?;
+    let pos = params.text_document_position.position;
+
+    // in php file it is the class declared there
+    let class = if path.get_ext() == "php" {
+        if !path.is_file() {
+            return None;
+        }
+        php::parse_php_file(&path, state.position_encoding())?.fqn
+    } else {
+        match definition::resolve_item(state, &path, pos, &mut Trace::default())? {
+            M2Item::Class(class) | M2Item::Method(class, _) | M2Item::Const(class, _) => {
+                definition::php::resolve_proxy_class(state, &class).to_string()
+            }
+            _ => return None,
+        }
+    };
+
+    let mut result = vec![];
+    if params.context.include_declaration {
+        result.extend(definition::php::find_class(state, &class));
+    }
+    result.extend(state.get_class_references(&class));
+    Some(result)
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use lsp_types::{
+        Position, ReferenceContext, TextDocumentIdentifier, TextDocumentPositionParams, Url,
+    };
+
+    use super::*;
+    use crate::test_utils::{module_path, state_with_module};
+
+    fn reference_params(path: &PathBuf, line: u32, character: u32) -> ReferenceParams {
+        ReferenceParams {
+            text_document_position: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier {
+                    uri: Url::from_file_path(path).unwrap(),
+                },
+                position: Position { line, character },
+            },
+            work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+            partial_result_params: lsp_types::PartialResultParams::default(),
+            context: ReferenceContext {
+                include_declaration: false,
+            },
+        }
+    }
+
+    fn referenced_lines(locations: Option<Vec<Location>>) -> Vec<(PathBuf, u32)> {
+        let mut result: Vec<(PathBuf, u32)> = locations
+            .unwrap_or_default()
+            .iter()
+            .map(|l| (l.uri.to_file_path().unwrap(), l.range.start.line))
+            .collect();
+        result.sort();
+        result
+    }
+
+    fn state_with_references() -> State {
+        let mut state = state_with_module();
+        state.set_file(
+            &PathBuf::from("/a/b/c/etc/di.xml"),
+            r#"<?xml version="1.0"?>
+            <config>
+                <preference for="Some\Module\Api\ConfigInterface" type="\Some\Module\Model\Config"/>
+                <type name="Some\Module\Block\Widget">
+                    <arguments>
+                        <argument name="config" xsi:type="object">Some\Module\Model\Config\Proxy</argument>
+                    </arguments>
+                </type>
+            </config>
+            "#,
+        );
+        state.set_file(
+            &PathBuf::from("/a/b/c/etc/events.xml"),
+            r#"<?xml version="1.0"?>
+            <config>
+                <event name="some_event">
+                    <observer name="some_observer" instance="Some\Module\Model\Config"/>
+                </event>
+            </config>
+            "#,
+        );
+        state.set_file(
+            &PathBuf::from("/a/b/c/view/frontend/layout/default.xml"),
+            r#"<?xml version="1.0"?>
+            <page>
+                <block class="Some\Module\Model\Config" name="some.block"/>
+            </page>
+            "#,
+        );
+        state
+    }
+
+    #[test]
+    fn test_references_from_xml_class() {
+        let state = state_with_references();
+        let path = PathBuf::from("/a/b/c/view/frontend/layout/default.xml");
+
+        assert_eq!(
+            referenced_lines(get_references_from_params(
+                &state,
+                &reference_params(&path, 2, 40)
+            )),
+            vec![
+                (PathBuf::from("/a/b/c/etc/di.xml"), 2),
+                (PathBuf::from("/a/b/c/etc/di.xml"), 5),
+                (PathBuf::from("/a/b/c/etc/events.xml"), 3),
+                (path, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_references_from_php_class() {
+        let state = state_with_references();
+        let path = module_path(&["Model", "Config.php"]);
+        let mut params = reference_params(&path, 0, 0);
+        params.context.include_declaration = true;
+
+        let result = referenced_lines(get_references_from_params(&state, &params));
+
+        assert_eq!(result.len(), 5);
+        assert!(result.iter().any(|(p, _)| p == &path));
+    }
+
+    #[test]
+    fn test_references_removed_with_source() {
+        let mut state = state_with_references();
+        state.set_file(&PathBuf::from("/a/b/c/etc/di.xml"), "<config/>");
+
+        assert_eq!(
+            state
+                .get_class_references("\\Some\\Module\\Model\\Config")
+                .len(),
+            2
+        );
+        assert_eq!(
+            state
+                .get_class_references("Some\\Module\\Api\\ConfigInterface")
+                .len(),
+            0
+        );
+    }
+}
@@ -0,0 +1,166 @@
+use lsp_types::{
+    ParameterInformation, ParameterLabel, SignatureHelp, SignatureHelpParams, SignatureInformation,
+};
+
+use crate::{
+    m2::{M2Item, M2Path, M2Uri},
+    state::State,
+    trace::Trace,
+    xml,
+};
+
+use super::definition;
+
+pub fn get_signature_help_from_params(
+    state: &State,
+    params: &SignatureHelpParams,
+) -> Option<SignatureHelp> {
+    let path = params
+        .text_document_position_params
+        .text_document
+        .uri
+        .to_path_buf()?;
+    let pos = params.text_document_position_params.position;
+
+    if let Some(M2Item::Method(class, method)) =
+        definition::resolve_item(state, &path, pos, &mut Trace::default())
+    {
+        return signature_help(state, &class, &method, None);
+    }
+    if path.get_ext() != "xml" {
+        return None;
+    }
+
+    // arguments of `<type/>` are passed to its constructor
+    let content = state.get_file_or_read(&path)?;
+    let tree = state.get_tree(&path, &content)?;
+    let encoding = state.position_encoding();
+    let class = xml::get_parent_type_name(&tree, &content, pos, encoding)?;
+    let argument = xml::get_parent_argument_name(&tree, &content, pos, encoding);
+    signature_help(state, &class, "__construct", argument.as_deref())
+}
+
+fn signature_help(
+    state: &State,
+    class: &str,
+    method: &str,
+    argument: Option<&str>,
+) -> Option<SignatureHelp> {
+    let class = state.resolve_virtual_type(class);
+    let phpclass = definition::php::get_php_class_from_class_name(state, &class)?;
+    let php_method = phpclass.methods.get(method)?;
+
+    let active_parameter = argument.and_then(|argument| {
+        php_method
+            .params
+            .iter()
+            .position(|param| param_name(param) == Some(argument))
+            .map(|index| index as u32)
+    });
+    let parameters = php_method
+        .params
+        .iter()
+        .map(|param| ParameterInformation {
+            label: ParameterLabel::Simple(param.clone()),
+            documentation: None,
+        })
+        .collect();
+
+    Some(SignatureHelp {
+        signatures: vec![SignatureInformation {
+            label: format!("{}({})", method, php_method.params.join(", ")),
+            documentation: None,
+            parameters: Some(parameters),
+            active_parameter,
+        }],
+        active_signature: Some(0),
+        active_parameter,
+    })
+}
+
+// `array &...$items = []` is named `items`
+fn param_name(param: &str) -> Option<&str> {
+    param
+        .split_whitespace()
+        .map(|part| part.trim_start_matches(['&', '.']))
+        .find_map(|part| part.strip_prefix('$'))
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use lsp_types::{TextDocumentIdentifier, TextDocumentPositionParams, Url};
+
+    use super::*;
+    use crate::test_utils::{split_cursor, state_with_module};
+
+    fn get_test_signature(xml: &str, path: &str) -> Option<SignatureHelp> {
+        let (content, pos) = split_cursor(xml);
+        let mut state = state_with_module();
+        let path = PathBuf::from(path);
+        state.set_file(&path, content);
+        let params = SignatureHelpParams {
+            context: None,
+            text_document_position_params: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier {
+                    uri: Url::from_file_path(&path).unwrap(),
+                },
+                position: pos,
+            },
+            work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+        };
+        get_signature_help_from_params(&state, &params)
+    }
+
+    #[test]
+    fn test_signature_help_for_constructor_argument() {
+        let help = get_test_signature(
+            r#"<?xml version="1.0"?>
+            <config>
+                <type name="Some\Module\Block\Widget">
+                    <arguments>
+                        <argument name="config" xsi:type="object">Some|</argument>
+                    </arguments>
+                </type>
+            </config>
+            "#,
+            "/a/b/c/etc/di.xml",
+        )
+        .expect("Signature help should be returned");
+
+        assert_eq!(
+            help.signatures[0].label,
+            "__construct(Template\\Context $context, \
+            private readonly \\Some\\Module\\Model\\Config $config, array $data = [])"
+        );
+        assert_eq!(help.active_parameter, Some(1));
+    }
+
+    #[test]
+    fn test_signature_help_for_method_reference() {
+        let help = get_test_signature(
+            r#"<?xml version="1.0"?>
+            <config>
+                <group id="default">
+                    <job name="some_job" instance="Some\Module\Test" method="test|Me"/>
+                </group>
+            </config>
+            "#,
+            "/a/b/c/etc/crontab.xml",
+        )
+        .expect("Signature help should be returned");
+
+        assert_eq!(help.signatures[0].label, "testMe($mode)");
+        assert_eq!(help.active_parameter, None);
+    }
+
+    #[test]
+    fn test_param_name() {
+        assert_eq!(param_name("array &...$items = []"), Some("items"));
+        assert_eq!(
+            param_name("private readonly Config $config"),
+            Some("config")
+        );
+    }
+}
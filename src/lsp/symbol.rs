@@ -0,0 +1,205 @@
+use std::path::PathBuf;
+
+use glob::glob;
+use lsp_types::{
+    DocumentSymbol, DocumentSymbolParams, Location, Range, SymbolInformation, SymbolKind, Url,
+    WorkspaceSymbolParams,
+};
+
+use crate::{
+    m2::{M2Path, M2Uri},
+    state::State,
+    xml,
+};
+
+pub fn get_document_symbols(state: &State, params: &DocumentSymbolParams) -> Vec<DocumentSymbol> {
+    let Some(path) = params.text_document.uri.to_path_buf() else {
+        return vec![];
+    };
+    if !path.ends_with("di.xml") {
+        return vec![];
+    }
+    let Some(content) = state.get_file_or_read(&path) else {
+        return vec![];
+    };
+    state
+        .get_tree(&path, &content)
+        .map_or_else(Vec::new, |tree| {
+            xml::get_di_symbols(&tree, &content, state.position_encoding())
+        })
+}
+
+pub fn get_workspace_symbols(
+    state: &State,
+    params: &WorkspaceSymbolParams,
+) -> Vec<SymbolInformation> {
+    let query = params.query.as_str();
+    let mut result = vec![];
+
+    for module in state.get_modules() {
+        if !fuzzy_match(&module, query) {
+            continue;
+        }
+        if let Some(path) = state.get_module_path(&module) {
+            result.extend(symbol(
+                &module,
+                SymbolKind::MODULE,
+                &path.append(&["registration.php"]),
+            ));
+        }
+    }
+
+    let mut classes = state.get_indexed_classes();
+    classes.extend(module_classes(state, query));
+    classes.sort_unstable();
+    classes.dedup();
+    for class in classes {
+        if !fuzzy_match(&class, query) {
+            continue;
+        }
+        if let Some(path) = class_file(state, &class) {
+            result.extend(symbol(&class, SymbolKind::CLASS, &path));
+        }
+    }
+
+    result
+}
+
+// scanning every module would be too slow, only the ones query is namespaced in
+fn module_classes(state: &State, query: &str) -> Vec<String> {
+    let query = query.trim_start_matches('\\');
+    let mut classes = vec![];
+    for prefix in state.get_module_class_prefixes() {
+        let in_module = query
+            .get(..prefix.len())
+            .is_some_and(|start| start.eq_ignore_ascii_case(&prefix));
+        let Some(module_path) = state.get_module_path(&prefix).filter(|_| in_module) else {
+            continue;
+        };
+        let Ok(candidates) = glob(module_path.append(&["**", "*.php"]).to_path_str()) else {
+            continue;
+        };
+        for path in candidates.filter_map(Result::ok) {
+            let rel_path = path.relative_to(&module_path).str_components().join("\\");
+            if rel_path != "registration.php" {
+                classes.push(format!("{}\\{}", prefix, rel_path.trim_end_matches(".php")));
+            }
+        }
+    }
+    classes
+}
+
+fn class_file(state: &State, class: &str) -> Option<PathBuf> {
+    let (module_paths, suffix) = state.split_class_to_path_and_suffix(class)?;
+    module_paths.into_iter().find_map(|mut file_path| {
+        for part in &suffix {
+            file_path.push(part);
+        }
+        file_path.set_extension("php");
+        file_path.is_file().then_some(file_path)
+    })
+}
+
+// query characters have to appear in the name in the same order
+fn fuzzy_match(name: &str, query: &str) -> bool {
+    let mut name_chars = name.chars().map(|c| c.to_ascii_lowercase());
+    query
+        .chars()
+        .map(|c| c.to_ascii_lowercase())
+        .all(|q| name_chars.any(|c| c == q))
+}
+
+#[allow(deprecated)]
+fn symbol(name: &str, kind: SymbolKind, path: &PathBuf) -> Option<SymbolInformation> {
+    Some(SymbolInformation {
+        name: name.into(),
+        kind,
+        tags: None,
+        deprecated: None,
+        location: Location {
+            uri: Url::from_file_path(path).ok()?,
+            range: Range::default(),
+        },
+        container_name: None,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::state_with_module;
+
+    fn get_test_symbols(state: &State, query: &str) -> Vec<(String, SymbolKind)> {
+        let params = WorkspaceSymbolParams {
+            query: query.into(),
+            work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+            partial_result_params: lsp_types::PartialResultParams::default(),
+        };
+        get_workspace_symbols(state, &params)
+            .into_iter()
+            .map(|s| (s.name, s.kind))
+            .collect()
+    }
+
+    #[test]
+    fn test_workspace_symbols_fuzzy_module_name() {
+        let state = state_with_module();
+
+        assert_eq!(
+            get_test_symbols(&state, "smod"),
+            vec![("Some_Module".to_string(), SymbolKind::MODULE)]
+        );
+    }
+
+    #[test]
+    fn test_workspace_symbols_classes_in_module_namespace() {
+        let state = state_with_module();
+
+        let symbols = get_test_symbols(&state, "Some\\Module\\Model\\Conf");
+
+        assert_eq!(
+            symbols,
+            vec![("Some\\Module\\Model\\Config".to_string(), SymbolKind::CLASS)]
+        );
+    }
+
+    #[test]
+    fn test_workspace_symbols_indexed_classes() {
+        let mut state = state_with_module();
+        state.add_di_type("Some\\Module\\Block\\Widget", "");
+        state.add_di_type("Some\\Module\\Missing", "");
+
+        assert_eq!(
+            get_test_symbols(&state, "widget"),
+            vec![("Some\\Module\\Block\\Widget".to_string(), SymbolKind::CLASS)]
+        );
+    }
+
+    fn get_test_document_symbols(state: &mut State, path: &str, content: &str) -> Vec<String> {
+        let path = PathBuf::from(path);
+        state.set_file(&path, content);
+        let params = DocumentSymbolParams {
+            text_document: lsp_types::TextDocumentIdentifier {
+                uri: Url::from_file_path(&path).unwrap(),
+            },
+            work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+            partial_result_params: lsp_types::PartialResultParams::default(),
+        };
+        get_document_symbols(state, &params)
+            .into_iter()
+            .map(|s| s.name)
+            .collect()
+    }
+
+    #[test]
+    fn test_document_symbols_only_for_di_xml() {
+        let content = r#"<config><type name="Some\Model\Service"/></config>"#;
+        let mut state = State::new();
+
+        assert_eq!(
+            get_test_document_symbols(&mut state, "/a/b/c/etc/frontend/di.xml", content),
+            vec!["Some\\Model\\Service"]
+        );
+        assert!(get_test_document_symbols(&mut state, "/a/b/c/etc/events.xml", content).is_empty());
+    }
+}
@@ -11,14 +11,25 @@ pub enum M2Item {
     RelComponent(String, PathBuf),
     Class(String),
     Method(String, String),
+    // method intercepted by plugin, in every type the plugin is declared for
+    PluggedMethod(Vec<String>, String),
     Const(String, String),
     FrontPhtml(String, String),
     AdminPhtml(String, String),
     BasePhtml(String, String),
+    Theme(M2Area, String),
+    ConfigPath(String),
+    Phrase(String),
+    LayoutElement(String),
+    AclResource(String),
+    LayoutHandle(String),
+    LessImport(String),
+    Module(String),
+    AssetFile(PathBuf),
 }
 
 #[allow(clippy::module_name_repetitions)]
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum M2Area {
     Frontend,
     Adminhtml,
@@ -44,7 +55,7 @@ impl M2Area {
 
 #[allow(clippy::module_name_repetitions)]
 pub trait M2Uri {
-    fn to_path_buf(&self) -> PathBuf;
+    fn to_path_buf(&self) -> Option<PathBuf>;
 }
 
 #[allow(clippy::module_name_repetitions)]
@@ -55,7 +66,6 @@ pub trait M2Path {
     fn append(&self, parts: &[&str]) -> Self;
     fn append_ext(&self, ext: &str) -> Self;
     fn get_ext(&self) -> String;
-    fn is_frontend(&self) -> bool;
     fn is_test(&self) -> bool;
     fn get_area(&self) -> M2Area;
     fn to_path_str(&self) -> &str;
@@ -100,10 +110,14 @@ impl M2Path for PathBuf {
             M2Area::Base
         } else if self.has_components(&["view", "frontend"])
             || self.has_components(&["design", "frontend"])
+            || self.has_components(&["etc", "frontend"])
+            || self.has_components(&["pub", "static", "frontend"])
         {
             M2Area::Frontend
         } else if self.has_components(&["view", "adminhtml"])
             || self.has_components(&["design", "adminhtml"])
+            || self.has_components(&["etc", "adminhtml"])
+            || self.has_components(&["pub", "static", "adminhtml"])
         {
             M2Area::Adminhtml
         } else {
@@ -147,19 +161,23 @@ impl M2Path for PathBuf {
             .to_lowercase()
     }
 
-    fn is_frontend(&self) -> bool {
-        self.has_components(&["view", "frontend"])
-            || self.has_components(&["app", "design", "frontend"])
-    }
-
     fn is_test(&self) -> bool {
         self.has_components(&["dev", "tests"])
     }
 }
 
 impl M2Uri for Url {
-    fn to_path_buf(&self) -> PathBuf {
-        self.to_file_path().expect("Url should convert to PathBuf")
+    fn to_path_buf(&self) -> Option<PathBuf> {
+        if self.scheme() != "file" {
+            return None;
+        }
+        self.to_file_path().ok().or_else(|| {
+            // UNC path (`file://server/share/...`), keep the server as leading `//server`
+            let host = self.host_str()?;
+            let local = Url::parse(&format!("file://{}", self.path())).ok()?;
+            let path = local.to_file_path().ok()?;
+            Some(PathBuf::from(format!("//{host}")).join(path.strip_prefix("/").ok()?))
+        })
     }
 }
 
@@ -181,6 +199,22 @@ pub fn is_part_of_class_name(text: &str) -> bool {
     true
 }
 
+pub fn is_part_of_template_path(text: &str) -> bool {
+    for char in text.chars() {
+        if !char.is_alphanumeric() && !['_', '-', '.', '/'].contains(&char) {
+            return false;
+        }
+    }
+    true
+}
+
+/// Nearest directory above the file that looks like Magento installation
+pub fn find_magento_root(path: &Path) -> Option<PathBuf> {
+    path.ancestors().skip(1).map(Path::to_path_buf).find(|dir| {
+        dir.append(&["app", "etc"]).is_dir() || dir.append(&["vendor", "magento"]).is_dir()
+    })
+}
+
 pub(crate) fn try_any_item_from_str(text: &str, area: &M2Area) -> Option<M2Item> {
     if does_ext_eq(text, "phtml") {
         try_phtml_item_from_str(text, area)
@@ -209,34 +243,47 @@ pub(crate) fn get_class_item_from_str(text: &str) -> M2Item {
 pub(crate) fn try_phtml_item_from_str(text: &str, area: &M2Area) -> Option<M2Item> {
     if text.split("::").count() == 2 {
         let mut parts = text.split("::");
+        let mod_name = parts.next()?;
+        let template = parts.next()?;
+        if mod_name.is_empty()
+            || !is_part_of_module_name(mod_name)
+            || !is_part_of_template_path(template)
+        {
+            return None;
+        }
+        let template = normalize_template_path(template);
         match area {
-            M2Area::Frontend => Some(M2Item::FrontPhtml(
-                parts.next()?.into(),
-                parts.next()?.into(),
-            )),
-            M2Area::Adminhtml => Some(M2Item::AdminPhtml(
-                parts.next()?.into(),
-                parts.next()?.into(),
-            )),
-            M2Area::Base => Some(M2Item::BasePhtml(
-                parts.next()?.into(),
-                parts.next()?.into(),
-            )),
+            M2Area::Frontend => Some(M2Item::FrontPhtml(mod_name.into(), template)),
+            M2Area::Adminhtml => Some(M2Item::AdminPhtml(mod_name.into(), template)),
+            M2Area::Base => Some(M2Item::BasePhtml(mod_name.into(), template)),
         }
     } else {
         None
     }
 }
 
+// collapse `./` and `//` segments, so path can be used for globbing
+fn normalize_template_path(template: &str) -> String {
+    template
+        .split('/')
+        .filter(|part| !part.is_empty() && *part != ".")
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
 fn does_ext_eq(path: &str, ext: &str) -> bool {
     Path::new(path)
         .extension()
-        .map_or(false, |e| e.eq_ignore_ascii_case(ext))
+        .is_some_and(|e| e.eq_ignore_ascii_case(ext))
 }
 
 #[cfg(test)]
 mod test {
-    use crate::m2::M2Path;
+    use std::path::PathBuf;
+
+    use lsp_types::Url;
+
+    use crate::m2::{M2Area, M2Item, M2Path, M2Uri};
 
     #[test]
     fn test_has_components_when_components_in_the_middle() {
@@ -300,4 +347,91 @@ mod test {
     fn test_is_part_of_module_name_when_only_one_letter() {
         assert!(super::is_part_of_module_name("N"));
     }
+
+    #[test]
+    fn test_try_phtml_item_from_str() {
+        assert_eq!(
+            super::try_phtml_item_from_str("Some_Module::path/to-file.phtml", &M2Area::Base),
+            Some(M2Item::BasePhtml(
+                "Some_Module".into(),
+                "path/to-file.phtml".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_try_phtml_item_from_str_with_placeholder_in_module() {
+        assert_eq!(
+            super::try_phtml_item_from_str("{{module}}::file.phtml", &M2Area::Frontend),
+            None
+        );
+    }
+
+    #[test]
+    fn test_try_phtml_item_from_str_with_placeholder_in_path() {
+        assert_eq!(
+            super::try_phtml_item_from_str("Some_Module::{{ path }}/file.phtml", &M2Area::Frontend),
+            None
+        );
+    }
+
+    #[test]
+    fn test_try_phtml_item_from_str_with_messy_path() {
+        assert_eq!(
+            super::try_phtml_item_from_str("Some_Module::./path//to/./file.phtml", &M2Area::Base),
+            Some(M2Item::BasePhtml(
+                "Some_Module".into(),
+                "path/to/file.phtml".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_get_area_from_etc_directory() {
+        let path = std::path::PathBuf::from("app/code/Some/Module/etc/frontend/di.xml");
+        assert_eq!(path.get_area(), M2Area::Frontend);
+        let path = std::path::PathBuf::from("app/code/Some/Module/etc/adminhtml/di.xml");
+        assert_eq!(path.get_area(), M2Area::Adminhtml);
+        let path = std::path::PathBuf::from("app/code/Some/Module/etc/di.xml");
+        assert_eq!(path.get_area(), M2Area::Base);
+    }
+
+    #[test]
+    fn test_get_area_from_pub_static_directory() {
+        let path = std::path::PathBuf::from(
+            "pub/static/frontend/Magento/luma/en_US/Magento_Ui/js/lib/collapsible.js",
+        );
+        assert_eq!(path.get_area(), M2Area::Frontend);
+        let path = std::path::PathBuf::from(
+            "pub/static/adminhtml/Magento/backend/en_US/Magento_Ui/js/lib/collapsible.js",
+        );
+        assert_eq!(path.get_area(), M2Area::Adminhtml);
+        let path = std::path::PathBuf::from("pub/static/_cache/merged/some.js");
+        assert_eq!(path.get_area(), M2Area::Base);
+    }
+
+    #[test]
+    fn test_uri_to_path_buf_decodes_percent_encoding() {
+        let uri = Url::parse("file:///a/b/My%20Module/etc/di%2Bcustom.xml").unwrap();
+        assert_eq!(
+            uri.to_path_buf(),
+            Some(PathBuf::from("/a/b/My Module/etc/di+custom.xml"))
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_uri_to_path_buf_unc_path() {
+        let uri = Url::parse("file://server/share/etc/di.xml").unwrap();
+        assert_eq!(
+            uri.to_path_buf(),
+            Some(PathBuf::from("//server/share/etc/di.xml"))
+        );
+    }
+
+    #[test]
+    fn test_uri_to_path_buf_not_a_file() {
+        let uri = Url::parse("untitled:Untitled-1").unwrap();
+        assert_eq!(uri.to_path_buf(), None);
+    }
 }
@@ -1,29 +1,55 @@
+mod email;
 mod js;
+mod less;
 mod lsp;
 mod m2;
+mod options;
 mod php;
+mod progress;
 mod queries;
 mod state;
+#[cfg(test)]
+mod test_utils;
+mod trace;
 mod ts;
 mod xml;
 
-use std::error::Error;
+use std::{
+    error::Error,
+    sync::Arc,
+    thread::{spawn, JoinHandle},
+    time::{Duration, Instant},
+};
 
 use anyhow::{Context, Result};
-use lsp_server::{Connection, ExtractError, Message, Request, RequestId, Response};
+use lsp_server::{Connection, ExtractError, Message, Notification, Request, RequestId, Response};
 use lsp_types::{
-    request::{Completion, GotoDefinition},
-    CompletionOptions, DidChangeTextDocumentParams, DidCloseTextDocumentParams,
-    DidOpenTextDocumentParams, InitializeParams, OneOf, ServerCapabilities,
+    notification::{DidChangeWatchedFiles, LogMessage, Notification as _, ShowMessage},
+    request::{
+        Completion, DocumentSymbolRequest, ExecuteCommand, GotoDefinition, GotoTypeDefinition,
+        HoverRequest, References, RegisterCapability, Request as _, SignatureHelpRequest,
+        WorkspaceSymbolRequest,
+    },
+    CompletionOptions, DidChangeConfigurationParams, DidChangeTextDocumentParams,
+    DidChangeWatchedFilesParams, DidChangeWatchedFilesRegistrationOptions,
+    DidCloseTextDocumentParams, DidOpenTextDocumentParams, DidSaveTextDocumentParams,
+    ExecuteCommandOptions, FileSystemWatcher, GlobPattern, HoverProviderCapability,
+    InitializeParams, LogMessageParams, MessageType, OneOf, PositionEncodingKind, Registration,
+    RegistrationParams, SaveOptions, ServerCapabilities, ShowMessageParams, SignatureHelpOptions,
     TextDocumentSyncCapability, TextDocumentSyncKind, TextDocumentSyncOptions,
-    WorkDoneProgressOptions,
+    TypeDefinitionProviderCapability, Url, WorkDoneProgressOptions,
 };
 
 use crate::{
     m2::{M2Path, M2Uri},
-    state::State,
+    options::Options,
+    state::{ArcState, State},
+    trace::Trace,
 };
 
+const BUFFER_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+const WATCHED_FILES: [&str; 2] = ["**/registration.php", "**/requirejs-config.js"];
+
 fn main() -> Result<(), Box<dyn Error + Sync + Send>> {
     // Note that  we must have our logging only write out to stderr.
     eprintln!("Starting magento2-ls LSP server");
@@ -33,8 +59,38 @@ fn main() -> Result<(), Box<dyn Error + Sync + Send>> {
     let (connection, io_threads) = Connection::stdio();
 
     // Run the server and wait for the two threads to end (typically by trigger LSP Exit event).
-    let server_capabilities = serde_json::to_value(ServerCapabilities {
+    let (id, initialization_params) = connection.initialize_start()?;
+
+    main_loop(&connection, id, initialization_params)?;
+    io_threads.join()?;
+
+    // Shut down gracefully.
+    eprintln!("shutting down server");
+    Ok(())
+}
+
+fn server_capabilities(position_encoding: PositionEncodingKind) -> ServerCapabilities {
+    ServerCapabilities {
+        position_encoding: Some(position_encoding),
         definition_provider: Some(OneOf::Left(true)),
+        type_definition_provider: Some(TypeDefinitionProviderCapability::Simple(true)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        references_provider: Some(OneOf::Left(true)),
+        workspace_symbol_provider: Some(OneOf::Left(true)),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        execute_command_provider: Some(ExecuteCommandOptions {
+            commands: vec![lsp::REINDEX_COMMAND.into()],
+            work_done_progress_options: WorkDoneProgressOptions {
+                work_done_progress: None,
+            },
+        }),
+        signature_help_provider: Some(SignatureHelpOptions {
+            trigger_characters: Some(vec![String::from("(")]),
+            retrigger_characters: None,
+            work_done_progress_options: WorkDoneProgressOptions {
+                work_done_progress: None,
+            },
+        }),
         completion_provider: Some(CompletionOptions {
             resolve_provider: Some(false),
             trigger_characters: Some(vec![
@@ -44,6 +100,7 @@ fn main() -> Result<(), Box<dyn Error + Sync + Send>> {
                 String::from(":"),
                 String::from("\\"),
                 String::from("/"),
+                String::from("."),
             ]),
             work_done_progress_options: WorkDoneProgressOptions {
                 work_done_progress: None,
@@ -54,50 +111,105 @@ fn main() -> Result<(), Box<dyn Error + Sync + Send>> {
         text_document_sync: Some(TextDocumentSyncCapability::Options(
             TextDocumentSyncOptions {
                 open_close: Some(true),
-                change: Some(TextDocumentSyncKind::FULL), //TODO change to INCREMENTAL
+                change: Some(TextDocumentSyncKind::INCREMENTAL),
                 will_save: None,
                 will_save_wait_until: None,
-                // save: Some(SaveOptions::default().into()),
-                save: None,
+                save: Some(
+                    SaveOptions {
+                        include_text: Some(true),
+                    }
+                    .into(),
+                ),
             },
         )),
         ..Default::default()
-    })
-    .context("Deserializing server capabilities")?;
-    let initialization_params = connection.initialize(server_capabilities)?;
-
-    main_loop(&connection, initialization_params)?;
-    io_threads.join()?;
+    }
+}
 
-    // Shut down gracefully.
-    eprintln!("shutting down server");
-    Ok(())
+// tree-sitter works on bytes, so utf-8 saves conversion if client supports it
+fn negotiate_position_encoding(params: &InitializeParams) -> PositionEncodingKind {
+    let utf8_offered = params
+        .capabilities
+        .general
+        .as_ref()
+        .and_then(|general| general.position_encodings.as_ref())
+        .is_some_and(|encodings| encodings.contains(&PositionEncodingKind::UTF8));
+    if utf8_offered {
+        PositionEncodingKind::UTF8
+    } else {
+        PositionEncodingKind::UTF16
+    }
 }
 
 fn main_loop(
     connection: &Connection,
+    init_id: RequestId,
     init_params: serde_json::Value,
 ) -> Result<(), Box<dyn Error + Sync + Send>> {
     let params: InitializeParams =
         serde_json::from_value(init_params).context("Deserializing initialize params")?;
 
     let state = State::new().into_arc();
+    state
+        .lock()
+        .set_position_encoding(negotiate_position_encoding(&params));
+    let capabilities = server_capabilities(state.lock().position_encoding().clone());
+    connection.initialize_finish(
+        init_id,
+        serde_json::json!({
+            "capabilities": serde_json::to_value(capabilities)
+                .context("Deserializing server capabilities")?,
+        }),
+    )?;
+    state
+        .lock()
+        .set_options(Options::from_value(params.initialization_options));
+    let work_done_progress = params
+        .capabilities
+        .window
+        .as_ref()
+        .and_then(|w| w.work_done_progress);
+    if work_done_progress == Some(true) {
+        state.lock().set_progress_sender(connection.sender.clone());
+    }
+    let watch_files = params
+        .capabilities
+        .workspace
+        .as_ref()
+        .and_then(|w| w.did_change_watched_files.as_ref())
+        .and_then(|w| w.dynamic_registration);
+    if watch_files == Some(true) {
+        connection.sender.send(get_register_watchers_message())?;
+    }
     let mut threads = vec![];
 
     if let Some(uri) = params.root_uri {
-        let path = uri.to_file_path().expect("Invalid root path");
-        threads.extend(State::update_index(&state, &path));
+        threads.extend(index_workspace(connection, &state, &uri)?);
     };
 
     if let Some(folders) = params.workspace_folders {
         for folder in folders {
-            let path = folder.uri.to_file_path().expect("Invalid workspace path");
-            threads.extend(State::update_index(&state, &path));
+            threads.extend(index_workspace(connection, &state, &folder.uri)?);
         }
     }
 
+    let mut last_buffer_sweep = Instant::now();
+
     eprintln!("Starting main loop");
     for msg in &connection.receiver {
+        // option can change at runtime with `workspace/didChangeConfiguration`
+        let buffer_idle_timeout = match state.lock().options().buffer_idle_timeout {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        };
+        if let Some(timeout) = buffer_idle_timeout {
+            if last_buffer_sweep.elapsed() >= BUFFER_SWEEP_INTERVAL {
+                last_buffer_sweep = Instant::now();
+                if let Some(since) = last_buffer_sweep.checked_sub(timeout) {
+                    state.lock().drop_buffers_idle_since(since);
+                }
+            }
+        }
         match msg {
             Message::Request(req) => {
                 #[cfg(debug_assertions)]
@@ -108,56 +220,158 @@ fn main_loop(
                 match req.method.as_str() {
                     "textDocument/completion" => {
                         let (id, params) = cast::<Completion>(req)?;
-                        let result = lsp::completion_handler(&state.lock(), &params);
+                        let indexing = threads.iter().any(|t| !t.is_finished());
+                        let result = lsp::completion_handler(&state.lock(), &params, indexing);
                         connection.sender.send(get_response_message(id, result))?;
                     }
                     "textDocument/definition" => {
                         let (id, params) = cast::<GotoDefinition>(req)?;
-                        let result = lsp::definition_handler(&state.lock(), &params);
+                        let state = state.lock();
+                        let mut trace = Trace::new(state.options().trace_resolution);
+                        let result = lsp::definition_handler(&state, &params, &mut trace);
+                        for step in trace.steps() {
+                            connection.sender.send(get_log_message(step))?;
+                        }
+                        connection.sender.send(get_response_message(id, result))?;
+                    }
+                    "textDocument/typeDefinition" => {
+                        let (id, params) = cast::<GotoTypeDefinition>(req)?;
+                        let result = lsp::type_definition_handler(&state.lock(), &params);
+                        connection.sender.send(get_response_message(id, result))?;
+                    }
+                    "textDocument/hover" => {
+                        let (id, params) = cast::<HoverRequest>(req)?;
+                        let result = lsp::hover_handler(&state.lock(), &params);
+                        connection.sender.send(get_response_message(id, result))?;
+                    }
+                    "textDocument/references" => {
+                        let (id, params) = cast::<References>(req)?;
+                        let result = lsp::references_handler(&state.lock(), &params);
+                        connection.sender.send(get_response_message(id, result))?;
+                    }
+                    "textDocument/signatureHelp" => {
+                        let (id, params) = cast::<SignatureHelpRequest>(req)?;
+                        let result = lsp::signature_help_handler(&state.lock(), &params);
+                        connection.sender.send(get_response_message(id, result))?;
+                    }
+                    "textDocument/documentSymbol" => {
+                        let (id, params) = cast::<DocumentSymbolRequest>(req)?;
+                        let result = lsp::document_symbol_handler(&state.lock(), &params);
+                        connection.sender.send(get_response_message(id, result))?;
+                    }
+                    "workspace/symbol" => {
+                        let (id, params) = cast::<WorkspaceSymbolRequest>(req)?;
+                        let result = lsp::workspace_symbol_handler(&state.lock(), &params);
                         connection.sender.send(get_response_message(id, result))?;
                     }
+                    "magento2-ls/targets" => {
+                        let (id, params) = cast::<lsp::Targets>(req)?;
+                        let result = lsp::targets_handler(&state.lock(), &params);
+                        connection.sender.send(get_response_message(id, result))?;
+                    }
+                    "magento2-ls/reindexModule" => {
+                        let (id, params) = cast::<lsp::ReindexModule>(req)?;
+                        let state = Arc::clone(&state);
+                        let sender = connection.sender.clone();
+                        // respond once indexed, without blocking other requests
+                        threads.push(spawn(move || {
+                            let result = lsp::reindex_module_handler(&state, &params);
+                            sender.send(get_response_message(id, result)).ok();
+                        }));
+                    }
+                    "workspace/executeCommand" => {
+                        let (id, params) = cast::<ExecuteCommand>(req)?;
+                        if params.command == lsp::REINDEX_COMMAND {
+                            let state = Arc::clone(&state);
+                            let sender = connection.sender.clone();
+                            // respond once indexed, without blocking other requests
+                            threads.push(spawn(move || {
+                                let result = lsp::reindex_handler(&state);
+                                sender.send(get_response_message(id, result)).ok();
+                            }));
+                        } else {
+                            eprintln!("unhandled command: {:?}", params.command);
+                            let result = serde_json::Value::Null;
+                            connection.sender.send(get_response_message(id, result))?;
+                        }
+                    }
                     _ => {
                         eprintln!("unhandled request: {:?}", req.method);
                     }
                 }
             }
-            Message::Response(_resp) => {
-                #[cfg(debug_assertions)]
-                eprintln!("response: {_resp:?}");
+            Message::Response(resp) => {
+                if !state.lock().handle_progress_response(&resp) {
+                    #[cfg(debug_assertions)]
+                    eprintln!("response: {resp:?}");
+                }
             }
             Message::Notification(not) => match not.method.as_str() {
                 "textDocument/didOpen" => {
                     let params: DidOpenTextDocumentParams = serde_json::from_value(not.params)
                         .context("Deserializing notification params")?;
-                    let path = params.text_document.uri.to_path_buf();
-                    state.lock().set_file(&path, params.text_document.text);
+                    let Some(path) = params.text_document.uri.to_path_buf() else {
+                        continue;
+                    };
+                    state.lock().open_file(&path, params.text_document.text);
+                    threads.extend(State::index_file_workspace(&state, &path));
                     #[cfg(debug_assertions)]
                     eprintln!("textDocument/didOpen: {path:?}");
                 }
                 "textDocument/didChange" => {
                     let params: DidChangeTextDocumentParams = serde_json::from_value(not.params)
                         .context("Deserializing notification params")?;
-                    let path = params.text_document.uri.to_path_buf();
+                    let Some(path) = params.text_document.uri.to_path_buf() else {
+                        continue;
+                    };
                     match path.get_ext().as_str() {
-                        "js" | "xml" => state
-                            .lock()
-                            .set_file(&path, &params.content_changes[0].text),
-                        "php" if path.ends_with("registration.php") => state
-                            .lock()
-                            .set_file(&path, &params.content_changes[0].text),
+                        "js" | "xml" | "phtml" | "php" | "html" | "txt" | "less" | "css" => {
+                            state.lock().change_file(&path, &params.content_changes);
+                        }
                         _ => (),
                     }
                     #[cfg(debug_assertions)]
                     eprintln!("textDocument/didChange: {path:?}");
                 }
+                "textDocument/didSave" => {
+                    let params: DidSaveTextDocumentParams = serde_json::from_value(not.params)
+                        .context("Deserializing notification params")?;
+                    let Some(path) = params.text_document.uri.to_path_buf() else {
+                        continue;
+                    };
+                    state.lock().save_file(&path, params.text);
+                    #[cfg(debug_assertions)]
+                    eprintln!("textDocument/didSave: {path:?}");
+                }
                 "textDocument/didClose" => {
                     let params: DidCloseTextDocumentParams = serde_json::from_value(not.params)
                         .context("Deserializing notification params")?;
-                    let path = params.text_document.uri.to_path_buf();
+                    let Some(path) = params.text_document.uri.to_path_buf() else {
+                        continue;
+                    };
                     state.lock().del_file(&path);
                     #[cfg(debug_assertions)]
                     eprintln!("textDocument/didClose: {path:?}");
                 }
+                "workspace/didChangeWatchedFiles" => {
+                    let params: DidChangeWatchedFilesParams = serde_json::from_value(not.params)
+                        .context("Deserializing notification params")?;
+                    let mut state = state.lock();
+                    for change in params.changes {
+                        if let Some(path) = change.uri.to_path_buf() {
+                            state.update_file_from_disk(&path);
+                        }
+                    }
+                }
+                "workspace/didChangeConfiguration" => {
+                    let params: DidChangeConfigurationParams =
+                        serde_json::from_value(not.params)
+                            .context("Deserializing notification params")?;
+                    let options = state.lock().options().merge_settings(params.settings);
+                    if let Some(options) = options {
+                        threads.extend(State::update_options(&state, options));
+                    }
+                }
                 _ => {
                     eprintln!("unhandled notification: {:?}", not.method);
                 }
@@ -172,6 +386,23 @@ fn main_loop(
     Ok(())
 }
 
+fn index_workspace(
+    connection: &Connection,
+    state: &ArcState,
+    uri: &Url,
+) -> Result<Vec<JoinHandle<()>>, Box<dyn Error + Sync + Send>> {
+    if let Ok(path) = uri.to_file_path() {
+        Ok(State::update_index(state, &path))
+    } else {
+        let message = format!("magento2-ls: can not index workspace, not a local path: {uri}");
+        eprintln!("{message}");
+        connection
+            .sender
+            .send(get_show_message(MessageType::ERROR, &message))?;
+        Ok(vec![])
+    }
+}
+
 fn get_response_message<T>(id: RequestId, result: T) -> Message
 where
     T: serde::Serialize,
@@ -184,6 +415,54 @@ where
     })
 }
 
+fn get_log_message(message: &str) -> Message {
+    let params = LogMessageParams {
+        typ: MessageType::LOG,
+        message: message.into(),
+    };
+    Message::Notification(Notification {
+        method: LogMessage::METHOD.into(),
+        params: serde_json::to_value(params).expect("Error serializing log message"),
+    })
+}
+
+// new modules and requirejs configs show up on disk, e.g. after `composer install`
+fn get_register_watchers_message() -> Message {
+    let watchers = WATCHED_FILES
+        .iter()
+        .map(|pattern| FileSystemWatcher {
+            glob_pattern: GlobPattern::String((*pattern).into()),
+            kind: None,
+        })
+        .collect();
+    let params = RegistrationParams {
+        registrations: vec![Registration {
+            id: "magento2-ls/watchers".into(),
+            method: DidChangeWatchedFiles::METHOD.into(),
+            register_options: Some(
+                serde_json::to_value(DidChangeWatchedFilesRegistrationOptions { watchers })
+                    .expect("Error serializing watchers"),
+            ),
+        }],
+    };
+    Message::Request(Request::new(
+        RequestId::from("magento2-ls/watchers".to_string()),
+        RegisterCapability::METHOD.into(),
+        params,
+    ))
+}
+
+fn get_show_message(typ: MessageType, message: &str) -> Message {
+    let params = ShowMessageParams {
+        typ,
+        message: message.into(),
+    };
+    Message::Notification(Notification {
+        method: ShowMessage::METHOD.into(),
+        params: serde_json::to_value(params).expect("Error serializing show message"),
+    })
+}
+
 fn cast<R>(req: Request) -> Result<(RequestId, R::Params), ExtractError<Request>>
 where
     R: lsp_types::request::Request,
@@ -191,3 +470,54 @@ where
 {
     req.extract(R::METHOD)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_index_workspace_with_non_file_uri() {
+        let (server, client) = Connection::memory();
+        let state = State::new().into_arc();
+        let uri = Url::parse("ssh://remote/var/www/magento").unwrap();
+
+        let threads = index_workspace(&server, &state, &uri).expect("Server should stay alive");
+
+        assert!(threads.is_empty());
+        let Ok(Message::Notification(notification)) = client.receiver.try_recv() else {
+            panic!("Error message should be sent to the client");
+        };
+        assert_eq!(notification.method, ShowMessage::METHOD);
+        assert!(state.lock().workspace_paths().is_empty());
+    }
+
+    fn initialize_params(encodings: Option<Vec<PositionEncodingKind>>) -> InitializeParams {
+        serde_json::from_value(serde_json::json!({
+            "capabilities": {
+                "general": { "positionEncodings": encodings },
+            },
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_negotiate_position_encoding() {
+        assert_eq!(
+            negotiate_position_encoding(&initialize_params(Some(vec![
+                PositionEncodingKind::UTF16,
+                PositionEncodingKind::UTF8,
+            ]))),
+            PositionEncodingKind::UTF8
+        );
+        assert_eq!(
+            negotiate_position_encoding(&initialize_params(Some(vec![
+                PositionEncodingKind::UTF32
+            ]))),
+            PositionEncodingKind::UTF16
+        );
+        assert_eq!(
+            negotiate_position_encoding(&initialize_params(None)),
+            PositionEncodingKind::UTF16
+        );
+    }
+}
@@ -0,0 +1,153 @@
+use std::path::Path;
+
+use glob::Pattern;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Options {
+    pub trace_resolution: bool,
+    pub case_insensitive_completion: bool,
+    pub lightweight_indexing: bool,
+    pub buffer_idle_timeout: u64,
+    pub scope_completion_to_module: bool,
+    pub exclude_paths: Vec<String>,
+}
+
+impl Options {
+    pub fn from_value(value: Option<serde_json::Value>) -> Self {
+        value
+            .and_then(|value| {
+                serde_json::from_value(value)
+                    .map_err(|e| eprintln!("Invalid options: {e}"))
+                    .ok()
+            })
+            .unwrap_or_default()
+    }
+
+    // settings can be namespaced by server name, as most clients send them,
+    // only keys present in settings override current options
+    pub fn merge_settings(&self, settings: serde_json::Value) -> Option<Self> {
+        let settings = match settings {
+            serde_json::Value::Object(mut map) if map.contains_key("magento2-ls") => {
+                map.remove("magento2-ls")?
+            }
+            settings => settings,
+        };
+        let serde_json::Value::Object(settings) = settings else {
+            return None;
+        };
+        let serde_json::Value::Object(mut merged) = serde_json::to_value(self).ok()? else {
+            return None;
+        };
+        let mut has_options = false;
+        for (key, value) in settings {
+            if let Some(current) = merged.get_mut(&key) {
+                *current = value;
+                has_options = true;
+            }
+        }
+        if !has_options {
+            return None;
+        }
+        serde_json::from_value(serde_json::Value::Object(merged))
+            .map_err(|e| eprintln!("Invalid options: {e}"))
+            .ok()
+    }
+
+    pub fn is_excluded(&self, path: &Path) -> bool {
+        self.exclude_paths
+            .iter()
+            .any(|pattern| Pattern::new(pattern).is_ok_and(|p| p.matches_path(path)))
+    }
+
+    // changing these makes current index stale
+    pub fn affects_index(&self, other: &Self) -> bool {
+        self.lightweight_indexing != other.lightweight_indexing
+            || self.exclude_paths != other.exclude_paths
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_options_from_value() {
+        let options = Options::from_value(Some(serde_json::json!({
+            "trace_resolution": true,
+            "case_insensitive_completion": true,
+            "lightweight_indexing": true,
+            "buffer_idle_timeout": 3600,
+            "scope_completion_to_module": true,
+            "exclude_paths": ["**/dev/**"],
+        })));
+        assert!(options.trace_resolution);
+        assert!(options.case_insensitive_completion);
+        assert!(options.lightweight_indexing);
+        assert_eq!(options.buffer_idle_timeout, 3600);
+        assert!(options.scope_completion_to_module);
+        assert_eq!(options.exclude_paths, vec!["**/dev/**"]);
+    }
+
+    #[test]
+    fn test_options_merge_settings() {
+        let options = Options::default();
+        let namespaced = serde_json::json!({"magento2-ls": {"lightweight_indexing": true}});
+        let flat = serde_json::json!({"lightweight_indexing": true});
+
+        assert!(options
+            .merge_settings(namespaced)
+            .is_some_and(|o| o.lightweight_indexing));
+        assert!(options
+            .merge_settings(flat)
+            .is_some_and(|o| o.lightweight_indexing));
+        assert_eq!(options.merge_settings(serde_json::Value::Null), None);
+    }
+
+    #[test]
+    fn test_options_merge_settings_keeps_unspecified() {
+        let options = Options {
+            case_insensitive_completion: true,
+            exclude_paths: vec!["**/dev/**".into()],
+            ..Options::default()
+        };
+
+        let merged = options
+            .merge_settings(serde_json::json!({"magento2-ls": {"lightweight_indexing": true}}))
+            .unwrap();
+        assert!(merged.lightweight_indexing);
+        assert!(merged.case_insensitive_completion);
+        assert_eq!(merged.exclude_paths, vec!["**/dev/**"]);
+    }
+
+    #[test]
+    fn test_options_merge_settings_ignores_other_servers() {
+        let options = Options {
+            exclude_paths: vec!["**/dev/**".into()],
+            ..Options::default()
+        };
+
+        let other = serde_json::json!({"intelephense": {"files": {"exclude": []}}});
+        assert_eq!(options.merge_settings(other), None);
+        let invalid = serde_json::json!({"magento2-ls": {"exclude_paths": "**/dev/**"}});
+        assert_eq!(options.merge_settings(invalid), None);
+    }
+
+    #[test]
+    fn test_options_is_excluded() {
+        let options = Options {
+            exclude_paths: vec!["**/vendor/magento/**".into()],
+            ..Options::default()
+        };
+
+        assert!(options.is_excluded(Path::new("/m2/vendor/magento/module-ui/etc/di.xml")));
+        assert!(!options.is_excluded(Path::new("/m2/app/code/Some/Module/etc/di.xml")));
+    }
+
+    #[test]
+    fn test_options_from_invalid_value() {
+        let options = Options::from_value(Some(serde_json::json!("invalid")));
+        assert_eq!(options, Options::default());
+    }
+}
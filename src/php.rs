@@ -5,14 +5,15 @@ use std::{
 
 use convert_case::{Case, Casing};
 use glob::glob;
-use lsp_types::{Position, Range, Url};
-use tree_sitter::{Node, QueryCursor};
+use lsp_types::{Position, PositionEncodingKind, Range, Url};
+use tree_sitter::{Node, QueryCursor, Tree};
 
 use crate::{
-    m2::M2Path,
+    m2::{self, M2Area, M2Item, M2Path},
+    progress::Progress,
     queries,
-    state::{ArcState, State},
-    ts::{self, get_range_from_node},
+    state::{self, ArcState, State},
+    ts::{self, get_range_from_node, node_at_position},
 };
 
 #[derive(Debug, Clone)]
@@ -22,16 +23,43 @@ pub struct PHPClass {
     pub range: Range,
     pub methods: HashMap<String, PHPMethod>,
     pub constants: HashMap<String, PHPConst>,
+    pub parents: Vec<String>,
+    // parent class, or interfaces extended by interface
+    pub extends: Vec<String>,
+    pub traits: Vec<String>,
+    pub constructor_params: Vec<String>,
+    pub api: bool,
+    pub template: Option<String>,
+}
+
+/// Parts of the class declaration class completion ranks and filters by
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClassSummary {
+    pub api: bool,
+    pub parents: Vec<String>,
+}
+
+impl From<&PHPClass> for ClassSummary {
+    fn from(phpclass: &PHPClass) -> Self {
+        Self {
+            api: phpclass.api,
+            parents: phpclass.parents.clone(),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct PHPMethod {
+    #[allow(dead_code)]
     pub name: String,
     pub range: Range,
+    // raw parameter declarations, as written in the method signature
+    pub params: Vec<String>,
 }
 
 #[derive(Debug, Clone)]
 pub struct PHPConst {
+    #[allow(dead_code)]
     pub name: String,
     pub range: Range,
 }
@@ -80,25 +108,15 @@ fn register_param_to_module(param: &str) -> Option<M2Module> {
     }
 }
 
-pub fn update_index(state: &ArcState, path: &PathBuf) {
-    // if current workspace is magento module
-    process_glob(state, &path.append(&["registration.php"]));
-    // if current workspace is magento installation
-    process_glob(
-        state,
-        &path.append(&["vendor", "*", "*", "registration.php"]),
-    ); // vendor modules / themes
-    process_glob(
-        state,
-        &path.append(&["app", "code", "*", "*", "registration.php"]),
-    ); // local modules
-    process_glob(
-        state,
-        &path.append(&["app", "design", "*", "*", "*", "registration.php"]),
-    ); // local themes
-    process_glob(
-        state,
-        &path.append(&[
+pub fn update_index(state: &ArcState, path: &PathBuf, progress: &Progress) {
+    let glob_paths = [
+        // if current workspace is magento module
+        path.append(&["registration.php"]),
+        // if current workspace is magento installation
+        path.append(&["vendor", "*", "*", "registration.php"]), // vendor modules / themes
+        path.append(&["app", "code", "*", "*", "registration.php"]), // local modules
+        path.append(&["app", "design", "*", "*", "*", "registration.php"]), // local themes
+        path.append(&[
             "vendor",
             "magento",
             "magento2-base",
@@ -107,49 +125,104 @@ pub fn update_index(state: &ArcState, path: &PathBuf) {
             "Magento",
             "Setup",
             "registration.php",
-        ]),
-    ); // magento2-base setup module
+        ]), // magento2-base setup module
+    ];
+    for (done, glob_path) in glob_paths.iter().enumerate() {
+        process_glob(state, glob_path);
+        progress.report(done + 1, glob_paths.len());
+    }
 }
 
-pub fn maybe_index_file(state: &mut State, content: &str, file_path: &PathBuf) {
+pub fn maybe_index_file(state: &mut State, content: &str, tree: &Tree, file_path: &PathBuf) {
     if file_path.to_path_str().ends_with("registration.php") {
-        update_index_from_registration(state, content, file_path);
+        update_index_from_registration(state, content, tree, file_path);
     }
 }
 
-fn update_index_from_registration(state: &mut State, content: &str, file_path: &Path) {
+fn update_index_from_registration(state: &mut State, content: &str, tree: &Tree, file_path: &Path) {
     state.set_source_file(file_path);
     let query = queries::php_registration();
-    let tree = tree_sitter_parsers::parse(content, "php");
     let mut cursor = QueryCursor::new();
     let matches = cursor.matches(query, tree.root_node(), content.as_bytes());
+    // every register call in the file points at the same directory
+    let mut parent = file_path.to_path_buf();
+    parent.pop();
+
     for m in matches {
         let mod_name = ts::get_node_str(m.captures[1].node, content)
             .trim_matches('"')
             .trim_matches('\'');
 
-        let mut parent = file_path.to_path_buf();
-        parent.pop();
+        add_registration(state, mod_name, &parent);
+    }
+}
 
-        state.add_module_path(mod_name, parent.clone());
+fn add_registration(state: &mut State, mod_name: &str, parent: &Path) {
+    state.add_module_path(mod_name, parent.to_path_buf());
 
-        match register_param_to_module(mod_name) {
-            Some(M2Module::Module(m)) => {
-                state.add_module(mod_name).add_module_path(m, parent);
-            }
-            Some(M2Module::Library(l)) => {
-                state
-                    .add_module(&l.replace('\\', "_"))
-                    .add_module_path(l, parent);
+    match register_param_to_module(mod_name) {
+        Some(M2Module::Module(m)) => {
+            state
+                .add_module(mod_name)
+                .add_module_path(m, parent.to_path_buf());
+        }
+        Some(M2Module::Library(l)) => {
+            state
+                .add_module(&l.replace('\\', "_"))
+                .add_module_path(l, parent.to_path_buf());
+        }
+        Some(M2Module::FrontTheme(t)) => {
+            state.add_front_theme_path(t, parent.to_path_buf());
+        }
+        Some(M2Module::AdminTheme(t)) => {
+            state.add_admin_theme_path(t, parent.to_path_buf());
+        }
+        _ => (),
+    }
+}
+
+pub fn update_index_lightweight(state: &ArcState, path: &PathBuf, progress: &Progress) {
+    let glob_paths = [
+        path.append(&["vendor", "*", "*", "registration.php"]),
+        path.append(&["app", "code", "*", "*", "registration.php"]),
+        path.append(&["app", "design", "*", "*", "*", "registration.php"]),
+    ];
+    for (done, glob_path) in glob_paths.iter().enumerate() {
+        let files = glob(glob_path.to_path_str())
+            .expect("Failed to read glob pattern")
+            .filter_map(Result::ok);
+
+        for file_path in files {
+            if state.lock().options().is_excluded(&file_path) {
+                continue;
             }
-            Some(M2Module::FrontTheme(t)) => {
-                state.add_front_theme_path(t, parent);
+            if let Some(mod_name) = registration_path_to_param(&file_path) {
+                let mut state = state.lock();
+                state.set_source_file(&file_path);
+                add_registration(&mut state, &mod_name, file_path.parent().unwrap_or(path));
             }
-            Some(M2Module::AdminTheme(t)) => {
-                state.add_admin_theme_path(t, parent);
+        }
+        progress.report(done + 1, glob_paths.len());
+    }
+}
+
+// guess what `registration.php` registers from directory layout, without reading it
+fn registration_path_to_param(file_path: &PathBuf) -> Option<String> {
+    let parts = file_path.str_components();
+    let len = parts.len();
+    match parts.get(len.checked_sub(5)?..len - 1)? {
+        ["app", "code", vendor, module] => Some(format!("{vendor}_{module}")),
+        ["design", area, vendor, theme] => Some(format!("{area}/{vendor}/{theme}")),
+        [_, "vendor", vendor, package] => {
+            let vendor = vendor.to_case(Case::Pascal);
+            if let Some(module) = package.strip_prefix("module-") {
+                Some(format!("{}_{}", vendor, module.to_case(Case::Pascal)))
+            } else {
+                let (area, theme) = package.strip_prefix("theme-")?.split_once('-')?;
+                Some(format!("{area}/{vendor}/{theme}"))
             }
-            _ => (),
         }
+        _ => None,
     }
 }
 
@@ -162,17 +235,181 @@ fn process_glob(state: &ArcState, glob_path: &PathBuf) {
         if file_path.is_test() {
             return;
         }
+        if state.lock().options().is_excluded(&file_path) {
+            continue;
+        }
 
-        let content =
-            std::fs::read_to_string(&file_path).expect("Should have been able to read the file");
+        let Some(content) = state::read_indexed_file(&file_path) else {
+            continue;
+        };
+
+        let tree = tree_sitter_parsers::parse(&content, "php");
+        update_index_from_registration(&mut state.lock(), &content, &tree, &file_path);
+    }
+}
+
+pub fn get_item_from_position(state: &State, path: &PathBuf, pos: Position) -> Option<M2Item> {
+    let content = state.get_file_or_read(path)?;
+    let tree = state.get_tree(path, &content)?;
+    get_item_from_pos(state, &content, &tree, path, pos)
+}
+
+fn get_item_from_pos(
+    state: &State,
+    content: &str,
+    tree: &Tree,
+    path: &PathBuf,
+    pos: Position,
+) -> Option<M2Item> {
+    let query = queries::phtml_item_from_pos();
+    let mut cursor = QueryCursor::new();
+    let matches = cursor.matches(query, tree.root_node(), content.as_bytes());
+
+    let mut item = None;
+    for m in matches {
+        let node = m.captures[m.captures.len() - 1].node;
+        if !node_at_position(node, content, pos, state.position_encoding()) {
+            continue;
+        }
+        let text = ts::get_node_str(node, content).trim_matches(|c| c == '\'' || c == '"');
+        if m.pattern_index == 0 {
+            return text_to_view_file(state, text);
+        }
+        if m.pattern_index == 2 {
+            return Some(M2Item::Phrase(text.replace("\\'", "'")));
+        }
+        item = m2::try_phtml_item_from_str(text, &path.get_area());
+    }
+    item
+}
+
+pub fn get_class_item_from_position(
+    state: &State,
+    path: &PathBuf,
+    pos: Position,
+) -> Option<M2Item> {
+    let content = state.get_file_or_read(path)?;
+    // parsed once for every check
+    let tree = state.get_tree(path, &content)?;
+    get_block_item_from_pos(&content, &tree, path, pos, state.position_encoding())
+        .or_else(|| get_controller_item_from_pos(state, &content, &tree, path, pos))
+        .or_else(|| get_plugin_item_from_pos(state, &content, &tree, pos))
+}
+
+// plugin `beforeSave`, `aroundSave` and `afterSave` methods intercept `save` of plugged type
+fn get_plugin_item_from_pos(
+    state: &State,
+    content: &str,
+    tree: &Tree,
+    pos: Position,
+) -> Option<M2Item> {
+    let point = ts::position_to_point(content, pos, state.position_encoding());
+    let node = tree.root_node().descendant_for_point_range(point, point)?;
+    let method_node = node.parent()?;
+    if node.kind() != "name" || method_node.kind() != "method_declaration" {
+        return None;
+    }
+    let name = ts::get_node_str(node, content);
+    let target = ["before", "around", "after"]
+        .iter()
+        .find_map(|prefix| name.strip_prefix(prefix))
+        .filter(|target| target.starts_with(char::is_uppercase))?;
+    let target = target[..1].to_lowercase() + &target[1..];
 
-        update_index_from_registration(&mut state.lock(), &content, &file_path);
+    let class_node = method_node.parent()?.parent()?;
+    let class_name = ts::get_node_str(class_node.child_by_field_name("name")?, content);
+    let mut cursor = tree.root_node().walk();
+    let namespace = tree
+        .root_node()
+        .children(&mut cursor)
+        .find(|child| child.kind() == "namespace_definition")
+        .and_then(|ns| ns.child_by_field_name("name"))
+        .map(|ns| ts::get_node_str(ns, content));
+    let class = namespace.map_or_else(|| class_name.into(), |ns| format!("{ns}\\{class_name}"));
+
+    let types = state.get_plugged_types(&class);
+    if types.is_empty() {
+        return None;
+    }
+    Some(M2Item::PluggedMethod(types, target))
+}
+
+// block classes point at their default template with `_template` property
+fn get_block_item_from_pos(
+    content: &str,
+    tree: &Tree,
+    path: &PathBuf,
+    pos: Position,
+    encoding: &PositionEncodingKind,
+) -> Option<M2Item> {
+    let query = queries::php_item_from_pos();
+    let mut cursor = QueryCursor::new();
+    let matches = cursor.matches(query, tree.root_node(), content.as_bytes());
+
+    for m in matches {
+        let node = m.captures[1].node;
+        if node_at_position(node, content, pos, encoding) {
+            let text = ts::get_node_str(node, content).trim_matches(|c| c == '\'' || c == '"');
+            return m2::try_phtml_item_from_str(text, &path.get_area());
+        }
+    }
+    None
+}
+
+// controller action renders layout handle named after route id, controller path and action,
+// so `Controller/Adminhtml/Order/View.php` of `sales` route is `sales_order_view` handle
+fn get_controller_item_from_pos(
+    state: &State,
+    content: &str,
+    tree: &Tree,
+    path: &PathBuf,
+    pos: Position,
+) -> Option<M2Item> {
+    let point = ts::position_to_point(content, pos, state.position_encoding());
+    let node = tree.root_node().descendant_for_point_range(point, point)?;
+    if node.kind() != "name" || node.parent()?.kind() != "class_declaration" {
+        return None;
+    }
+
+    let module = state.get_module_class_prefix_for_path(path)?;
+    let module_path = state
+        .get_module_paths(&module)
+        .into_iter()
+        .find(|module_path| path.starts_with(module_path))?;
+    let rel_path = path.relative_to(module_path);
+    let mut parts = rel_path.str_components();
+    if parts.first() != Some(&"Controller") {
+        return None;
+    }
+    parts.remove(0);
+    let area = if parts.first() == Some(&"Adminhtml") {
+        parts.remove(0);
+        M2Area::Adminhtml
+    } else {
+        M2Area::Frontend
+    };
+    let action = parts.pop()?.trim_end_matches(".php");
+    if parts.is_empty() {
+        return None;
     }
+
+    let route_id = state.get_route(&area, &module.replace('\\', "_"))?;
+    let handle = format!("{}_{}_{}", route_id, parts.join("_"), action);
+    Some(M2Item::LayoutHandle(handle.to_lowercase()))
 }
 
-pub fn parse_php_file(file_path: &PathBuf) -> Option<PHPClass> {
-    let content =
-        std::fs::read_to_string(file_path).expect("Should have been able to read the file");
+// view files are resolved the same way as html templates, from module web dir
+pub fn text_to_view_file(state: &State, text: &str) -> Option<M2Item> {
+    let (mod_name, file_path) = text.split_once("::")?;
+    if !m2::is_part_of_module_name(mod_name) || file_path.is_empty() {
+        return None;
+    }
+    let mod_path = state.get_module_path(mod_name)?;
+    Some(M2Item::ModHtml(mod_name.into(), file_path.into(), mod_path))
+}
+
+pub fn parse_php_file(file_path: &Path, encoding: &PositionEncodingKind) -> Option<PHPClass> {
+    let content = state::read_indexed_file(file_path)?;
     let tree = tree_sitter_parsers::parse(&content, "php");
     let query = queries::php_class();
 
@@ -183,23 +420,31 @@ pub fn parse_php_file(file_path: &PathBuf) -> Option<PHPClass> {
     let mut cls: Option<Node> = None;
     let mut methods: HashMap<String, PHPMethod> = HashMap::new();
     let mut constants: HashMap<String, PHPConst> = HashMap::new();
+    let mut uses: HashMap<String, String> = HashMap::new();
+    let mut parents: Vec<&str> = vec![];
+    let mut extends: Vec<&str> = vec![];
+    let mut traits: Vec<&str> = vec![];
+    let mut constructor_params: Vec<String> = vec![];
+    let mut template: Option<String> = None;
 
     for m in matches {
         if m.pattern_index == 0 {
             ns = Some(m.captures[0].node);
         }
-        if m.pattern_index == 1 || m.pattern_index == 2 {
+        if m.pattern_index == 1 || m.pattern_index == 2 || m.pattern_index == 11 {
             cls = Some(m.captures[0].node);
         }
-        if m.pattern_index == 3 {
-            let method_node = m.captures[1].node;
+        // interface methods are public even without visibility modifier
+        if m.pattern_index == 3 || m.pattern_index == 10 {
+            let method_node = m.captures[m.captures.len() - 1].node;
             let method_name = ts::get_node_str(method_node, &content);
             if !method_name.is_empty() {
                 methods.insert(
                     method_name.into(),
                     PHPMethod {
                         name: method_name.into(),
-                        range: get_range_from_node(method_node),
+                        range: get_range_from_node(method_node, &content, encoding),
+                        params: method_params(method_node, &content),
                     },
                 );
             }
@@ -212,11 +457,42 @@ pub fn parse_php_file(file_path: &PathBuf) -> Option<PHPClass> {
                     const_name.into(),
                     PHPConst {
                         name: const_name.into(),
-                        range: get_range_from_node(const_node),
+                        range: get_range_from_node(const_node, &content, encoding),
                     },
                 );
             }
         }
+        if m.pattern_index == 5 {
+            let use_name = ts::get_node_str(m.captures[0].node, &content);
+            let alias = m.captures.get(1).map_or_else(
+                || use_name.rsplit('\\').next().unwrap_or(use_name),
+                |alias| ts::get_node_str(alias.node, &content),
+            );
+            uses.insert(alias.into(), use_name.into());
+        }
+        if m.pattern_index == 6 || m.pattern_index == 7 {
+            // keep leading backslash, it marks fully qualified name
+            let parent_node = m.captures[0].node;
+            let parent = parent_node.utf8_text(content.as_bytes()).unwrap_or("");
+            parents.push(parent);
+            if m.pattern_index == 6 {
+                extends.push(parent);
+            }
+        }
+        if m.pattern_index == 12 {
+            let trait_node = m.captures[0].node;
+            traits.push(trait_node.utf8_text(content.as_bytes()).unwrap_or(""));
+        }
+        if m.pattern_index == 9 {
+            let text = ts::get_node_str(m.captures[1].node, &content);
+            template = Some(text.trim_matches(|c| c == '\'' || c == '"').into());
+        }
+        if m.pattern_index == 8 {
+            let param = ts::get_node_str(m.captures[1].node, &content);
+            if !param.is_empty() {
+                constructor_params.push(param.into());
+            }
+        }
     }
 
     if ns.is_none() || cls.is_none() {
@@ -228,22 +504,32 @@ pub fn parse_php_file(file_path: &PathBuf) -> Option<PHPClass> {
     let ns_text = ns_node.utf8_text(content.as_bytes()).unwrap_or("");
     let cls_text = cls_node.utf8_text(content.as_bytes()).unwrap_or("");
 
+    let api = cls_node
+        .parent()
+        .and_then(|declaration| declaration.prev_named_sibling())
+        .filter(|sibling| sibling.kind() == "comment")
+        .is_some_and(|docblock| is_api_docblock(ts::get_node_str(docblock, &content)));
+
     let fqn = ns_text.to_string() + "\\" + cls_text;
     if fqn == "\\" {
         return None;
     }
 
-    let uri = Url::from_file_path(file_path.clone()).expect("Path can not be converted to Url");
-    let range = Range {
-        start: Position {
-            line: cls_node.start_position().row as u32,
-            character: cls_node.start_position().column as u32,
-        },
-        end: Position {
-            line: cls_node.end_position().row as u32,
-            character: cls_node.end_position().column as u32,
-        },
-    };
+    let uri = Url::from_file_path(file_path).expect("Path can not be converted to Url");
+    let range = get_range_from_node(cls_node, &content, encoding);
+
+    let parents = parents
+        .iter()
+        .map(|name| resolve_class_name(name, ns_text, &uses))
+        .collect();
+    let extends = extends
+        .iter()
+        .map(|name| resolve_class_name(name, ns_text, &uses))
+        .collect();
+    let traits = traits
+        .iter()
+        .map(|name| resolve_class_name(name, ns_text, &uses))
+        .collect();
 
     Some(PHPClass {
         fqn,
@@ -251,5 +537,549 @@ pub fn parse_php_file(file_path: &PathBuf) -> Option<PHPClass> {
         range,
         methods,
         constants,
+        parents,
+        extends,
+        traits,
+        constructor_params,
+        api,
+        template,
     })
 }
+
+// split on top level commas, grammar does not know every modifier
+// (like `readonly` in promoted properties) to rely on parameter nodes
+fn method_params(name_node: Node, content: &str) -> Vec<String> {
+    let Some(params_node) = name_node
+        .parent()
+        .and_then(|method| method.child_by_field_name("parameters"))
+    else {
+        return vec![];
+    };
+    let text = params_node.utf8_text(content.as_bytes()).unwrap_or("");
+    let inner = text
+        .strip_prefix('(')
+        .and_then(|text| text.strip_suffix(')'))
+        .unwrap_or(text);
+
+    let mut params = vec![];
+    let mut depth = 0;
+    let mut quote = None;
+    let mut start = 0;
+    for (i, c) in inner.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => (),
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '(' | '[') => depth += 1,
+            (None, ')' | ']') => depth -= 1,
+            (None, ',') if depth == 0 => {
+                params.push(&inner[start..i]);
+                start = i + 1;
+            }
+            _ => (),
+        }
+    }
+    params.push(&inner[start..]);
+
+    params
+        .into_iter()
+        .map(|param| param.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|param| !param.is_empty())
+        .collect()
+}
+
+// `@api` tag marks class as stable, safe to depend on
+fn is_api_docblock(docblock: &str) -> bool {
+    docblock.split_whitespace().any(|word| word == "@api")
+}
+
+fn resolve_class_name(name: &str, namespace: &str, uses: &HashMap<String, String>) -> String {
+    if let Some(fqn) = name.strip_prefix('\\') {
+        return fqn.into();
+    }
+    let (first, rest) = name
+        .split_once('\\')
+        .map_or((name, None), |(first, rest)| (first, Some(rest)));
+    match (uses.get(first), rest) {
+        (Some(used), Some(rest)) => format!("{}\\{}", used, rest),
+        (Some(used), None) => used.clone(),
+        (None, _) => format!("{}\\{}", namespace, name),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::state::DiPlugin;
+    use crate::test_utils::{fixture_path, module_path, TempDir};
+
+    #[test]
+    fn test_update_index_from_registration_with_module_and_theme() {
+        let bundle_path = fixture_path(&["vendor", "some", "bundle"]);
+        let file_path = bundle_path.append(&["registration.php"]);
+        let content = std::fs::read_to_string(&file_path).unwrap();
+        let mut state = State::new();
+
+        let tree = tree_sitter_parsers::parse(&content, "php");
+        update_index_from_registration(&mut state, &content, &tree, &file_path);
+
+        assert_eq!(state.get_modules(), vec!["Some_Bundle"]);
+        assert_eq!(
+            state.get_module_path("Some_Bundle"),
+            Some(bundle_path.clone())
+        );
+        assert_eq!(
+            state.get_module_path("Some\\Bundle"),
+            Some(bundle_path.clone())
+        );
+        assert_eq!(state.list_front_themes_paths(), vec![&bundle_path]);
+        assert!(state.list_admin_themes_paths().is_empty());
+    }
+
+    #[test]
+    fn test_update_index_lightweight() {
+        let state = State::new().into_arc();
+
+        update_index_lightweight(&state, &fixture_path(&[]), &Progress::default());
+
+        let state = state.lock();
+        let module_path = module_path(&[]);
+        assert_eq!(
+            state.get_module_path("Some_Module"),
+            Some(module_path.clone())
+        );
+        assert_eq!(state.get_module_path("Some\\Module"), Some(module_path));
+        assert!(state.get_modules().contains(&"Some_Module".to_string()));
+        assert_eq!(
+            state
+                .get_theme_path(&m2::M2Area::Frontend, "Some/parent")
+                .cloned(),
+            Some(fixture_path(&[
+                "app", "design", "frontend", "Some", "parent"
+            ]))
+        );
+        assert_eq!(
+            state.list_admin_themes_paths(),
+            vec![&fixture_path(&[
+                "vendor",
+                "magento",
+                "theme-adminhtml-backend"
+            ])]
+        );
+    }
+
+    #[test]
+    fn test_registration_path_to_param() {
+        assert_eq!(
+            registration_path_to_param(&PathBuf::from(
+                "/a/vendor/magento/module-catalog-search/registration.php"
+            )),
+            Some("Magento_CatalogSearch".into())
+        );
+        assert_eq!(
+            registration_path_to_param(&PathBuf::from(
+                "/a/vendor/magento/theme-frontend-luma/registration.php"
+            )),
+            Some("frontend/Magento/luma".into())
+        );
+        assert_eq!(
+            registration_path_to_param(&PathBuf::from("/a/vendor/some/bundle/registration.php")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_php_file_constructor_params() {
+        let file_path = module_path(&["Block", "Widget.php"]);
+
+        let class = parse_php_file(&file_path, &PositionEncodingKind::UTF16)
+            .expect("Class should be parsed");
+
+        assert_eq!(class.constructor_params, vec!["context", "config", "data"]);
+    }
+
+    #[test]
+    fn test_parse_php_file_api_tag() {
+        let model_path = module_path(&["Model"]);
+
+        let stable = parse_php_file(
+            &model_path.append(&["Stable.php"]),
+            &PositionEncodingKind::UTF16,
+        );
+        let config = parse_php_file(
+            &model_path.append(&["Config.php"]),
+            &PositionEncodingKind::UTF16,
+        );
+
+        assert!(stable.expect("Class should be parsed").api);
+        assert!(!config.expect("Class should be parsed").api);
+    }
+
+    #[test]
+    fn test_parse_php_file_method_params() {
+        let file_path = module_path(&["Block", "Widget.php"]);
+
+        let class = parse_php_file(&file_path, &PositionEncodingKind::UTF16)
+            .expect("Class should be parsed");
+
+        assert_eq!(
+            class.methods["__construct"].params,
+            vec![
+                "Template\\Context $context",
+                "private readonly \\Some\\Module\\Model\\Config $config",
+                "array $data = []"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_php_file_parents() {
+        let file_path = module_path(&["Model", "Child.php"]);
+
+        let class = parse_php_file(&file_path, &PositionEncodingKind::UTF16)
+            .expect("Class should be parsed");
+
+        assert_eq!(
+            class.parents,
+            vec![
+                "Some\\Module\\Model\\Config",
+                "Some\\Module\\Api\\TestInterface",
+                "Some\\Module\\Api\\MissingInterface"
+            ]
+        );
+        assert_eq!(class.extends, vec!["Some\\Module\\Model\\Config"]);
+        assert_eq!(class.traits, vec!["Some\\Module\\Traits\\HasLimit"]);
+    }
+
+    #[test]
+    fn test_parse_php_file_traits_with_conflict_resolution() {
+        let dir = TempDir::new("traits");
+        let file_path = dir.write(
+            &["Limited.php"],
+            r#"<?php
+namespace Some\Module\Model;
+
+class Limited
+{
+    use HasLimit, \Other\HasLimit {
+        limit as protected maxLimit;
+    }
+}
+"#,
+        );
+
+        let class = parse_php_file(&file_path, &PositionEncodingKind::UTF16);
+
+        assert_eq!(
+            class.expect("Class should be parsed").traits,
+            vec!["Some\\Module\\Model\\HasLimit", "Other\\HasLimit"]
+        );
+    }
+
+    #[test]
+    fn test_parse_php_file_skips_unreadable_file() {
+        let file_path = module_path(&["Model", "Missing.php"]);
+
+        assert!(parse_php_file(&file_path, &PositionEncodingKind::UTF16).is_none());
+    }
+
+    #[test]
+    fn test_resolve_class_name() {
+        let mut uses = HashMap::new();
+        uses.insert("Bar".to_string(), "Other\\Bar".to_string());
+
+        assert_eq!(resolve_class_name("Foo", "Some", &uses), "Some\\Foo");
+        assert_eq!(resolve_class_name("Bar", "Some", &uses), "Other\\Bar");
+        assert_eq!(
+            resolve_class_name("Bar\\Baz", "Some", &uses),
+            "Other\\Bar\\Baz"
+        );
+        assert_eq!(resolve_class_name("\\Foo\\Baz", "Some", &uses), "Foo\\Baz");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_update_index_follows_symlinked_vendor_module() {
+        let root = TempDir::new("symlink");
+        std::fs::create_dir_all(root.path(&["vendor", "some"])).unwrap();
+        let linked_path = root.path(&["vendor", "some", "module"]);
+        std::os::unix::fs::symlink(module_path(&[]), &linked_path).unwrap();
+        let arc_state = State::new().into_arc();
+
+        update_index(&arc_state, &root.path(&[]), &Progress::default());
+
+        let state = arc_state.lock();
+        assert_eq!(state.get_modules(), vec!["Some_Module"]);
+        assert_eq!(state.get_module_path("Some_Module"), Some(linked_path));
+    }
+
+    #[test]
+    fn test_get_item_from_pos_view_file_url() {
+        let mut state = State::new();
+        state.add_module_path("Some_Module", PathBuf::from("/a/b/Some/Module"));
+        let content =
+            r#"<img src="<?= $block->getViewFileUrl('Some_Module::images/logo.svg') ?>"/>"#;
+
+        let item = get_item_from_pos(
+            &state,
+            content,
+            &tree_sitter_parsers::parse(content, "php"),
+            &PathBuf::from("/a/b/c/view/frontend/templates/logo.phtml"),
+            Position {
+                line: 0,
+                character: 50,
+            },
+        );
+
+        assert_eq!(
+            item,
+            Some(M2Item::ModHtml(
+                "Some_Module".into(),
+                "images/logo.svg".into(),
+                PathBuf::from("/a/b/Some/Module")
+            ))
+        );
+    }
+
+    #[test]
+    fn test_get_item_from_pos_template_in_phtml() {
+        let state = State::new();
+        let content = r#"<?= $block->setTemplate('Some_Module::some/file.phtml')->toHtml() ?>"#;
+
+        let item = get_item_from_pos(
+            &state,
+            content,
+            &tree_sitter_parsers::parse(content, "php"),
+            &PathBuf::from("/a/b/c/view/adminhtml/templates/logo.phtml"),
+            Position {
+                line: 0,
+                character: 40,
+            },
+        );
+
+        assert_eq!(
+            item,
+            Some(M2Item::AdminPhtml(
+                "Some_Module".into(),
+                "some/file.phtml".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_get_item_from_pos_phrase() {
+        let state = State::new();
+        let content = r#"<span><?= $escaper->escapeHtml(__('Don\'t Save')) ?></span>"#;
+
+        let item = get_item_from_pos(
+            &state,
+            content,
+            &tree_sitter_parsers::parse(content, "php"),
+            &PathBuf::from("/a/b/c/view/frontend/templates/button.phtml"),
+            Position {
+                line: 0,
+                character: 40,
+            },
+        );
+
+        assert_eq!(item, Some(M2Item::Phrase("Don't Save".into())));
+    }
+
+    #[test]
+    fn test_get_block_item_from_pos_template_property() {
+        let content = r#"<?php
+class Widget extends Template
+{
+    protected $_template = 'Some_Module::widget/default.phtml';
+
+    public function useCompact()
+    {
+        $this->_template = "Some_Module::widget/compact.phtml";
+    }
+}
+"#;
+        let path = PathBuf::from("/a/b/Some/Module/Block/Widget.php");
+
+        let item = get_block_item_from_pos(
+            content,
+            &tree_sitter_parsers::parse(content, "php"),
+            &path,
+            Position {
+                line: 3,
+                character: 40,
+            },
+            &PositionEncodingKind::UTF16,
+        );
+        assert_eq!(
+            item,
+            Some(M2Item::BasePhtml(
+                "Some_Module".into(),
+                "widget/default.phtml".into()
+            ))
+        );
+
+        let item = get_block_item_from_pos(
+            content,
+            &tree_sitter_parsers::parse(content, "php"),
+            &path,
+            Position {
+                line: 7,
+                character: 40,
+            },
+            &PositionEncodingKind::UTF16,
+        );
+        assert_eq!(
+            item,
+            Some(M2Item::BasePhtml(
+                "Some_Module".into(),
+                "widget/compact.phtml".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_get_block_item_from_pos_other_string() {
+        let content = r#"<?php
+class Widget extends Template
+{
+    protected $_label = 'Some_Module::widget/default.phtml';
+}
+"#;
+
+        let item = get_block_item_from_pos(
+            content,
+            &tree_sitter_parsers::parse(content, "php"),
+            &PathBuf::from("/a/b/Some/Module/Block/Widget.php"),
+            Position {
+                line: 3,
+                character: 40,
+            },
+            &PositionEncodingKind::UTF16,
+        );
+
+        assert_eq!(item, None);
+    }
+
+    fn state_with_routes() -> State {
+        let mut state = State::new();
+        state.add_module_path("Some\\Module", PathBuf::from("/a/b/Some/Module"));
+        state.set_file(
+            &PathBuf::from("/a/b/Some/Module/etc/frontend/routes.xml"),
+            r#"<?xml version="1.0"?>
+            <config>
+                <router id="standard">
+                    <route id="some_route" frontName="some">
+                        <module name="Some_Module"/>
+                    </route>
+                </router>
+            </config>
+            "#,
+        );
+        state.set_file(
+            &PathBuf::from("/a/b/Some/Module/etc/adminhtml/routes.xml"),
+            r#"<?xml version="1.0"?>
+            <config>
+                <router id="admin">
+                    <route id="some_admin" frontName="some_admin">
+                        <module name="Some_Module"/>
+                    </route>
+                </router>
+            </config>
+            "#,
+        );
+        state
+    }
+
+    fn get_test_controller_item(state: &mut State, path: &str, pos: Position) -> Option<M2Item> {
+        let path = PathBuf::from(path);
+        state.set_file(
+            &path,
+            "<?php\nnamespace Some\\Module\\Controller;\n\nclass View implements HttpGetActionInterface\n{\n}\n",
+        );
+        get_class_item_from_position(state, &path, pos)
+    }
+
+    #[test]
+    fn test_get_class_item_from_pos_controller_layout_handle() {
+        let mut state = state_with_routes();
+
+        let item = get_test_controller_item(
+            &mut state,
+            "/a/b/Some/Module/Controller/Order/Item/View.php",
+            Position::new(3, 8),
+        );
+
+        assert_eq!(
+            item,
+            Some(M2Item::LayoutHandle("some_route_order_item_view".into()))
+        );
+    }
+
+    #[test]
+    fn test_get_class_item_from_pos_admin_controller_layout_handle() {
+        let mut state = state_with_routes();
+
+        let item = get_test_controller_item(
+            &mut state,
+            "/a/b/Some/Module/Controller/Adminhtml/Order/View.php",
+            Position::new(3, 8),
+        );
+
+        assert_eq!(
+            item,
+            Some(M2Item::LayoutHandle("some_admin_order_view".into()))
+        );
+    }
+
+    #[test]
+    fn test_get_class_item_from_pos_plugin_method() {
+        let mut state = State::new();
+        for type_name in [
+            "Some\\Module\\Model\\Product",
+            "Some\\Module\\Model\\Bundle",
+        ] {
+            state.add_plugin(
+                &M2Area::Base,
+                type_name,
+                "some_plugin",
+                DiPlugin {
+                    class: Some("\\Some\\Module\\Plugin\\ProductPlugin".into()),
+                    disabled: None,
+                },
+            );
+        }
+        let path = PathBuf::from("/a/b/Some/Module/Plugin/ProductPlugin.php");
+        state.set_file(
+            &path,
+            "<?php\nnamespace Some\\Module\\Plugin;\n\nclass ProductPlugin\n{\n    public function afterGetName($subject, $result) {}\n    public function helper() {}\n}\n",
+        );
+
+        assert_eq!(
+            get_class_item_from_position(&state, &path, Position::new(5, 24)),
+            Some(M2Item::PluggedMethod(
+                vec![
+                    "Some\\Module\\Model\\Bundle".into(),
+                    "Some\\Module\\Model\\Product".into()
+                ],
+                "getName".into()
+            ))
+        );
+        assert_eq!(
+            get_class_item_from_position(&state, &path, Position::new(6, 24)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_get_class_item_from_pos_controller_outside_class_name() {
+        let mut state = state_with_routes();
+
+        let item = get_test_controller_item(
+            &mut state,
+            "/a/b/Some/Module/Controller/Order/View.php",
+            Position::new(3, 25),
+        );
+
+        assert_eq!(item, None);
+    }
+}
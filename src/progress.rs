@@ -0,0 +1,187 @@
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use crossbeam_channel::Sender;
+use lsp_server::{Message, Notification, Request, RequestId};
+use lsp_types::{
+    notification::{Notification as _, Progress as ProgressNotification},
+    request::{Request as _, WorkDoneProgressCreate},
+    NumberOrString, ProgressParams, ProgressParamsValue, WorkDoneProgress, WorkDoneProgressBegin,
+    WorkDoneProgressCreateParams, WorkDoneProgressEnd, WorkDoneProgressReport,
+};
+
+use crate::state::ArcState;
+
+static NEXT_TOKEN: AtomicUsize = AtomicUsize::new(0);
+
+// progress can begin only once client created the token, indexing goes on without it
+const CREATE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Work done progress shown by the client, does nothing without a sender
+#[derive(Debug, Default)]
+pub struct Progress {
+    sender: Option<Sender<Message>>,
+    token: String,
+}
+
+impl Progress {
+    pub fn begin(state: &ArcState, title: &str) -> Self {
+        let token = format!("magento2-ls/{}", NEXT_TOKEN.fetch_add(1, Ordering::Relaxed));
+        let id = RequestId::from(token.clone());
+        let Some((sender, created)) = state.lock().request_progress(id.clone()) else {
+            return Self::default();
+        };
+        let create = Request::new(
+            id.clone(),
+            WorkDoneProgressCreate::METHOD.into(),
+            WorkDoneProgressCreateParams {
+                token: NumberOrString::String(token.clone()),
+            },
+        );
+        sender.send(create.into()).ok();
+        // response is passed by the main loop, state can not be locked while waiting
+        if created.recv_timeout(CREATE_TIMEOUT) != Ok(true) {
+            state.lock().forget_progress_request(&id);
+            return Self::default();
+        }
+
+        let progress = Self {
+            sender: Some(sender),
+            token,
+        };
+        progress.send(WorkDoneProgress::Begin(WorkDoneProgressBegin {
+            title: title.into(),
+            cancellable: Some(false),
+            message: None,
+            percentage: Some(0),
+        }));
+        progress
+    }
+
+    /// Report `done` out of `total` steps
+    pub fn report(&self, done: usize, total: usize) {
+        let percentage = done * 100 / total.max(1);
+        self.send(WorkDoneProgress::Report(WorkDoneProgressReport {
+            cancellable: None,
+            message: None,
+            percentage: u32::try_from(percentage).ok(),
+        }));
+    }
+
+    pub fn end(self) {
+        self.send(WorkDoneProgress::End(WorkDoneProgressEnd { message: None }));
+    }
+
+    fn send(&self, value: WorkDoneProgress) {
+        if let Some(sender) = &self.sender {
+            let params = ProgressParams {
+                token: NumberOrString::String(self.token.clone()),
+                value: ProgressParamsValue::WorkDone(value),
+            };
+            let notification = Notification::new(ProgressNotification::METHOD.into(), params);
+            sender.send(notification.into()).ok();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        sync::Arc,
+        thread::{spawn, JoinHandle},
+    };
+
+    use crossbeam_channel::Receiver;
+    use lsp_server::Response;
+
+    use super::*;
+    use crate::state::State;
+
+    fn progress_value(message: Message) -> WorkDoneProgress {
+        let Message::Notification(notification) = message else {
+            panic!("Progress should be a notification");
+        };
+        assert_eq!(notification.method, ProgressNotification::METHOD);
+        let params: ProgressParams = serde_json::from_value(notification.params).unwrap();
+        let ProgressParamsValue::WorkDone(value) = params.value;
+        value
+    }
+
+    fn index_in_thread(state: &ArcState) -> JoinHandle<()> {
+        let state = Arc::clone(state);
+        spawn(move || {
+            let progress = Progress::begin(&state, "PHP Indexing");
+            progress.report(1, 4);
+            progress.end();
+        })
+    }
+
+    fn state_with_progress() -> (ArcState, Receiver<Message>) {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let mut state = State::new();
+        state.set_progress_sender(sender);
+        (state.into_arc(), receiver)
+    }
+
+    fn created_response(receiver: &Receiver<Message>) -> Response {
+        let Ok(Message::Request(create)) = receiver.recv() else {
+            panic!("Progress token should be created first");
+        };
+        assert_eq!(create.method, WorkDoneProgressCreate::METHOD);
+        // nothing is reported before client creates the token
+        assert!(receiver.try_recv().is_err());
+        Response::new_ok(create.id, ())
+    }
+
+    #[test]
+    fn test_progress_begins_once_token_is_created() {
+        let (state, receiver) = state_with_progress();
+        let thread = index_in_thread(&state);
+
+        let response = created_response(&receiver);
+        assert!(state.lock().handle_progress_response(&response));
+        thread.join().unwrap();
+
+        let messages = receiver.try_iter().collect::<Vec<_>>();
+        assert_eq!(messages.len(), 3);
+        assert!(matches!(
+            progress_value(messages[0].clone()),
+            WorkDoneProgress::Begin(WorkDoneProgressBegin { ref title, .. }) if title == "PHP Indexing"
+        ));
+        assert!(matches!(
+            progress_value(messages[1].clone()),
+            WorkDoneProgress::Report(WorkDoneProgressReport {
+                percentage: Some(25),
+                ..
+            })
+        ));
+        assert!(matches!(
+            progress_value(messages[2].clone()),
+            WorkDoneProgress::End(_)
+        ));
+    }
+
+    #[test]
+    fn test_progress_is_not_reported_when_token_is_refused() {
+        let (state, receiver) = state_with_progress();
+        let thread = index_in_thread(&state);
+
+        let response = created_response(&receiver);
+        let refused = Response::new_err(response.id, 0, "refused".into());
+        assert!(state.lock().handle_progress_response(&refused));
+        thread.join().unwrap();
+
+        assert!(receiver.try_iter().next().is_none());
+        assert!(!state.lock().handle_progress_response(&refused));
+    }
+
+    #[test]
+    fn test_progress_without_sender_does_nothing() {
+        let state = State::new().into_arc();
+        let progress = Progress::begin(&state, "PHP Indexing");
+        progress.report(1, 2);
+        progress.end();
+    }
+}
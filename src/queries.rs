@@ -8,6 +8,8 @@ pub static JS_COMPLETION_ITEM_DEFINITION: OnceLock<Query> = OnceLock::new();
 
 pub static PHP_REGISTRATION: OnceLock<Query> = OnceLock::new();
 pub static PHP_CLASS: OnceLock<Query> = OnceLock::new();
+pub static PHTML_ITEM_FROM_POS: OnceLock<Query> = OnceLock::new();
+pub static PHP_ITEM_FROM_POS: OnceLock<Query> = OnceLock::new();
 
 pub static XML_TAG_AT_POS: OnceLock<Query> = OnceLock::new();
 pub static XML_CURRENT_POSITION_PATH: OnceLock<Query> = OnceLock::new();
@@ -100,6 +102,56 @@ pub fn php_class() -> &'static Query {
         ((method_declaration (visibility_modifier)
           @_vis (name) @name) (#eq? @_vis "public"))       ; pattern: 3
         (const_element (name) @const)                      ; pattern: 4
+        (namespace_use_clause . (_) @use
+          (namespace_aliasing_clause (name) @alias)?)      ; pattern: 5
+        (base_clause (_) @parent)                          ; pattern: 6
+        (class_interface_clause (_) @parent)               ; pattern: 7
+        ((method_declaration (name) @_name
+          (formal_parameters (_ (variable_name (name) @param))))
+          (#eq? @_name "__construct"))                     ; pattern: 8
+        (property_element (variable_name (name) @_name)
+          (property_initializer (string) @template)
+          (#eq? @_name "_template"))                       ; pattern: 9
+        (interface_declaration (declaration_list
+          (method_declaration (name) @name)))              ; pattern: 10
+        (trait_declaration (name) @class)                  ; pattern: 11
+        ; bare trait names are qualified_name too, skips `{ ... }` use_list
+        (use_declaration (qualified_name) @trait)          ; pattern: 12
+        "#,
+        "php",
+    )
+}
+
+pub fn phtml_item_from_pos() -> &'static Query {
+    query(
+        &PHTML_ITEM_FROM_POS,
+        r#"
+        (member_call_expression
+            name: (name) @name
+            arguments: (arguments . (string) @str)
+            (#match? @name "^getView(File)?Url$"))         ; pattern: 0
+        (string) @str                                      ; pattern: 1
+        (function_call_expression
+            function: (qualified_name) @name
+            arguments: (arguments . (string) @str)
+            (#eq? @name "__"))                             ; pattern: 2
+        "#,
+        "php",
+    )
+}
+
+pub fn php_item_from_pos() -> &'static Query {
+    query(
+        &PHP_ITEM_FROM_POS,
+        r#"
+        (property_element
+            (variable_name (name) @name)
+            (property_initializer (string) @str)
+            (#eq? @name "_template"))                      ; pattern: 0
+        (assignment_expression
+            left: (member_access_expression name: (name) @name)
+            right: (string) @str
+            (#eq? @name "_template"))                      ; pattern: 1
         "#,
         "php",
     )
@@ -166,6 +218,6 @@ fn query(static_query: &'static OnceLock<Query>, query: &str, lang: &str) -> &'s
     })
 }
 
-fn get_language(lang: &str) -> Language {
+pub fn get_language(lang: &str) -> Language {
     tree_sitter_parsers::parse("", lang).language()
 }
@@ -1,18 +1,26 @@
 use std::{
-    collections::HashMap,
+    borrow::Cow,
+    cell::{Cell, RefCell},
+    collections::{HashMap, HashSet},
     path::{Path, PathBuf},
     sync::Arc,
     thread::{spawn, JoinHandle},
-    time::SystemTime,
+    time::{Duration, Instant, SystemTime},
 };
 
-use lsp_types::Position;
+use crossbeam_channel::{Receiver, Sender};
+use lsp_server::{Message, RequestId, Response};
+use lsp_types::{Location, PositionEncodingKind, TextDocumentContentChangeEvent};
 use parking_lot::Mutex;
+use tree_sitter::Tree;
 
 use crate::{
     js,
-    m2::{M2Area, M2Item, M2Path},
-    php, xml,
+    m2::{self, M2Area, M2Item, M2Path},
+    options::Options,
+    php::{self, ClassSummary},
+    progress::Progress,
+    ts, xml,
 };
 
 trait HashMapId {
@@ -32,26 +40,64 @@ impl HashMapId for M2Area {
 #[derive(Debug, Clone, PartialEq, Eq)]
 enum Trackee {
     Module(String),
-    ModulePath(String),
+    ModulePath(String, PathBuf),
     JsMap(M2Area, String),
     JsMixin(M2Area, String),
     JsPaths(M2Area, String),
     Themes(M2Area, String),
+    CacheType(String),
+    ConfigPath(String),
+    Plugin(M2Area, String, String),
+    DiType(String),
+    Event(String),
+    LayoutElement(String),
+    AclResource(String),
+    ClassReference(String),
+    LayoutHandle(String),
+    Route(M2Area, String),
 }
 
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DiPlugin {
+    pub class: Option<String>,
+    pub disabled: Option<bool>,
+}
+
+impl DiPlugin {
+    // later declarations and area config are merged on top of the global one
+    fn merge(&mut self, other: &Self) {
+        if other.class.is_some() {
+            self.class = other.class.clone();
+        }
+        if other.disabled.is_some() {
+            self.disabled = other.disabled;
+        }
+    }
+}
+
+// di.xml file plugin is declared in, with declared attributes
+type PluginDeclaration = (PathBuf, DiPlugin);
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 struct TrackingList(HashMap<PathBuf, Vec<Trackee>>);
 
+impl Trackee {
+    // cached completion lists are built from module and theme directories
+    const fn affects_completion(&self) -> bool {
+        matches!(
+            self,
+            Self::Module(_) | Self::ModulePath(..) | Self::Themes(..)
+        )
+    }
+}
+
 impl TrackingList {
     pub fn new() -> Self {
         Self(HashMap::new())
     }
 
     pub fn track(&mut self, source_path: &Path, trackee: Trackee) {
-        self.0
-            .entry(source_path.into())
-            .or_insert_with(Vec::new)
-            .push(trackee);
+        self.0.entry(source_path.into()).or_default().push(trackee);
     }
 
     pub fn maybe_track(&mut self, source_path: Option<&PathBuf>, trackee: Trackee) {
@@ -63,21 +109,106 @@ impl TrackingList {
     pub fn untrack(&mut self, source_path: &Path) -> Option<Vec<Trackee>> {
         self.0.remove(source_path)
     }
+
+    pub fn completion_trackees(&self, source_path: &Path) -> Vec<Trackee> {
+        self.0
+            .get(source_path)
+            .into_iter()
+            .flatten()
+            .filter(|trackee| trackee.affects_completion())
+            .cloned()
+            .collect()
+    }
+
+    pub fn sources_within(&self, base_path: &Path) -> Vec<PathBuf> {
+        self.0
+            .keys()
+            .filter(|source_path| source_path.starts_with(base_path))
+            .cloned()
+            .collect()
+    }
 }
 
+// completions are requested on every keystroke, typing the same prefix again
+// within that time reuses computed list
+const COMPLETION_CACHE_TTL: Duration = Duration::from_secs(2);
+
 #[derive(Debug, Clone, PartialEq, Eq)]
+struct CachedCompletion {
+    created: Instant,
+    items: Vec<CompletionCandidate>,
+}
+
+/// Completion label and the directory it was found in, if it comes from one
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CompletionCandidate {
+    pub label: String,
+    pub source: Option<PathBuf>,
+}
+
+impl CompletionCandidate {
+    pub fn new(label: String, source: &Path) -> Self {
+        Self {
+            label,
+            source: Some(source.to_path_buf()),
+        }
+    }
+}
+
+impl From<String> for CompletionCandidate {
+    fn from(label: String) -> Self {
+        Self {
+            label,
+            source: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Buffer {
+    content: String,
+    // kept to parse only edited parts of the content on change
+    tree: Option<Tree>,
+    // reading a buffer only needs shared access to the state
+    accessed: Cell<Instant>,
+}
+
+#[derive(Debug, Clone)]
 pub struct State {
     source_file: Option<PathBuf>,
     track_entities: TrackingList,
-    buffers: HashMap<PathBuf, String>,
+    buffers: HashMap<PathBuf, Buffer>,
+    // documents between `didOpen` and `didClose`, their buffers are never dropped
+    open_files: HashSet<PathBuf>,
     modules: Vec<String>,
-    module_paths: HashMap<String, PathBuf>,
+    // every path module was registered at, preferred one last
+    module_paths: HashMap<String, Vec<PathBuf>>,
     front_themes: HashMap<String, PathBuf>,
     admin_themes: HashMap<String, PathBuf>,
     js_maps: [HashMap<String, String>; 3],
     js_mixins: [HashMap<String, Vec<String>>; 3],
     js_paths: [HashMap<String, String>; 3],
+    cache_types: HashMap<String, String>,
+    config_paths: HashMap<String, Vec<Location>>,
+    // same plugin can be declared (or disabled) in many di.xml files
+    plugins: [HashMap<(String, String), Vec<PluginDeclaration>>; 3],
+    di_types: HashMap<String, Vec<(PathBuf, String)>>,
+    events: HashMap<String, Vec<PathBuf>>,
+    layout_elements: HashMap<String, Vec<Location>>,
+    acl_resources: HashMap<String, Vec<Location>>,
+    class_references: HashMap<String, Vec<Location>>,
+    layout_handles: HashMap<String, Vec<Location>>,
+    routes: [HashMap<String, String>; 3],
     workspaces: Vec<PathBuf>,
+    options: Options,
+    position_encoding: PositionEncodingKind,
+    completion_cache: RefCell<HashMap<(M2Area, String), CachedCompletion>>,
+    // class files parsed for completion, with modification time they were parsed at
+    class_summaries: RefCell<HashMap<PathBuf, (SystemTime, Option<ClassSummary>)>>,
+    // set when client can show indexing progress
+    progress_sender: Option<Sender<Message>>,
+    // progress token creation requests waiting for client response
+    progress_requests: HashMap<RequestId, Sender<bool>>,
 }
 
 #[allow(clippy::module_name_repetitions)]
@@ -89,6 +220,7 @@ impl State {
             source_file: None,
             track_entities: TrackingList::new(),
             buffers: HashMap::new(),
+            open_files: HashSet::new(),
             modules: vec![],
             module_paths: HashMap::new(),
             front_themes: HashMap::new(),
@@ -96,16 +228,75 @@ impl State {
             js_maps: [HashMap::new(), HashMap::new(), HashMap::new()],
             js_mixins: [HashMap::new(), HashMap::new(), HashMap::new()],
             js_paths: [HashMap::new(), HashMap::new(), HashMap::new()],
+            cache_types: HashMap::new(),
+            config_paths: HashMap::new(),
+            plugins: [HashMap::new(), HashMap::new(), HashMap::new()],
+            di_types: HashMap::new(),
+            events: HashMap::new(),
+            layout_elements: HashMap::new(),
+            acl_resources: HashMap::new(),
+            class_references: HashMap::new(),
+            layout_handles: HashMap::new(),
+            routes: [HashMap::new(), HashMap::new(), HashMap::new()],
             workspaces: vec![],
+            options: Options::default(),
+            position_encoding: PositionEncodingKind::UTF16,
+            completion_cache: RefCell::new(HashMap::new()),
+            class_summaries: RefCell::new(HashMap::new()),
+            progress_sender: None,
+            progress_requests: HashMap::new(),
         }
     }
 
+    pub const fn options(&self) -> &Options {
+        &self.options
+    }
+
+    pub fn set_options(&mut self, options: Options) {
+        self.options = options;
+    }
+
+    pub fn set_progress_sender(&mut self, sender: Sender<Message>) {
+        self.progress_sender = Some(sender);
+    }
+
+    /// Sender for the token creation request, and receiver of whether client created the token
+    pub fn request_progress(&mut self, id: RequestId) -> Option<(Sender<Message>, Receiver<bool>)> {
+        let sender = self.progress_sender.clone()?;
+        let (created_sender, created) = crossbeam_channel::bounded(1);
+        self.progress_requests.insert(id, created_sender);
+        Some((sender, created))
+    }
+
+    pub fn forget_progress_request(&mut self, id: &RequestId) {
+        self.progress_requests.remove(id);
+    }
+
+    /// Passes client response to the progress waiting for it, false if nothing waits for it
+    pub fn handle_progress_response(&mut self, response: &Response) -> bool {
+        self.progress_requests
+            .remove(&response.id)
+            .map(|created| created.send(response.error.is_none()).ok())
+            .is_some()
+    }
+
+    pub const fn position_encoding(&self) -> &PositionEncodingKind {
+        &self.position_encoding
+    }
+
+    pub fn set_position_encoding(&mut self, encoding: PositionEncodingKind) {
+        self.position_encoding = encoding;
+    }
+
     pub fn set_source_file(&mut self, path: &Path) {
         self.source_file = Some(path.to_owned());
     }
 
     pub fn clear_from_source(&mut self, path: &Path) {
         if let Some(list) = self.track_entities.untrack(path) {
+            if list.iter().any(Trackee::affects_completion) {
+                self.completion_cache.get_mut().clear();
+            }
             for trackee in list {
                 match trackee {
                     Trackee::JsMap(area, name) => {
@@ -120,8 +311,13 @@ impl State {
                     Trackee::Module(module) => {
                         self.modules.retain(|m| m != &module);
                     }
-                    Trackee::ModulePath(module) => {
-                        self.module_paths.remove(&module);
+                    Trackee::ModulePath(module, module_path) => {
+                        if let Some(paths) = self.module_paths.get_mut(&module) {
+                            paths.retain(|p| p != &module_path);
+                            if paths.is_empty() {
+                                self.module_paths.remove(&module);
+                            }
+                        }
                     }
                     Trackee::Themes(area, module) => match area {
                         M2Area::Frontend => {
@@ -135,6 +331,82 @@ impl State {
                             self.admin_themes.remove(&module);
                         }
                     },
+                    Trackee::CacheType(name) => {
+                        self.cache_types.remove(&name);
+                    }
+                    Trackee::ConfigPath(config_path) => {
+                        // same path can be declared by system.xml of many modules
+                        if let Some(locations) = self.config_paths.get_mut(&config_path) {
+                            locations.retain(|l| l.uri.to_file_path().map_or(true, |p| p != path));
+                            if locations.is_empty() {
+                                self.config_paths.remove(&config_path);
+                            }
+                        }
+                    }
+                    Trackee::Plugin(area, type_name, name) => {
+                        let key = (type_name, name);
+                        if let Some(entries) = self.plugins[area.id()].get_mut(&key) {
+                            entries.retain(|(source, _)| source != path);
+                            if entries.is_empty() {
+                                self.plugins[area.id()].remove(&key);
+                            }
+                        }
+                    }
+                    Trackee::DiType(name) => {
+                        if let Some(entries) = self.di_types.get_mut(&name) {
+                            entries.retain(|(source, _)| source != path);
+                            if entries.is_empty() {
+                                self.di_types.remove(&name);
+                            }
+                        }
+                    }
+                    Trackee::Event(name) => {
+                        // same event can be observed in many files
+                        if let Some(sources) = self.events.get_mut(&name) {
+                            sources.retain(|source| source != path);
+                            if sources.is_empty() {
+                                self.events.remove(&name);
+                            }
+                        }
+                    }
+                    Trackee::LayoutElement(name) => {
+                        // blocks and containers are declared in many layout files
+                        if let Some(locations) = self.layout_elements.get_mut(&name) {
+                            locations.retain(|l| l.uri.to_file_path().map_or(true, |p| p != path));
+                            if locations.is_empty() {
+                                self.layout_elements.remove(&name);
+                            }
+                        }
+                    }
+                    Trackee::AclResource(id) => {
+                        // parent resources are repeated in every acl.xml
+                        if let Some(locations) = self.acl_resources.get_mut(&id) {
+                            locations.retain(|l| l.uri.to_file_path().map_or(true, |p| p != path));
+                            if locations.is_empty() {
+                                self.acl_resources.remove(&id);
+                            }
+                        }
+                    }
+                    Trackee::LayoutHandle(handle) => {
+                        // same handle is extended by layout files of many modules
+                        if let Some(locations) = self.layout_handles.get_mut(&handle) {
+                            locations.retain(|l| l.uri.to_file_path().map_or(true, |p| p != path));
+                            if locations.is_empty() {
+                                self.layout_handles.remove(&handle);
+                            }
+                        }
+                    }
+                    Trackee::Route(area, module) => {
+                        self.routes[area.id()].remove(&module);
+                    }
+                    Trackee::ClassReference(class) => {
+                        if let Some(locations) = self.class_references.get_mut(&class) {
+                            locations.retain(|l| l.uri.to_file_path().map_or(true, |p| p != path));
+                            if locations.is_empty() {
+                                self.class_references.remove(&class);
+                            }
+                        }
+                    }
                 }
             }
         }
@@ -144,22 +416,193 @@ impl State {
     where
         S: Into<String>,
     {
-        let content = content.into();
+        self.store_file(path, strip_bom(content.into()), None);
+    }
+
+    pub fn open_file<S>(&mut self, path: &Path, content: S)
+    where
+        S: Into<String>,
+    {
+        self.open_files.insert(path.to_path_buf());
+        self.set_file(path, content);
+    }
+
+    pub fn is_open(&self, path: &Path) -> bool {
+        self.open_files.contains(path)
+    }
+
+    /// Applies incremental changes on top of the stored buffer
+    pub fn change_file(&mut self, path: &PathBuf, changes: &[TextDocumentContentChangeEvent]) {
+        let (mut content, mut tree) = match self.buffers.get(path) {
+            Some(buffer) => (buffer.content.clone(), buffer.tree.clone()),
+            None if changes.first().is_some_and(|change| change.range.is_none()) => {
+                (String::new(), None)
+            }
+            // ranges are relative to client content, applying them to disk content
+            // would corrupt the buffer
+            None => return,
+        };
+        for change in changes {
+            match (change.range, tree.as_mut()) {
+                (Some(range), Some(tree)) => tree.edit(&ts::input_edit(
+                    &content,
+                    range,
+                    &change.text,
+                    &self.position_encoding,
+                )),
+                (Some(_), None) => (),
+                // whole content replaced, nothing to reuse
+                (None, _) => tree = None,
+            }
+            apply_change(&mut content, change, &self.position_encoding);
+        }
+        self.store_file(path, strip_bom(content), tree.as_ref());
+    }
+
+    fn store_file(&mut self, path: &Path, content: String, old_tree: Option<&Tree>) {
+        let tree = tree_language(path).map(|lang| ts::parse(&content, lang, old_tree));
+        // edit that declares the same modules and themes again keeps cached completions
+        let trackees = self.track_entities.completion_trackees(path);
+        let cache = std::mem::take(self.completion_cache.get_mut());
         self.clear_from_source(path);
-        js::maybe_index_file(self, &content, &path.to_owned());
-        php::maybe_index_file(self, &content, &path.to_owned());
+        if let Some(tree) = &tree {
+            self.index_file(path, &content, tree);
+        }
+        if self.track_entities.completion_trackees(path) == trackees {
+            *self.completion_cache.get_mut() = cache;
+        }
+
+        let buffer = Buffer {
+            tree,
+            content,
+            accessed: Cell::new(Instant::now()),
+        };
+        self.buffers.insert(path.to_owned(), buffer);
+    }
 
-        self.buffers.insert(path.to_owned(), content);
+    /// Syntax tree of the file, parsed from `content` if file is not opened
+    pub fn get_tree(&self, path: &Path, content: &str) -> Option<Tree> {
+        let stored = self
+            .buffers
+            .get(path)
+            .and_then(|buffer| buffer.tree.clone());
+        stored.or_else(|| tree_language(path).map(|lang| ts::parse(content, lang, None)))
     }
 
     pub fn get_file(&self, path: &PathBuf) -> Option<&String> {
-        self.buffers.get(path)
+        self.buffers.get(path).map(|buffer| {
+            buffer.accessed.set(Instant::now());
+            &buffer.content
+        })
+    }
+
+    pub fn get_file_or_read(&self, path: &PathBuf) -> Option<Cow<'_, str>> {
+        self.get_file(path).map_or_else(
+            || read_file(path).ok().map(Cow::Owned),
+            |content| Some(Cow::Borrowed(content.as_str())),
+        )
+    }
+
+    /// Completion candidates for `(area, prefix)`, `compute` is only called
+    /// if there is nothing cached or cached list expired.
+    pub fn get_completion_candidates<F>(
+        &self,
+        area: &M2Area,
+        prefix: &str,
+        compute: F,
+    ) -> Vec<CompletionCandidate>
+    where
+        F: FnOnce() -> Vec<CompletionCandidate>,
+    {
+        let key = (area.clone(), prefix.to_string());
+        if let Some(cached) = self.completion_cache.borrow().get(&key) {
+            if cached.created.elapsed() < COMPLETION_CACHE_TTL {
+                return cached.items.clone();
+            }
+        }
+
+        let items = compute();
+        let mut cache = self.completion_cache.borrow_mut();
+        cache.retain(|_, cached| cached.created.elapsed() < COMPLETION_CACHE_TTL);
+        cache.insert(
+            key,
+            CachedCompletion {
+                created: Instant::now(),
+                items: items.clone(),
+            },
+        );
+        items
+    }
+
+    /// Summary of the class declared in the file, parsed again only when file is modified
+    pub fn get_class_summary(&self, path: &Path) -> Option<ClassSummary> {
+        let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok()?;
+        if let Some((parsed_at, summary)) = self.class_summaries.borrow().get(path) {
+            if *parsed_at == modified {
+                return summary.clone();
+            }
+        }
+        let summary =
+            php::parse_php_file(path, &self.position_encoding).map(|c| ClassSummary::from(&c));
+        self.class_summaries
+            .borrow_mut()
+            .insert(path.to_path_buf(), (modified, summary.clone()));
+        summary
     }
 
     pub fn del_file(&mut self, path: &PathBuf) {
+        self.open_files.remove(path);
         self.buffers.remove(path);
     }
 
+    /// Saved content is final, so it is indexed even for files not tracked on change.
+    /// Without `text` sent by client it is read again from disk.
+    pub fn save_file(&mut self, path: &Path, text: Option<String>) {
+        match text {
+            Some(text) => self.set_file(path, text),
+            None if self.is_open(path) => {
+                if let Ok(content) = read_file(path) {
+                    self.set_file(path, content);
+                }
+            }
+            None => self.update_file_from_disk(path),
+        }
+    }
+
+    /// Index file again after it was created, changed or deleted on disk.
+    /// Opened files are skipped, client keeps sending their content.
+    pub fn update_file_from_disk(&mut self, path: &Path) {
+        if self.is_open(path) {
+            return;
+        }
+        self.clear_from_source(path);
+        if self.options.is_excluded(path) {
+            return;
+        }
+        let Some(lang) = tree_language(path) else {
+            return;
+        };
+        if let Ok(content) = read_file(path) {
+            let tree = ts::parse(&content, lang, None);
+            self.index_file(path, &content, &tree);
+        }
+    }
+
+    // indexers only look at files they know, all of them get tree of file language
+    fn index_file(&mut self, path: &Path, content: &str, tree: &Tree) {
+        js::maybe_index_file(self, content, tree, &path.to_owned());
+        php::maybe_index_file(self, content, tree, &path.to_owned());
+        xml::maybe_index_file(self, content, tree, path);
+    }
+
+    /// Drop buffers of not opened files not accessed since given time.
+    /// Indexed data is kept, dropped files are read from disk when needed.
+    pub fn drop_buffers_idle_since(&mut self, since: Instant) {
+        let open_files = &self.open_files;
+        self.buffers
+            .retain(|path, buffer| open_files.contains(path) || buffer.accessed.get() >= since);
+    }
+
     pub fn get_modules(&self) -> Vec<String> {
         let mut modules = self.modules.clone();
         modules.sort_unstable();
@@ -175,13 +618,45 @@ impl State {
     }
 
     pub fn get_module_path(&self, module: &str) -> Option<PathBuf> {
-        self.module_paths.get(module).cloned()
+        self.module_paths
+            .get(module)
+            .and_then(|paths| paths.last())
+            .cloned()
+    }
+
+    /// All paths module is registered at, preferred one first
+    pub fn get_module_paths(&self, module: &str) -> Vec<PathBuf> {
+        self.module_paths
+            .get(module)
+            .map_or_else(Vec::new, |paths| paths.iter().rev().cloned().collect())
+    }
+
+    /// Class prefix of the module owning the file, the most nested module path wins
+    pub fn get_module_class_prefix_for_path(&self, path: &Path) -> Option<String> {
+        self.module_paths
+            .iter()
+            .filter(|(name, _)| name.contains('\\'))
+            .flat_map(|(name, paths)| paths.iter().map(move |p| (name, p)))
+            .filter(|(_, module_path)| path.starts_with(module_path))
+            .max_by_key(|(_, module_path)| module_path.components().count())
+            .map(|(name, _)| name.clone())
+    }
+
+    pub fn get_module_path_ignore_case(&self, module: &str) -> Option<(String, PathBuf)> {
+        if let Some(path) = self.get_module_path(module) {
+            return Some((module.into(), path));
+        }
+        self.module_paths
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(module))
+            .and_then(|(name, paths)| Some((name.clone(), paths.last()?.clone())))
     }
 
     pub fn add_module(&mut self, module: &str) -> &mut Self {
         self.track_entities
             .maybe_track(self.source_file.as_ref(), Trackee::Module(module.into()));
 
+        self.completion_cache.get_mut().clear();
         self.modules.push(module.into());
         self
     }
@@ -193,10 +668,22 @@ impl State {
         let module = module.into();
         self.track_entities.maybe_track(
             self.source_file.as_ref(),
-            Trackee::ModulePath(module.clone()),
+            Trackee::ModulePath(module.clone(), path.clone()),
         );
 
-        self.module_paths.insert(module, path);
+        self.completion_cache.get_mut().clear();
+        let paths = self.module_paths.entry(module).or_default();
+        paths.retain(|p| p != &path);
+        // app/code overrides vendor copy of the module, regardless of indexing order
+        if path.has_components(&["vendor"])
+            && paths
+                .last()
+                .is_some_and(|existing| existing.has_components(&["app", "code"]))
+        {
+            paths.insert(0, path);
+        } else {
+            paths.push(path);
+        }
         self
     }
 
@@ -210,6 +697,7 @@ impl State {
             Trackee::Themes(M2Area::Adminhtml, name.clone()),
         );
 
+        self.completion_cache.get_mut().clear();
         self.admin_themes.insert(name, path);
     }
 
@@ -223,6 +711,7 @@ impl State {
             Trackee::Themes(M2Area::Frontend, name.clone()),
         );
 
+        self.completion_cache.get_mut().clear();
         self.front_themes.insert(name, path);
     }
 
@@ -262,10 +751,7 @@ impl State {
             Trackee::JsMixin(area.clone(), name.clone()),
         );
 
-        self.js_mixins[area.id()]
-            .entry(name)
-            .or_insert_with(Vec::new)
-            .push(val);
+        self.js_mixins[area.id()].entry(name).or_default().push(val);
     }
 
     pub fn get_component_mixins_for_area<S>(&self, name: S, area: &M2Area) -> Vec<M2Item>
@@ -305,6 +791,285 @@ impl State {
             .collect()
     }
 
+    pub fn add_cache_type<S>(&mut self, name: S, instance: S)
+    where
+        S: Into<String>,
+    {
+        let name = name.into();
+        self.track_entities
+            .maybe_track(self.source_file.as_ref(), Trackee::CacheType(name.clone()));
+
+        self.cache_types.insert(name, instance.into());
+    }
+
+    pub fn get_cache_types(&self) -> Vec<String> {
+        self.cache_types.keys().map(ToString::to_string).collect()
+    }
+
+    pub fn add_config_path<S>(&mut self, path: S, location: Location)
+    where
+        S: Into<String>,
+    {
+        let path = path.into();
+        self.track_entities
+            .maybe_track(self.source_file.as_ref(), Trackee::ConfigPath(path.clone()));
+
+        self.config_paths.entry(path).or_default().push(location);
+    }
+
+    pub fn get_config_path_locations(&self, path: &str) -> Vec<Location> {
+        self.config_paths.get(path).cloned().unwrap_or_default()
+    }
+
+    pub fn get_config_paths(&self) -> Vec<String> {
+        self.config_paths.keys().map(ToString::to_string).collect()
+    }
+
+    pub fn add_di_type<S>(&mut self, name: S, parent_type: S)
+    where
+        S: Into<String>,
+    {
+        let name = name.into();
+        self.track_entities
+            .maybe_track(self.source_file.as_ref(), Trackee::DiType(name.clone()));
+
+        let source = self.source_file.clone().unwrap_or_default();
+        self.di_types
+            .entry(name)
+            .or_default()
+            .push((source, parent_type.into()));
+    }
+
+    // type declared in many files is a virtualType if any of them gives it a parent
+    fn get_di_parent_type(&self, name: &str) -> Option<&String> {
+        self.di_types
+            .get(name)?
+            .iter()
+            .rev()
+            .map(|(_, parent_type)| parent_type)
+            .find(|parent_type| !parent_type.is_empty())
+    }
+
+    pub fn get_di_types(&self) -> Vec<String> {
+        self.di_types.keys().map(ToString::to_string).collect()
+    }
+
+    pub fn get_virtual_types(&self) -> Vec<String> {
+        self.di_types
+            .keys()
+            .filter(|name| self.get_di_parent_type(name).is_some())
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    // virtualType can be based on another virtualType, follow it down to the class
+    pub fn resolve_virtual_type(&self, name: &str) -> String {
+        let mut name = name.trim_matches('\\');
+        // bounded, so alias cycle does not hang
+        for _ in 0..self.di_types.len() {
+            match self.get_di_parent_type(name) {
+                Some(parent_type) => name = parent_type.trim_matches('\\'),
+                None => break,
+            }
+        }
+        name.to_string()
+    }
+
+    pub fn add_event<S>(&mut self, name: S)
+    where
+        S: Into<String>,
+    {
+        let name = name.into();
+        self.track_entities
+            .maybe_track(self.source_file.as_ref(), Trackee::Event(name.clone()));
+
+        let sources = self.events.entry(name).or_default();
+        if let Some(source) = &self.source_file {
+            sources.push(source.clone());
+        }
+    }
+
+    pub fn get_event_names(&self) -> Vec<String> {
+        self.events.keys().map(ToString::to_string).collect()
+    }
+
+    pub fn add_layout_element<S>(&mut self, name: S, location: Location)
+    where
+        S: Into<String>,
+    {
+        let name = name.into();
+        self.track_entities.maybe_track(
+            self.source_file.as_ref(),
+            Trackee::LayoutElement(name.clone()),
+        );
+
+        self.layout_elements.entry(name).or_default().push(location);
+    }
+
+    pub fn get_layout_element_locations(&self, name: &str) -> Vec<Location> {
+        self.layout_elements.get(name).cloned().unwrap_or_default()
+    }
+
+    pub fn add_acl_resource<S>(&mut self, id: S, location: Location)
+    where
+        S: Into<String>,
+    {
+        let id = id.into();
+        self.track_entities
+            .maybe_track(self.source_file.as_ref(), Trackee::AclResource(id.clone()));
+
+        self.acl_resources.entry(id).or_default().push(location);
+    }
+
+    pub fn get_acl_resource_locations(&self, id: &str) -> Vec<Location> {
+        self.acl_resources.get(id).cloned().unwrap_or_default()
+    }
+
+    pub fn get_acl_resources(&self) -> Vec<String> {
+        self.acl_resources.keys().map(ToString::to_string).collect()
+    }
+
+    pub fn add_layout_handle<S>(&mut self, handle: S, location: Location)
+    where
+        S: Into<String>,
+    {
+        let handle = handle.into();
+        self.track_entities.maybe_track(
+            self.source_file.as_ref(),
+            Trackee::LayoutHandle(handle.clone()),
+        );
+
+        self.layout_handles
+            .entry(handle)
+            .or_default()
+            .push(location);
+    }
+
+    pub fn get_layout_handle_locations(&self, handle: &str) -> Vec<Location> {
+        self.layout_handles.get(handle).cloned().unwrap_or_default()
+    }
+
+    pub fn add_route(&mut self, area: &M2Area, module: &str, route_id: &str) {
+        self.track_entities.maybe_track(
+            self.source_file.as_ref(),
+            Trackee::Route(area.clone(), module.into()),
+        );
+
+        self.routes[area.id()].insert(module.into(), route_id.into());
+    }
+
+    pub fn get_route(&self, area: &M2Area, module: &str) -> Option<&String> {
+        self.routes[area.id()].get(module)
+    }
+
+    pub fn add_class_reference(&mut self, class: &str, location: Location) {
+        let class = class.trim_matches('\\').to_string();
+        if class.is_empty() {
+            return;
+        }
+        self.track_entities.maybe_track(
+            self.source_file.as_ref(),
+            Trackee::ClassReference(class.clone()),
+        );
+
+        let locations = self.class_references.entry(class).or_default();
+        if !locations.contains(&location) {
+            locations.push(location);
+        }
+    }
+
+    pub fn get_class_references(&self, class: &str) -> Vec<Location> {
+        self.class_references
+            .get(class.trim_matches('\\'))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Classes known from the index, without scanning module files
+    pub fn get_indexed_classes(&self) -> Vec<String> {
+        let mut classes: Vec<String> = self
+            .class_references
+            .keys()
+            .chain(self.di_types.keys())
+            .filter(|class| class.contains('\\'))
+            .cloned()
+            .collect();
+        classes.sort_unstable();
+        classes.dedup();
+        classes
+    }
+
+    pub fn add_plugin(&mut self, area: &M2Area, type_name: &str, name: &str, plugin: DiPlugin) {
+        let type_name = type_name.trim_matches('\\').to_string();
+        self.track_entities.maybe_track(
+            self.source_file.as_ref(),
+            Trackee::Plugin(area.clone(), type_name.clone(), name.into()),
+        );
+
+        let source = self.source_file.clone().unwrap_or_default();
+        self.plugins[area.id()]
+            .entry((type_name, name.into()))
+            .or_default()
+            .push((source, plugin));
+    }
+
+    pub fn get_plugin(&self, area: &M2Area, type_name: &str, name: &str) -> Option<DiPlugin> {
+        let key = (type_name.trim_matches('\\').to_string(), name.to_string());
+        let mut areas = vec![M2Area::Base.id()];
+        if area != &M2Area::Base {
+            areas.push(area.id());
+        }
+        let mut result: Option<DiPlugin> = None;
+        for entries in areas
+            .into_iter()
+            .filter_map(|id| self.plugins[id].get(&key))
+        {
+            for (_, plugin) in entries {
+                result.get_or_insert_with(DiPlugin::default).merge(plugin);
+            }
+        }
+        result
+    }
+
+    pub fn get_enabled_plugin_names(&self, area: &M2Area, type_name: &str) -> Vec<String> {
+        let type_name = type_name.trim_matches('\\');
+        let mut names = vec![];
+        for plugins in [&self.plugins[M2Area::Base.id()], &self.plugins[area.id()]] {
+            for (plugin_type, name) in plugins.keys() {
+                if plugin_type == type_name && !names.contains(name) {
+                    names.push(name.clone());
+                }
+            }
+        }
+        names.retain(|name| {
+            self.get_plugin(area, type_name, name)
+                .is_some_and(|plugin| plugin.disabled != Some(true))
+        });
+        names
+    }
+
+    /// Types the class is plugged into, in any area
+    pub fn get_plugged_types(&self, class: &str) -> Vec<String> {
+        let class = class.trim_matches('\\');
+        let mut types = self
+            .plugins
+            .iter()
+            .flat_map(HashMap::iter)
+            .filter(|(_, entries)| {
+                entries.iter().any(|(_, plugin)| {
+                    plugin
+                        .class
+                        .as_ref()
+                        .is_some_and(|plugin_class| plugin_class.trim_matches('\\') == class)
+                })
+            })
+            .map(|((type_name, _), _)| type_name.clone())
+            .collect::<Vec<_>>();
+        types.sort_unstable();
+        types.dedup();
+        types
+    }
+
     pub fn list_front_themes_paths(&self) -> Vec<&PathBuf> {
         self.front_themes.values().collect::<Vec<&PathBuf>>()
     }
@@ -325,6 +1090,29 @@ impl State {
         }
     }
 
+    pub fn get_theme_path(&self, area: &M2Area, name: &str) -> Option<&PathBuf> {
+        match area {
+            M2Area::Frontend => self.front_themes.get(&format!("frontend/{}", name)),
+            M2Area::Adminhtml => self.admin_themes.get(&format!("adminhtml/{}", name)),
+            M2Area::Base => self
+                .get_theme_path(&M2Area::Frontend, name)
+                .or_else(|| self.get_theme_path(&M2Area::Adminhtml, name)),
+        }
+    }
+
+    pub fn list_themes_names(&self, area: &M2Area) -> Vec<String> {
+        match area {
+            M2Area::Base => self
+                .admin_themes
+                .keys()
+                .chain(self.front_themes.keys())
+                .cloned()
+                .collect(),
+            M2Area::Adminhtml => self.admin_themes.keys().cloned().collect(),
+            M2Area::Frontend => self.front_themes.keys().cloned().collect(),
+        }
+    }
+
     pub fn workspace_paths(&self) -> Vec<PathBuf> {
         self.workspaces.clone()
     }
@@ -337,14 +1125,6 @@ impl State {
         self.workspaces.contains(&path.to_path_buf())
     }
 
-    pub fn get_item_from_position(&self, path: &PathBuf, pos: Position) -> Option<M2Item> {
-        match path.get_ext().as_str() {
-            "js" => js::get_item_from_position(self, path, pos),
-            "xml" => xml::get_item_from_position(self, path, pos),
-            _ => None,
-        }
-    }
-
     pub fn into_arc(self) -> ArcState {
         Arc::new(Mutex::new(self))
     }
@@ -355,37 +1135,172 @@ impl State {
             vec![]
         } else {
             state.add_workspace_path(path);
-            vec![
-                spawn_index(arc_state, path, php::update_index, "PHP Indexing"),
-                spawn_index(arc_state, path, js::update_index, "JS Indexing"),
-            ]
+            if state.options.lightweight_indexing {
+                return vec![spawn_lightweight_index(arc_state, path)];
+            }
+            spawn_full_index(arc_state, path)
         }
     }
 
-    pub fn split_class_to_path_and_suffix(&self, class: &str) -> Option<(PathBuf, Vec<String>)> {
+    // client did not send any workspace, so find one from the opened file
+    pub fn index_file_workspace(arc_state: &ArcState, file_path: &Path) -> Vec<JoinHandle<()>> {
+        if !arc_state.lock().workspace_paths().is_empty() {
+            return vec![];
+        }
+        m2::find_magento_root(file_path)
+            .map_or_else(Vec::new, |root| Self::update_index(arc_state, &root))
+    }
+
+    // workspaces are indexed again from scratch when new options change what gets indexed
+    pub fn update_options(arc_state: &ArcState, options: Options) -> Vec<JoinHandle<()>> {
+        {
+            let mut state = arc_state.lock();
+            let reindex = state.options.affects_index(&options);
+            state.set_options(options);
+            if !reindex {
+                return vec![];
+            }
+        }
+        Self::reindex(arc_state)
+    }
+
+    /// Drop everything indexed from workspaces and index them again
+    pub fn reindex(arc_state: &ArcState) -> Vec<JoinHandle<()>> {
+        let workspaces = {
+            let mut state = arc_state.lock();
+            let workspaces = std::mem::take(&mut state.workspaces);
+            for workspace in &workspaces {
+                for source_path in state.track_entities.sources_within(workspace) {
+                    state.clear_from_source(&source_path);
+                }
+            }
+            workspaces
+        };
+
+        workspaces
+            .iter()
+            .flat_map(|path| Self::update_index(arc_state, path))
+            .collect()
+    }
+
+    pub fn reindex_module(arc_state: &ArcState, module: &str) -> bool {
+        let module_path = {
+            let mut state = arc_state.lock();
+            let Some(module_path) = state.get_module_path(module) else {
+                return false;
+            };
+            for source_path in state.track_entities.sources_within(&module_path) {
+                state.clear_from_source(&source_path);
+            }
+            module_path
+        };
+
+        // request waits for it, client shows that already
+        let progress = Progress::default();
+        php::update_index(arc_state, &module_path, &progress);
+        js::update_index(arc_state, &module_path, &progress);
+        xml::update_index(arc_state, &module_path, &progress);
+        true
+    }
+
+    /// Paths of the longest registered namespace prefix of the class and the rest of its parts
+    pub fn split_class_to_path_and_suffix(
+        &self,
+        class: &str,
+    ) -> Option<(Vec<PathBuf>, Vec<String>)> {
         let mut parts = class.split('\\').collect::<Vec<_>>();
         let mut suffix = vec![];
 
         while let Some(part) = parts.pop() {
             suffix.push(part.to_string());
             let prefix = parts.join("\\");
-            let module_path = self.get_module_path(&prefix);
-            match module_path {
-                Some(mod_path) => {
-                    suffix.reverse();
-                    return Some((mod_path, suffix));
-                }
-                None => continue,
+            let module_paths = self.get_module_paths(&prefix);
+            if !module_paths.is_empty() {
+                suffix.reverse();
+                return Some((module_paths, suffix));
             }
         }
         None
     }
 }
 
+/// Read file without UTF-8 BOM, it is not part of the document for lsp clients
+/// and would shift tree-sitter byte offsets.
+pub fn read_file(path: &Path) -> std::io::Result<String> {
+    std::fs::read_to_string(path).map(strip_bom)
+}
+
+/// Read file to index, unreadable file is reported and skipped
+pub fn read_indexed_file(path: &Path) -> Option<String> {
+    read_file(path)
+        .map_err(|e| eprintln!("Skipping {}: {e}", path.display()))
+        .ok()
+}
+
+fn strip_bom(mut content: String) -> String {
+    if content.starts_with('\u{feff}') {
+        content.drain(..'\u{feff}'.len_utf8());
+    }
+    content
+}
+
+fn tree_language(path: &Path) -> Option<&'static str> {
+    match path.to_path_buf().get_ext().as_str() {
+        "xml" => Some("html"),
+        "js" => Some("javascript"),
+        "php" | "phtml" => Some("php"),
+        _ => None,
+    }
+}
+
+fn apply_change(
+    content: &mut String,
+    change: &TextDocumentContentChangeEvent,
+    encoding: &PositionEncodingKind,
+) {
+    match change.range {
+        Some(range) => {
+            let start = ts::position_to_offset(content, range.start, encoding);
+            let end = ts::position_to_offset(content, range.end, encoding).max(start);
+            content.replace_range(start..end, &change.text);
+        }
+        None => content.clone_from(&change.text),
+    }
+}
+
+fn spawn_full_index(state: &ArcState, path: &Path) -> Vec<JoinHandle<()>> {
+    vec![
+        spawn_index(state, path, php::update_index, "PHP Indexing"),
+        spawn_index(state, path, js::update_index, "JS Indexing"),
+        spawn_index(state, path, xml::update_index, "XML Indexing"),
+    ]
+}
+
+// modules are discovered first so completion works early, config is parsed after that
+fn spawn_lightweight_index(state: &ArcState, path: &Path) -> JoinHandle<()> {
+    let discovery = spawn_index(
+        state,
+        path,
+        php::update_index_lightweight,
+        "Module Discovery",
+    );
+    let state = Arc::clone(state);
+    let path = path.to_path_buf();
+
+    spawn(move || {
+        if discovery.join().is_err() {
+            return;
+        }
+        for thread in spawn_full_index(&state, &path) {
+            thread.join().ok();
+        }
+    })
+}
+
 fn spawn_index(
     state: &ArcState,
     path: &Path,
-    callback: fn(&ArcState, &PathBuf),
+    callback: fn(&ArcState, &PathBuf, &Progress),
     msg: &str,
 ) -> JoinHandle<()> {
     let state = Arc::clone(state);
@@ -395,10 +1310,685 @@ fn spawn_index(
     spawn(move || {
         eprintln!("Start {}", msg);
         let index_start = SystemTime::now();
-        callback(&state, &path);
+        let progress = Progress::begin(&state, &msg);
+        callback(&state, &path, &progress);
+        progress.end();
         index_start.elapsed().map_or_else(
             |_| eprintln!("{} done", msg),
             |d| eprintln!("{} done in {:?}", msg, d),
         );
     })
 }
+
+#[cfg(test)]
+mod test {
+    use lsp_types::{Position, Range, Url};
+
+    use super::*;
+    use crate::{
+        test_utils::{fixture_path, module_path, TempDir},
+        trace::Trace,
+    };
+
+    #[test]
+    fn test_reindex_module_refreshes_only_that_module() {
+        let module_path = module_path(&[]);
+        let arc_state = State::new().into_arc();
+        {
+            let mut state = arc_state.lock();
+            state.set_source_file(&module_path.append(&["registration.php"]));
+            state
+                .add_module("Some_Module")
+                .add_module_path("Some_Module", module_path.clone());
+            state.set_source_file(&module_path.append(&[
+                "view",
+                "frontend",
+                "requirejs-config.js",
+            ]));
+            state.add_component_map("staleComponent", "Some_Module/js/stale", &M2Area::Frontend);
+            state.set_source_file(&PathBuf::from(
+                "/other/Module/view/frontend/requirejs-config.js",
+            ));
+            state.add_component_map("otherComponent", "Other_Module/js/other", &M2Area::Frontend);
+        }
+
+        assert!(State::reindex_module(&arc_state, "Some_Module"));
+
+        let state = arc_state.lock();
+        assert_eq!(state.get_modules(), vec!["Some_Module"]);
+        assert_eq!(state.get_module_path("Some_Module"), Some(module_path));
+        assert!(state
+            .get_component_map("staleComponent", &M2Area::Frontend)
+            .is_none());
+        assert_eq!(
+            state.get_component_map("someModuleComponent", &M2Area::Frontend),
+            Some(&"Some_Module/js/component".to_string())
+        );
+        assert_eq!(
+            state.get_component_map("otherComponent", &M2Area::Frontend),
+            Some(&"Other_Module/js/other".to_string())
+        );
+    }
+
+    #[test]
+    fn test_index_file_workspace_discovers_magento_root() {
+        let arc_state = State::new().into_arc();
+        let file_path = module_path(&["etc", "di.xml"]);
+
+        for thread in State::index_file_workspace(&arc_state, &file_path) {
+            thread.join().unwrap();
+        }
+
+        let state = arc_state.lock();
+        assert_eq!(state.workspace_paths(), vec![fixture_path(&[])]);
+        assert_eq!(state.get_module_path("Some_Module"), Some(module_path(&[])));
+    }
+
+    #[test]
+    fn test_update_options_reindexes_with_exclude_paths() {
+        let arc_state = State::new().into_arc();
+        for thread in State::update_index(&arc_state, &fixture_path(&[])) {
+            thread.join().unwrap();
+        }
+        assert!(arc_state.lock().get_module_path("Some_Module").is_some());
+
+        let options = Options {
+            exclude_paths: vec!["**/app/code/**".into()],
+            ..Options::default()
+        };
+        for thread in State::update_options(&arc_state, options.clone()) {
+            thread.join().unwrap();
+        }
+
+        let state = arc_state.lock();
+        assert_eq!(state.options(), &options);
+        assert_eq!(state.workspace_paths(), vec![fixture_path(&[])]);
+        assert!(state.get_module_path("Some_Module").is_none());
+        assert!(state.get_module_path("Some_Bundle").is_some());
+    }
+
+    #[test]
+    fn test_lightweight_index_is_followed_by_full_index() {
+        let mut state = State::new();
+        state.set_options(Options {
+            lightweight_indexing: true,
+            ..Options::default()
+        });
+        let arc_state = state.into_arc();
+
+        for thread in State::update_index(&arc_state, &fixture_path(&[])) {
+            thread.join().unwrap();
+        }
+
+        let state = arc_state.lock();
+        assert!(state.get_module_path("Some_Module").is_some());
+        assert_eq!(
+            state.get_component_map("someModuleComponent", &M2Area::Frontend),
+            Some(&"Some_Module/js/component".to_string())
+        );
+        assert!(state
+            .get_config_paths()
+            .iter()
+            .any(|path| path.starts_with("some_section/")));
+    }
+
+    #[test]
+    fn test_update_options_keeps_index_for_other_options() {
+        let arc_state = State::new().into_arc();
+        arc_state.lock().add_workspace_path(&PathBuf::from("/a/b"));
+        let options = Options {
+            trace_resolution: true,
+            ..Options::default()
+        };
+
+        assert!(State::update_options(&arc_state, options).is_empty());
+        assert_eq!(
+            arc_state.lock().workspace_paths(),
+            vec![PathBuf::from("/a/b")]
+        );
+    }
+
+    #[test]
+    fn test_update_file_from_disk_adds_and_removes_module() {
+        let module = TempDir::new("watched");
+        let registration = module.write(
+            &["registration.php"],
+            &std::fs::read_to_string(module_path(&["registration.php"])).unwrap(),
+        );
+        let mut state = State::new();
+
+        state.update_file_from_disk(&registration);
+        assert_eq!(state.get_modules(), vec!["Some_Module"]);
+        assert_eq!(state.get_module_path("Some_Module"), Some(module.path(&[])));
+
+        std::fs::remove_file(&registration).unwrap();
+        state.update_file_from_disk(&registration);
+        assert!(state.get_modules().is_empty());
+        assert!(state.get_module_path("Some_Module").is_none());
+    }
+
+    #[test]
+    fn test_save_file_with_text_updates_index() {
+        let path = module_path(&["etc", "events.xml"]);
+        let mut state = State::new();
+        state.open_file(&path, std::fs::read_to_string(&path).unwrap());
+        assert_eq!(state.get_event_names(), vec!["some_module_test_event"]);
+
+        state.save_file(
+            &path,
+            Some(r#"<config><event name="saved_event"><observer/></event></config>"#.into()),
+        );
+
+        assert_eq!(state.get_event_names(), vec!["saved_event"]);
+    }
+
+    #[test]
+    fn test_save_file_without_text_reads_from_disk() {
+        let registration = module_path(&["registration.php"]);
+        let mut state = State::new();
+        state.open_file(&registration, "<?php\n");
+        assert!(state.get_modules().is_empty());
+
+        state.save_file(&registration, None);
+
+        assert_eq!(state.get_modules(), vec!["Some_Module"]);
+        assert!(state
+            .get_file(&registration)
+            .is_some_and(|content| content.contains("Some_Module")));
+    }
+
+    #[test]
+    fn test_split_class_to_path_and_suffix_prefers_longest_prefix() {
+        let mut state = State::new();
+        state
+            .add_module_path(
+                "Magento\\Framework",
+                PathBuf::from("/a/lib/internal/Magento/Framework"),
+            )
+            .add_module_path(
+                "Magento\\Framework\\App",
+                PathBuf::from("/a/vendor/magento/framework-app"),
+            );
+
+        assert_eq!(
+            state.split_class_to_path_and_suffix(
+                "Magento\\Framework\\App\\ResourceConnection\\Config"
+            ),
+            Some((
+                vec![PathBuf::from("/a/vendor/magento/framework-app")],
+                vec!["ResourceConnection".to_string(), "Config".to_string()]
+            ))
+        );
+        assert_eq!(
+            state.split_class_to_path_and_suffix("Magento\\Framework\\Url"),
+            Some((
+                vec![PathBuf::from("/a/lib/internal/Magento/Framework")],
+                vec!["Url".to_string()]
+            ))
+        );
+    }
+
+    #[test]
+    fn test_index_file_workspace_skipped_with_workspace() {
+        let arc_state = State::new().into_arc();
+        arc_state.lock().add_workspace_path(&PathBuf::from("/a/b"));
+        let file_path = module_path(&["etc", "di.xml"]);
+
+        assert!(State::index_file_workspace(&arc_state, &file_path).is_empty());
+        assert_eq!(
+            arc_state.lock().workspace_paths(),
+            vec![PathBuf::from("/a/b")]
+        );
+    }
+
+    fn change(
+        range: Option<((u32, u32), (u32, u32))>,
+        text: &str,
+    ) -> TextDocumentContentChangeEvent {
+        TextDocumentContentChangeEvent {
+            range: range.map(|(start, end)| lsp_types::Range {
+                start: Position::new(start.0, start.1),
+                end: Position::new(end.0, end.1),
+            }),
+            range_length: None,
+            text: text.into(),
+        }
+    }
+
+    #[test]
+    fn test_change_file_applies_incremental_changes() {
+        let mut state = State::new();
+        let path = PathBuf::from("/a/b/c/etc/events.xml");
+        state.set_file(&path, "<config>\n    <event name=\"föö\"/>\n</config>\n");
+
+        state.change_file(
+            &path,
+            &[
+                change(Some(((1, 17), (1, 20))), "some_event"),
+                change(Some(((2, 0), (2, 0))), "    <event name=\"other\"/>\n"),
+            ],
+        );
+
+        assert_eq!(
+            state.get_file(&path).unwrap(),
+            "<config>\n    <event name=\"some_event\"/>\n    <event name=\"other\"/>\n</config>\n"
+        );
+        let mut events = state.get_event_names();
+        events.sort_unstable();
+        assert_eq!(events, vec!["other", "some_event"]);
+    }
+
+    #[test]
+    fn test_change_file_uses_state_position_encoding() {
+        let path = PathBuf::from("/a/b/c/etc/events.xml");
+        let content = "<config>\n    <event name=\"föö\"/>\n</config>\n";
+        let mut utf8_state = State::new();
+        utf8_state.set_position_encoding(PositionEncodingKind::UTF8);
+        utf8_state.set_file(&path, content);
+        let mut utf16_state = State::new();
+        utf16_state.set_file(&path, content);
+
+        // `föö` is 3 utf-16 code units, but 5 bytes
+        utf8_state.change_file(&path, &[change(Some(((1, 17), (1, 22))), "bar")]);
+        utf16_state.change_file(&path, &[change(Some(((1, 17), (1, 20))), "bar")]);
+
+        let expected = "<config>\n    <event name=\"bar\"/>\n</config>\n";
+        assert_eq!(utf8_state.get_file(&path).unwrap(), expected);
+        assert_eq!(utf16_state.get_file(&path).unwrap(), expected);
+    }
+
+    fn tree_nodes(node: tree_sitter::Node) -> Vec<(String, usize, usize)> {
+        let mut nodes = vec![(node.kind().to_string(), node.start_byte(), node.end_byte())];
+        let mut cursor = node.walk();
+        for child in node.children(&mut cursor) {
+            nodes.extend(tree_nodes(child));
+        }
+        nodes
+    }
+
+    #[test]
+    fn test_change_file_reparse_matches_full_parse() {
+        let mut state = State::new();
+        let path = PathBuf::from("/a/b/c/Model/Some.php");
+        state.set_file(&path, "<?php\nclass Some\n{\n    const NAME = 'föö';\n}\n");
+
+        state.change_file(
+            &path,
+            &[
+                change(Some(((3, 18), (3, 21))), "bar"),
+                change(
+                    Some(((3, 24), (3, 24))),
+                    "\n    public function get() {\n        return 'ą';\n    }",
+                ),
+                change(Some(((1, 6), (1, 10))), "Other"),
+            ],
+        );
+
+        let content = state.get_file(&path).unwrap().clone();
+        assert_eq!(
+            content,
+            "<?php\nclass Other\n{\n    const NAME = 'bar';\n    public function get() {\n        return 'ą';\n    }\n}\n"
+        );
+        let incremental = state.get_tree(&path, &content).unwrap();
+        let full = ts::parse(&content, "php", None);
+        assert_eq!(
+            tree_nodes(incremental.root_node()),
+            tree_nodes(full.root_node())
+        );
+    }
+
+    #[test]
+    fn test_change_file_full_document() {
+        let mut state = State::new();
+        let path = PathBuf::from("/a/b/c/etc/events.xml");
+        state.set_file(&path, "<config/>");
+
+        state.change_file(&path, &[change(None, "<config></config>")]);
+
+        assert_eq!(state.get_file(&path).unwrap(), "<config></config>");
+    }
+
+    #[test]
+    fn test_completion_candidates_are_cached_until_index_changes() {
+        let mut state = State::new();
+        let computed = Cell::new(0);
+        let compute = || {
+            computed.set(computed.get() + 1);
+            vec![CompletionCandidate::from(
+                "Some_Module::a.phtml".to_string(),
+            )]
+        };
+
+        state.get_completion_candidates(&M2Area::Frontend, "Some_Module::", compute);
+        let items = state.get_completion_candidates(&M2Area::Frontend, "Some_Module::", compute);
+        assert_eq!(items[0].label, "Some_Module::a.phtml");
+        assert_eq!(computed.get(), 1);
+
+        state.get_completion_candidates(&M2Area::Adminhtml, "Some_Module::", compute);
+        assert_eq!(computed.get(), 2);
+
+        state.add_module_path("Other_Module", PathBuf::from("/a/b/c"));
+        state.get_completion_candidates(&M2Area::Frontend, "Some_Module::", compute);
+        assert_eq!(computed.get(), 3);
+    }
+
+    #[test]
+    fn test_completion_candidates_are_cached_while_typing() {
+        let mut state = State::new();
+        let registration = module_path(&["registration.php"]);
+        state.open_file(
+            &registration,
+            std::fs::read_to_string(&registration).unwrap(),
+        );
+        let path = module_path(&["etc", "di.xml"]);
+        state.open_file(&path, "<config></config>");
+        let computed = Cell::new(0);
+        let compute = || {
+            computed.set(computed.get() + 1);
+            vec![]
+        };
+        let typed = |character: u32, text: &str| TextDocumentContentChangeEvent {
+            range: Some(Range::new(
+                Position::new(0, character),
+                Position::new(0, character),
+            )),
+            range_length: None,
+            text: text.into(),
+        };
+
+        state.get_completion_candidates(&M2Area::Base, "Some\\", compute);
+        state.change_file(&path, &[typed(8, "<type/>")]);
+        state.change_file(&registration, &[typed(5, " ")]);
+        state.get_completion_candidates(&M2Area::Base, "Some\\", compute);
+        assert_eq!(computed.get(), 1);
+
+        state.change_file(
+            &registration,
+            &[TextDocumentContentChangeEvent {
+                range: None,
+                range_length: None,
+                text: "<?php\n".into(),
+            }],
+        );
+        state.get_completion_candidates(&M2Area::Base, "Some\\", compute);
+        assert_eq!(computed.get(), 2);
+    }
+
+    #[test]
+    fn test_set_file_strips_bom() {
+        let mut state = State::new();
+        let path = PathBuf::from("/a/b/c/etc/di.xml");
+        state.set_file(
+            &path,
+            "\u{feff}<?xml version=\"1.0\"?>\n<config><type name=\"Some\\Module\\Model\\Config\"/></config>",
+        );
+
+        assert!(state.get_file(&path).unwrap().starts_with("<?xml"));
+        assert_eq!(
+            xml::get_item_from_position(&state, &path, Position::new(1, 20), &mut Trace::default()),
+            Some(M2Item::Class("Some\\Module\\Model\\Config".into()))
+        );
+        assert_eq!(
+            state.get_class_references("Some\\Module\\Model\\Config")[0]
+                .range
+                .start,
+            Position::new(1, 8)
+        );
+    }
+
+    #[test]
+    fn test_reindex_module_unknown_module() {
+        let arc_state = State::new().into_arc();
+        assert!(!State::reindex_module(&arc_state, "Unknown_Module"));
+    }
+
+    #[test]
+    fn test_plugin_disabled_globally_and_enabled_in_area() {
+        let mut state = State::new();
+        state.add_plugin(
+            &M2Area::Base,
+            "Some\\Module\\Model\\Service",
+            "some_plugin",
+            DiPlugin {
+                class: Some("Some\\Module\\Plugin\\ServicePlugin".into()),
+                disabled: Some(true),
+            },
+        );
+        state.add_plugin(
+            &M2Area::Frontend,
+            "Some\\Module\\Model\\Service",
+            "some_plugin",
+            DiPlugin {
+                class: None,
+                disabled: Some(false),
+            },
+        );
+
+        let type_name = "Some\\Module\\Model\\Service";
+        assert_eq!(
+            state.get_enabled_plugin_names(&M2Area::Frontend, type_name),
+            vec!["some_plugin"]
+        );
+        assert!(state
+            .get_enabled_plugin_names(&M2Area::Adminhtml, type_name)
+            .is_empty());
+        assert_eq!(
+            state.get_plugin(&M2Area::Frontend, type_name, "some_plugin"),
+            Some(DiPlugin {
+                class: Some("Some\\Module\\Plugin\\ServicePlugin".into()),
+                disabled: Some(false),
+            })
+        );
+    }
+
+    #[test]
+    fn test_plugin_disabled_in_other_file_keeps_class() {
+        let mut state = State::new();
+        let type_name = "Some\\Module\\Model\\Service";
+        state.set_source_file(&PathBuf::from("/a/etc/di.xml"));
+        state.add_plugin(
+            &M2Area::Base,
+            type_name,
+            "some_plugin",
+            DiPlugin {
+                class: Some("Some\\Module\\Plugin\\ServicePlugin".into()),
+                disabled: None,
+            },
+        );
+        state.set_source_file(&PathBuf::from("/b/etc/di.xml"));
+        state.add_plugin(
+            &M2Area::Base,
+            type_name,
+            "some_plugin",
+            DiPlugin {
+                class: None,
+                disabled: Some(true),
+            },
+        );
+
+        assert_eq!(
+            state.get_plugin(&M2Area::Base, type_name, "some_plugin"),
+            Some(DiPlugin {
+                class: Some("Some\\Module\\Plugin\\ServicePlugin".into()),
+                disabled: Some(true),
+            })
+        );
+
+        state.clear_from_source(&PathBuf::from("/b/etc/di.xml"));
+        assert_eq!(
+            state.get_enabled_plugin_names(&M2Area::Base, type_name),
+            vec!["some_plugin"]
+        );
+        assert_eq!(
+            state.get_plugged_types("Some\\Module\\Plugin\\ServicePlugin"),
+            vec![type_name]
+        );
+
+        state.clear_from_source(&PathBuf::from("/a/etc/di.xml"));
+        assert_eq!(
+            state.get_plugin(&M2Area::Base, type_name, "some_plugin"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_virtual_type_kept_while_declared_in_other_file() {
+        let mut state = State::new();
+        state.set_source_file(&PathBuf::from("/a/etc/di.xml"));
+        state.add_di_type("someVirtualType", "Some\\Module\\Model\\Service");
+        state.set_source_file(&PathBuf::from("/b/etc/di.xml"));
+        state.add_di_type("someVirtualType", "");
+
+        assert_eq!(state.get_virtual_types(), vec!["someVirtualType"]);
+
+        state.clear_from_source(&PathBuf::from("/b/etc/di.xml"));
+        assert_eq!(
+            state.resolve_virtual_type("someVirtualType"),
+            "Some\\Module\\Model\\Service"
+        );
+
+        state.clear_from_source(&PathBuf::from("/a/etc/di.xml"));
+        assert!(state.get_di_types().is_empty());
+    }
+
+    #[test]
+    fn test_config_path_kept_while_declared_in_other_file() {
+        let mut state = State::new();
+        for path in ["/a/etc/adminhtml/system.xml", "/b/etc/adminhtml/system.xml"] {
+            let location = Location {
+                uri: Url::from_file_path(path).unwrap(),
+                range: Range::default(),
+            };
+            state.set_source_file(&PathBuf::from(path));
+            state.add_config_path("some/group/field", location);
+        }
+
+        state.clear_from_source(&PathBuf::from("/a/etc/adminhtml/system.xml"));
+        let locations = state.get_config_path_locations("some/group/field");
+        assert_eq!(locations.len(), 1);
+        assert_eq!(
+            locations[0].uri.to_file_path().unwrap(),
+            PathBuf::from("/b/etc/adminhtml/system.xml")
+        );
+
+        state.clear_from_source(&PathBuf::from("/b/etc/adminhtml/system.xml"));
+        assert!(state.get_config_paths().is_empty());
+    }
+
+    #[test]
+    fn test_event_kept_while_observed_in_other_file() {
+        let mut state = State::new();
+        state.set_source_file(&PathBuf::from("/a/etc/events.xml"));
+        state.add_event("some_event");
+        state.set_source_file(&PathBuf::from("/b/etc/events.xml"));
+        state.add_event("some_event");
+
+        state.clear_from_source(&PathBuf::from("/a/etc/events.xml"));
+        assert_eq!(state.get_event_names(), vec!["some_event"]);
+
+        state.clear_from_source(&PathBuf::from("/b/etc/events.xml"));
+        assert!(state.get_event_names().is_empty());
+    }
+
+    #[test]
+    fn test_module_path_from_app_code_wins_over_vendor() {
+        let vendor_path = PathBuf::from("/a/vendor/some/module");
+        let app_code_path = PathBuf::from("/a/app/code/Some/Module");
+
+        let mut state = State::new();
+        state.add_module_path("Some_Module", vendor_path.clone());
+        state.add_module_path("Some_Module", app_code_path.clone());
+        assert_eq!(
+            state.get_module_path("Some_Module"),
+            Some(app_code_path.clone())
+        );
+
+        let mut state = State::new();
+        state.add_module_path("Some_Module", app_code_path.clone());
+        state.add_module_path("Some_Module", vendor_path);
+        assert_eq!(state.get_module_path("Some_Module"), Some(app_code_path));
+    }
+
+    #[test]
+    fn test_module_paths_from_multiple_roots() {
+        let first_path = PathBuf::from("/a/app/code/Some/Module");
+        let second_path = PathBuf::from("/b/app/code/Some/Module");
+
+        let mut state = State::new();
+        state.set_source_file(&first_path.join("registration.php"));
+        state.add_module_path("Some_Module", first_path.clone());
+        state.set_source_file(&second_path.join("registration.php"));
+        state.add_module_path("Some_Module", second_path.clone());
+
+        assert_eq!(
+            state.get_module_paths("Some_Module"),
+            vec![second_path.clone(), first_path.clone()]
+        );
+
+        state.clear_from_source(&second_path.join("registration.php"));
+        assert_eq!(state.get_module_path("Some_Module"), Some(first_path));
+    }
+
+    #[test]
+    fn test_get_class_summary_cached_per_file() {
+        let state = State::new();
+        let path = module_path(&["Model", "Stable.php"]);
+
+        let summary = state.get_class_summary(&path);
+
+        assert_eq!(
+            summary,
+            Some(ClassSummary {
+                api: true,
+                parents: vec![],
+            })
+        );
+        assert!(state.class_summaries.borrow().contains_key(&path));
+        assert_eq!(state.get_class_summary(&path), summary);
+        assert_eq!(
+            state.get_class_summary(&fixture_path(&["missing.php"])),
+            None
+        );
+    }
+
+    #[test]
+    fn test_drop_idle_buffers_keeps_index() {
+        let mut state = State::new();
+        let old_path = PathBuf::from("/a/b/c/etc/cache.xml");
+        let new_path = PathBuf::from("/a/b/d/etc/cache.xml");
+        state.set_file(
+            &old_path,
+            r#"<config><type name="full_page" instance="Some\Cache"/></config>"#,
+        );
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        let since = Instant::now();
+        state.set_file(&new_path, "<config/>");
+
+        state.drop_buffers_idle_since(since);
+
+        assert_eq!(state.get_file(&old_path), None);
+        assert_eq!(state.get_file(&new_path), Some(&"<config/>".to_string()));
+        assert_eq!(state.get_cache_types(), vec!["full_page"]);
+    }
+
+    #[test]
+    fn test_drop_idle_buffers_keeps_open_files() {
+        let mut state = State::new();
+        let open_path = PathBuf::from("/a/b/c/etc/events.xml");
+        let saved_path = PathBuf::from("/a/b/d/etc/events.xml");
+        state.open_file(&open_path, "<config>\n</config>\n");
+        state.set_file(&saved_path, "<config>\n</config>\n");
+        std::thread::sleep(std::time::Duration::from_millis(2));
+
+        state.drop_buffers_idle_since(Instant::now());
+        let inserted = change(Some(((1, 0), (1, 0))), "    <event name=\"some_event\"/>\n");
+        state.change_file(&open_path, std::slice::from_ref(&inserted));
+        state.change_file(&saved_path, &[inserted]);
+
+        assert_eq!(
+            state.get_file(&open_path),
+            Some(&"<config>\n    <event name=\"some_event\"/>\n</config>\n".to_string())
+        );
+        assert_eq!(state.get_file(&saved_path), None);
+    }
+}
@@ -0,0 +1,92 @@
+use std::path::PathBuf;
+
+use lsp_types::Position;
+
+use crate::{
+    lsp::definition,
+    m2::{M2Item, M2Path},
+    state::State,
+    trace::Trace,
+};
+
+pub fn fixture_path(parts: &[&str]) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .append(&["tests"])
+        .append(parts)
+}
+
+// `Some_Module` fixture module, with `Some\Module` classes
+pub fn module_path(parts: &[&str]) -> PathBuf {
+    fixture_path(&["app", "code", "Some", "Module"]).append(parts)
+}
+
+pub fn state_with_module() -> State {
+    let mut state = State::new();
+    state
+        .add_module("Some_Module")
+        .add_module_path("Some_Module", module_path(&[]))
+        .add_module_path("Some\\Module", module_path(&[]));
+    state
+}
+
+/// Content without `|` cursor marker and position of the marker
+pub fn split_cursor(text: &str) -> (String, Position) {
+    let (line, l) = text
+        .lines()
+        .enumerate()
+        .find(|(_, l)| l.contains('|'))
+        .expect("Test has to have a | character");
+    let column = l.find('|').expect("Test has to have a | character");
+    let character = l[..column].encode_utf16().count();
+    (
+        text.replace('|', ""),
+        Position::new(line as u32, character as u32),
+    )
+}
+
+/// Item under `|` cursor marker, resolved the same way as for goto definition
+pub fn item_at_cursor(state: &mut State, content: &str, path: &str) -> Option<M2Item> {
+    let (content, pos) = split_cursor(content);
+    let path = PathBuf::from(path);
+    state.set_file(&path, content);
+    definition::resolve_item(state, &path, pos, &mut Trace::default())
+}
+
+/// Item under `|` cursor marker, with `Some_Module` registered in `/a/b/c/Some_Module`
+pub fn get_test_item(content: &str, path: &str) -> Option<M2Item> {
+    let mut state = State::new();
+    state.add_module_path("Some_Module", PathBuf::from("/a/b/c/Some_Module"));
+    item_at_cursor(&mut state, content, path)
+}
+
+/// Writable directory for a test, removed on drop even when the test fails
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        let path =
+            std::env::temp_dir().append(&[&format!("magento2-ls-{name}-{}", std::process::id())]);
+        std::fs::create_dir_all(&path).expect("Temp dir should be created");
+        Self(path)
+    }
+
+    pub fn path(&self, parts: &[&str]) -> PathBuf {
+        self.0.append(parts)
+    }
+
+    /// Write file under the directory, creating missing parent directories
+    pub fn write(&self, parts: &[&str], content: &str) -> PathBuf {
+        let path = self.path(parts);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).expect("Parent dir should be created");
+        }
+        std::fs::write(&path, content).expect("Temp file should be written");
+        path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        std::fs::remove_dir_all(&self.0).ok();
+    }
+}
@@ -0,0 +1,27 @@
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Trace {
+    enabled: bool,
+    steps: Vec<String>,
+}
+
+impl Trace {
+    pub const fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            steps: vec![],
+        }
+    }
+
+    pub fn step<F>(&mut self, message: F)
+    where
+        F: FnOnce() -> String,
+    {
+        if self.enabled {
+            self.steps.push(message());
+        }
+    }
+
+    pub fn steps(&self) -> &[String] {
+        &self.steps
+    }
+}
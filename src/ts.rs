@@ -1,58 +1,151 @@
-use lsp_types::{Position, Range};
-use tree_sitter::Node;
+use lsp_types::{Position, PositionEncodingKind, Range};
+use tree_sitter::{InputEdit, Node, Parser, Point, Tree};
 
-pub fn get_range_from_node(node: Node) -> Range {
+use crate::queries;
+
+// tree-sitter columns are counted in bytes, lsp ones in utf-16 code units
+// unless client negotiated utf-8 positions, then no conversion is needed
+fn is_utf8(encoding: &PositionEncodingKind) -> bool {
+    *encoding == PositionEncodingKind::UTF8
+}
+
+pub fn get_range_from_node(node: Node, content: &str, encoding: &PositionEncodingKind) -> Range {
+    let utf8 = is_utf8(encoding);
     Range {
-        start: Position {
-            line: node.start_position().row as u32,
-            character: node.start_position().column as u32,
-        },
-        end: Position {
-            line: node.end_position().row as u32,
-            character: node.end_position().column as u32,
-        },
+        start: point_to_position(content, node.start_byte(), node.start_position(), utf8),
+        end: point_to_position(content, node.end_byte(), node.end_position(), utf8),
+    }
+}
+
+fn point_to_position(content: &str, byte: usize, point: Point, utf8: bool) -> Position {
+    let line_start = byte - point.column;
+    let character = match content.get(line_start..byte) {
+        Some(text) if !utf8 => text.encode_utf16().count(),
+        _ => point.column,
+    };
+    Position {
+        line: point.row as u32,
+        character: character as u32,
     }
 }
 
-pub fn get_node_text_before_pos(node: Node, content: &str, pos: Position) -> String {
-    let text = node.utf8_text(content.as_bytes()).unwrap_or("");
+/// Byte offset of lsp position in the content
+pub fn position_to_offset(content: &str, pos: Position, encoding: &PositionEncodingKind) -> usize {
+    let mut offset = 0;
+    for (line_no, line) in content.split_inclusive('\n').enumerate() {
+        if line_no == pos.line as usize {
+            return offset + character_to_column(line, pos.character, is_utf8(encoding));
+        }
+        offset += line.len();
+    }
+    content.len()
+}
 
-    let node_start_pos = node.start_position();
-    let node_end_pos = node.end_position();
+pub fn offset_to_position(
+    content: &str,
+    offset: usize,
+    encoding: &PositionEncodingKind,
+) -> Position {
+    point_to_position(
+        content,
+        offset,
+        offset_to_point(content, offset),
+        is_utf8(encoding),
+    )
+}
 
-    let text = if node_end_pos.row == node_start_pos.row {
-        text.to_string()
-    } else {
-        let take_lines = pos.line as usize - node_start_pos.row;
-        text.split('\n')
-            .take(take_lines + 1)
-            .collect::<Vec<&str>>()
-            .join("\n")
+fn offset_to_point(content: &str, offset: usize) -> Point {
+    let line_start = content[..offset].rfind('\n').map_or(0, |i| i + 1);
+    let row = content[..line_start].matches('\n').count();
+    Point::new(row, offset - line_start)
+}
+
+/// Parse content, `old_tree` has to be edited to match it already, then only
+/// changed parts are parsed again
+pub fn parse(content: &str, lang: &str, old_tree: Option<&Tree>) -> Tree {
+    let mut parser = Parser::new();
+    parser
+        .set_language(queries::get_language(lang))
+        .expect("Language should be compatible with parser");
+    parser
+        .parse(content, old_tree)
+        .expect("Parser should have language set")
+}
+
+/// Tree edit for replacing `range` of the content with `new_text`,
+/// has to be computed before the change is applied
+pub fn input_edit(
+    content: &str,
+    range: Range,
+    new_text: &str,
+    encoding: &PositionEncodingKind,
+) -> InputEdit {
+    let start_byte = position_to_offset(content, range.start, encoding);
+    let old_end_byte = position_to_offset(content, range.end, encoding).max(start_byte);
+    let start_position = offset_to_point(content, start_byte);
+    let new_end_position = match new_text.rfind('\n') {
+        Some(i) => Point::new(
+            start_position.row + new_text.matches('\n').count(),
+            new_text.len() - i - 1,
+        ),
+        None => Point::new(start_position.row, start_position.column + new_text.len()),
     };
+    InputEdit {
+        start_byte,
+        old_end_byte,
+        new_end_byte: start_byte + new_text.len(),
+        start_position,
+        old_end_position: offset_to_point(content, old_end_byte),
+        new_end_position,
+    }
+}
 
-    if pos.line as usize == node_start_pos.row {
-        let end = pos.character as usize - node_start_pos.column;
-        text.chars().take(end).collect::<String>()
-    } else {
-        let mut trimed = false;
-        let mut lines = text
-            .split('\n')
-            .rev()
-            .map(|line| {
-                if trimed {
-                    line.to_owned()
-                } else {
-                    trimed = true;
-                    line.chars()
-                        .take(pos.character as usize)
-                        .collect::<String>()
-                }
-            })
-            .collect::<Vec<String>>();
-
-        lines.reverse();
-        lines.join("\n")
+pub fn position_to_point(content: &str, pos: Position, encoding: &PositionEncodingKind) -> Point {
+    let offset = position_to_offset(content, pos, encoding);
+    let line_start = content[..offset].rfind('\n').map_or(0, |i| i + 1);
+    Point::new(pos.line as usize, offset - line_start)
+}
+
+// byte column of lsp character in the line, line end if it is too short
+fn character_to_column(line: &str, character: u32, utf8: bool) -> usize {
+    let mut units = 0;
+    for (i, c) in line.char_indices() {
+        if units >= character as usize || c == '\n' {
+            return i;
+        }
+        units += if utf8 { c.len_utf8() } else { c.len_utf16() };
     }
+    line.len()
+}
+
+pub fn get_node_text_before_pos(
+    node: Node,
+    content: &str,
+    pos: Position,
+    encoding: &PositionEncodingKind,
+) -> String {
+    let start = node.start_byte();
+    let end = node.end_byte();
+    let start_row = node.start_position().row;
+    let Some(text) = content.get(start..end) else {
+        return String::new();
+    };
+    if (pos.line as usize) < start_row {
+        return String::new();
+    }
+
+    let line_start = if pos.line as usize == start_row {
+        Some(start - node.start_position().column)
+    } else {
+        text.match_indices('\n')
+            .nth(pos.line as usize - start_row - 1)
+            .map(|(i, _)| start + i + 1)
+    };
+    let offset = line_start.map_or(end, |line_start| {
+        line_start + character_to_column(&content[line_start..], pos.character, is_utf8(encoding))
+    });
+
+    content[start..offset.clamp(start, end)].to_string()
 }
 
 pub fn get_node_str<'a>(node: Node, content: &'a str) -> &'a str {
@@ -61,16 +154,20 @@ pub fn get_node_str<'a>(node: Node, content: &'a str) -> &'a str {
         .trim_matches('\\')
 }
 
-pub fn node_at_position(node: Node, pos: Position) -> bool {
-    let start = node.start_position();
-    let end = node.end_position();
-    if pos.line < start.row as u32 || pos.line > end.row as u32 {
+pub fn node_at_position(
+    node: Node,
+    content: &str,
+    pos: Position,
+    encoding: &PositionEncodingKind,
+) -> bool {
+    let range = get_range_from_node(node, content, encoding);
+    if pos.line < range.start.line || pos.line > range.end.line {
         return false;
     }
-    if pos.line == start.row as u32 && pos.character < start.column as u32 {
+    if pos.line == range.start.line && pos.character < range.start.character {
         return false;
     }
-    if pos.line == end.row as u32 && pos.character > end.column as u32 {
+    if pos.line == range.end.line && pos.character > range.end.character {
         return false;
     }
     true
@@ -80,3 +177,127 @@ pub fn node_last_child(node: Node) -> Option<Node> {
     let children_count = node.child_count();
     node.child(children_count - 1)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn find_node<'a>(node: Node<'a>, kind: &str) -> Option<Node<'a>> {
+        if node.kind() == kind {
+            return Some(node);
+        }
+        let mut cursor = node.walk();
+        let children: Vec<Node<'a>> = node.children(&mut cursor).collect();
+        children
+            .into_iter()
+            .find_map(|child| find_node(child, kind))
+    }
+
+    #[test]
+    fn test_position_to_offset_counts_utf16() {
+        let content = "a😀b\nc";
+        assert_eq!(
+            position_to_offset(content, Position::new(0, 3), &PositionEncodingKind::UTF16),
+            5
+        );
+        assert_eq!(
+            position_to_offset(content, Position::new(0, 99), &PositionEncodingKind::UTF16),
+            6
+        );
+        assert_eq!(
+            position_to_offset(content, Position::new(1, 1), &PositionEncodingKind::UTF16),
+            8
+        );
+        assert_eq!(
+            position_to_offset(content, Position::new(5, 0), &PositionEncodingKind::UTF16),
+            8
+        );
+        assert_eq!(
+            position_to_point(content, Position::new(0, 3), &PositionEncodingKind::UTF16),
+            Point::new(0, 5)
+        );
+    }
+
+    #[test]
+    fn test_input_edit_for_multiline_text() {
+        let content = "a😀b\ncd";
+        let range = Range::new(Position::new(0, 3), Position::new(1, 1));
+        let edit = input_edit(content, range, "x\nyz", &PositionEncodingKind::UTF16);
+
+        assert_eq!(edit.start_byte, 5);
+        assert_eq!(edit.old_end_byte, 8);
+        assert_eq!(edit.new_end_byte, 9);
+        assert_eq!(edit.start_position, Point::new(0, 5));
+        assert_eq!(edit.old_end_position, Point::new(1, 1));
+        assert_eq!(edit.new_end_position, Point::new(1, 2));
+    }
+
+    #[test]
+    fn test_utf8_positions_are_byte_columns() {
+        let content = "a😀b\nc";
+        assert_eq!(character_to_column(content, 5, true), 5);
+        assert_eq!(character_to_column(content, 3, false), 5);
+        assert_eq!(
+            point_to_position(content, 5, Point::new(0, 5), true),
+            Position::new(0, 5)
+        );
+        assert_eq!(
+            point_to_position(content, 5, Point::new(0, 5), false),
+            Position::new(0, 3)
+        );
+    }
+
+    #[test]
+    fn test_node_position_after_multibyte_char() {
+        let content = "<!-- żółć --><a href=\"Some_Module::x\"/>";
+        let tree = tree_sitter_parsers::parse(content, "html");
+        let node = find_node(tree.root_node(), "attribute_value").unwrap();
+
+        // `<!-- żółć -->` is 13 characters, but 17 bytes
+        assert_eq!(
+            get_range_from_node(node, content, &PositionEncodingKind::UTF16),
+            Range {
+                start: Position::new(0, 22),
+                end: Position::new(0, 36),
+            }
+        );
+        assert!(node_at_position(
+            node,
+            content,
+            Position::new(0, 22),
+            &PositionEncodingKind::UTF16
+        ));
+        assert!(!node_at_position(
+            node,
+            content,
+            Position::new(0, 37),
+            &PositionEncodingKind::UTF16
+        ));
+        assert_eq!(
+            get_node_text_before_pos(
+                node,
+                content,
+                Position::new(0, 34),
+                &PositionEncodingKind::UTF16
+            ),
+            "Some_Module:"
+        );
+    }
+
+    #[test]
+    fn test_get_node_text_before_pos_multiline() {
+        let content = "<a>\n  ąb\n  cd</a>";
+        let tree = tree_sitter_parsers::parse(content, "html");
+        let node = find_node(tree.root_node(), "text").unwrap();
+
+        assert_eq!(
+            get_node_text_before_pos(
+                node,
+                content,
+                Position::new(2, 3),
+                &PositionEncodingKind::UTF16
+            ),
+            "\n  ąb\n  c"
+        );
+    }
+}
@@ -1,13 +1,23 @@
-use lsp_types::{Position, Range};
-use std::{collections::HashMap, path::PathBuf};
-use tree_sitter::{Node, QueryCursor};
+use glob::glob;
+use lsp_types::{DocumentSymbol, Location, Position, PositionEncodingKind, Range, SymbolKind, Url};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+use tree_sitter::{Node, QueryCursor, Tree};
 
 use crate::{
     js,
     m2::{self, M2Item, M2Path},
+    php,
+    progress::Progress,
     queries,
-    state::State,
-    ts::{get_node_str, get_node_text_before_pos, node_at_position, node_last_child},
+    state::{self, ArcState, DiPlugin, State},
+    trace::Trace,
+    ts::{
+        get_node_str, get_node_text_before_pos, get_range_from_node, node_at_position,
+        node_last_child,
+    },
 };
 
 #[allow(clippy::module_name_repetitions)]
@@ -33,16 +43,20 @@ impl XmlCompletion {
     }
 
     pub fn attribute_eq(&self, attr: &str, val: &str) -> bool {
-        self.tag.as_ref().map_or(false, |t| {
-            t.attributes.get(attr).map_or(false, |v| v == val)
-        })
+        self.tag
+            .as_ref()
+            .is_some_and(|t| t.attributes.get(attr).is_some_and(|v| v == val))
+    }
+
+    pub fn attribute(&self, attr: &str) -> Option<&str> {
+        self.tag.as_ref()?.attributes.get(attr).map(String::as_str)
     }
 
     pub fn attribute_in(&self, attr: &str, vals: &[&str]) -> bool {
-        self.tag.as_ref().map_or(false, |t| {
+        self.tag.as_ref().is_some_and(|t| {
             t.attributes
                 .get(attr)
-                .map_or(false, |v| vals.contains(&v.as_ref()))
+                .is_some_and(|v| vals.contains(&v.as_ref()))
         })
     }
 }
@@ -67,21 +81,533 @@ impl XmlTag {
     }
 }
 
-pub fn get_current_position_path(content: &str, pos: Position) -> Option<XmlCompletion> {
-    let tree = tree_sitter_parsers::parse(content, "html");
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct XmlElement {
+    path: String,
+    tag: XmlTag,
+    range: Range,
+    attribute_ranges: HashMap<String, Range>,
+}
+
+const INDEXED_FILES: [&[&str]; 11] = [
+    &["etc", "cache.xml"],
+    &["etc", "events.xml"],
+    &["etc", "*", "events.xml"],
+    &["etc", "adminhtml", "system.xml"],
+    &["etc", "di.xml"],
+    &["etc", "frontend", "di.xml"],
+    &["etc", "adminhtml", "di.xml"],
+    &["view", "*", "layout", "*.xml"],
+    &["etc", "acl.xml"],
+    &["etc", "frontend", "routes.xml"],
+    &["etc", "adminhtml", "routes.xml"],
+];
+
+// module layouts overridden by theme, `<Vendor_Module>/layout/*.xml`
+const THEME_LAYOUT_FILES: &[&str] = &["*_*", "layout", "*.xml"];
+
+pub fn update_index(state: &ArcState, path: &PathBuf, progress: &Progress) {
+    let steps = INDEXED_FILES.len() + 1;
+    for (done, file) in INDEXED_FILES.iter().enumerate() {
+        // if current workspace is magento module
+        process_glob(state, &path.append(file));
+        // if current workspace is magento installation
+        process_glob(state, &path.append(&["vendor", "*", "*"]).append(file));
+        process_glob(state, &path.append(&["app", "code", "*", "*"]).append(file));
+        progress.report(done + 1, steps);
+    }
+    // if current workspace is magento theme
+    process_glob(state, &path.append(THEME_LAYOUT_FILES));
+    // if current workspace is magento installation
+    process_glob(
+        state,
+        &path
+            .append(&["vendor", "*", "*"])
+            .append(THEME_LAYOUT_FILES),
+    );
+    process_glob(
+        state,
+        &path
+            .append(&["app", "design", "*", "*", "*"])
+            .append(THEME_LAYOUT_FILES),
+    );
+    progress.report(steps, steps);
+}
+
+pub fn maybe_index_file(state: &mut State, content: &str, tree: &Tree, file_path: &Path) {
+    if file_path.ends_with("cache.xml") {
+        update_index_from_cache_config(state, content, tree, file_path);
+    } else if file_path.ends_with("events.xml") {
+        update_index_from_events_config(state, content, tree, file_path);
+    } else if file_path.ends_with("adminhtml/system.xml") {
+        update_index_from_system_config(state, content, tree, file_path);
+    } else if is_indexed_di_config(file_path) {
+        update_index_from_di_config(state, content, tree, file_path);
+    } else if file_path.ends_with("routes.xml") {
+        update_index_from_routes_config(state, content, tree, file_path);
+    } else if file_path.ends_with("etc/acl.xml") {
+        update_index_from_acl_config(state, content, tree, file_path);
+    } else if file_path.to_path_buf().has_components(&["layout"]) {
+        update_index_from_layout(state, content, tree, file_path);
+    }
+}
+
+// other areas (webapi_rest, crontab, etc.) are not supported yet
+fn is_indexed_di_config(file_path: &Path) -> bool {
+    file_path.ends_with("etc/di.xml")
+        || file_path.ends_with("etc/frontend/di.xml")
+        || file_path.ends_with("etc/adminhtml/di.xml")
+}
+
+fn process_glob(state: &ArcState, glob_path: &PathBuf) {
+    let files = glob(glob_path.to_path_str())
+        .expect("Failed to read glob pattern")
+        .filter_map(Result::ok);
+
+    for file_path in files {
+        if file_path.is_test() || state.lock().options().is_excluded(&file_path) {
+            continue;
+        }
+
+        let Some(content) = state::read_indexed_file(&file_path) else {
+            continue;
+        };
+
+        let tree = tree_sitter_parsers::parse(&content, "html");
+        maybe_index_file(&mut state.lock(), &content, &tree, &file_path);
+    }
+}
+
+fn update_index_from_cache_config(state: &mut State, content: &str, tree: &Tree, file_path: &Path) {
+    state.set_source_file(file_path);
+    for element in get_xml_elements(tree, content, state.position_encoding()) {
+        if element.path != "/config/type" {
+            continue;
+        }
+        if let Some(name) = element.tag.attributes.get("name").filter(|n| !n.is_empty()) {
+            let instance = element.tag.attributes.get("instance").cloned();
+            state.add_cache_type(name.clone(), instance.unwrap_or_default());
+        }
+    }
+}
+
+fn update_index_from_events_config(
+    state: &mut State,
+    content: &str,
+    tree: &Tree,
+    file_path: &Path,
+) {
+    state.set_source_file(file_path);
+    let Ok(uri) = Url::from_file_path(file_path) else {
+        return;
+    };
+    for element in get_xml_elements(tree, content, state.position_encoding()) {
+        if element.path == "/config/event/observer" {
+            add_class_references(state, &element, &uri, &["instance"]);
+        }
+        if element.path != "/config/event" {
+            continue;
+        }
+        if let Some(name) = element.tag.attributes.get("name").filter(|n| !n.is_empty()) {
+            state.add_event(name.clone());
+        }
+    }
+}
+
+fn update_index_from_layout(state: &mut State, content: &str, tree: &Tree, file_path: &Path) {
+    state.set_source_file(file_path);
+    let Ok(uri) = Url::from_file_path(file_path) else {
+        return;
+    };
+    // layout file is named after the handle it extends
+    if let Some(handle) = file_path.file_stem().and_then(|stem| stem.to_str()) {
+        let location = Location {
+            uri: uri.clone(),
+            range: Range::default(),
+        };
+        state.add_layout_handle(handle, location);
+    }
+    for element in get_xml_elements(tree, content, state.position_encoding()) {
+        if element.tag.name == "block" {
+            add_class_references(state, &element, &uri, &["class"]);
+        }
+        if element.tag.name != "block" && element.tag.name != "container" {
+            continue;
+        }
+        if let Some(name) = element.tag.attributes.get("name").filter(|n| !n.is_empty()) {
+            let location = Location {
+                uri: uri.clone(),
+                range: element.range,
+            };
+            state.add_layout_element(name.clone(), location);
+        }
+    }
+}
+
+fn update_index_from_routes_config(
+    state: &mut State,
+    content: &str,
+    tree: &Tree,
+    file_path: &Path,
+) {
+    state.set_source_file(file_path);
+    let area = file_path.to_path_buf().get_area();
+    let mut route_id = String::new();
+    for element in get_xml_elements(tree, content, state.position_encoding()) {
+        match element.path.as_str() {
+            "/config/router/route" => {
+                route_id = element
+                    .tag
+                    .attributes
+                    .get("id")
+                    .cloned()
+                    .unwrap_or_default();
+            }
+            "/config/router/route/module" if !route_id.is_empty() => {
+                if let Some(name) = element.tag.attributes.get("name").filter(|n| !n.is_empty()) {
+                    state.add_route(&area, name, &route_id);
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+fn update_index_from_acl_config(state: &mut State, content: &str, tree: &Tree, file_path: &Path) {
+    state.set_source_file(file_path);
+    let Ok(uri) = Url::from_file_path(file_path) else {
+        return;
+    };
+    for element in get_xml_elements(tree, content, state.position_encoding()) {
+        if element.tag.name != "resource" || !element.path.starts_with("/config/acl/resources") {
+            continue;
+        }
+        if let Some(id) = element.tag.attributes.get("id").filter(|id| !id.is_empty()) {
+            let location = Location {
+                uri: uri.clone(),
+                range: element.range,
+            };
+            state.add_acl_resource(id.clone(), location);
+        }
+    }
+}
+
+fn update_index_from_system_config(
+    state: &mut State,
+    content: &str,
+    tree: &Tree,
+    file_path: &Path,
+) {
+    state.set_source_file(file_path);
+    let Ok(uri) = Url::from_file_path(file_path) else {
+        return;
+    };
+    // groups can be nested, so keep ids of all currently open elements
+    let mut ids: Vec<(usize, String)> = vec![];
+    for element in get_xml_elements(tree, content, state.position_encoding()) {
+        if !element.path.starts_with("/config/system/section") {
+            continue;
+        }
+        let depth = element.path.matches('/').count();
+        ids.retain(|(d, _)| *d < depth);
+        let Some(id) = element.tag.attributes.get("id").filter(|id| !id.is_empty()) else {
+            continue;
+        };
+        match element.tag.name.as_str() {
+            "section" | "group" => ids.push((depth, id.clone())),
+            "field" => {
+                let mut parts: Vec<&str> = ids.iter().map(|(_, id)| id.as_str()).collect();
+                parts.push(id);
+                let location = Location {
+                    uri: uri.clone(),
+                    range: element.range,
+                };
+                state.add_config_path(parts.join("/"), location);
+            }
+            _ => (),
+        }
+    }
+}
+
+fn update_index_from_di_config(state: &mut State, content: &str, tree: &Tree, file_path: &Path) {
+    state.set_source_file(file_path);
+    let Ok(uri) = Url::from_file_path(file_path) else {
+        return;
+    };
+    let area = file_path.to_path_buf().get_area();
+    let mut type_name = String::new();
+    for element in get_xml_elements(tree, content, state.position_encoding()) {
+        let attributes: &[&str] = match element.path.as_str() {
+            "/config/preference" => &["for", "type"],
+            "/config/type" => &["name"],
+            "/config/virtualType" | "/config/type/plugin" => &["type"],
+            _ => &[],
+        };
+        add_class_references(state, &element, &uri, attributes);
+
+        match element.path.as_str() {
+            "/config/type" => {
+                type_name = element
+                    .tag
+                    .attributes
+                    .get("name")
+                    .cloned()
+                    .unwrap_or_default();
+                if !type_name.is_empty() {
+                    state.add_di_type(type_name.trim_matches('\\'), "");
+                }
+            }
+            "/config/virtualType" => {
+                if let Some(name) = element.tag.attributes.get("name").filter(|n| !n.is_empty()) {
+                    let parent_type = element.tag.attributes.get("type").cloned();
+                    state.add_di_type(name.clone(), parent_type.unwrap_or_default());
+                }
+            }
+            "/config/type/plugin" if !type_name.is_empty() => {
+                let Some(name) = element.tag.attributes.get("name").filter(|n| !n.is_empty())
+                else {
+                    continue;
+                };
+                let plugin = DiPlugin {
+                    class: element.tag.attributes.get("type").cloned(),
+                    disabled: element
+                        .tag
+                        .attributes
+                        .get("disabled")
+                        .map(|disabled| disabled == "true"),
+                };
+                state.add_plugin(&area, &type_name, name, plugin);
+            }
+            _ => (),
+        }
+    }
+}
+
+// classes referenced by the element attributes and its `xsi:type="object"` text
+fn add_class_references(state: &mut State, element: &XmlElement, uri: &Url, attributes: &[&str]) {
+    let mut classes: Vec<&str> = attributes
+        .iter()
+        .filter_map(|attr| element.tag.attributes.get(*attr))
+        .map(String::as_str)
+        .collect();
+    if element
+        .tag
+        .attributes
+        .get("xsi:type")
+        .is_some_and(|t| t == "object")
+    {
+        let text = element.tag.text.trim();
+        classes.push(text.strip_suffix("\\Proxy").unwrap_or(text));
+    }
+    for class in classes {
+        let location = Location {
+            uri: uri.clone(),
+            range: element.range,
+        };
+        state.add_class_reference(class, location);
+    }
+}
+
+/// Outline of `di.xml`, plugins nested in types they are declared for
+pub fn get_di_symbols(
+    tree: &Tree,
+    content: &str,
+    encoding: &PositionEncodingKind,
+) -> Vec<DocumentSymbol> {
+    let mut result: Vec<DocumentSymbol> = vec![];
+    for element in get_xml_elements(tree, content, encoding) {
+        let (kind, name_attr, detail_attr) = match element.path.as_str() {
+            "/config/type" => (SymbolKind::CLASS, "name", None),
+            "/config/virtualType" => (SymbolKind::OBJECT, "name", Some("type")),
+            "/config/preference" => (SymbolKind::INTERFACE, "for", Some("type")),
+            "/config/type/plugin" => (SymbolKind::FUNCTION, "name", Some("type")),
+            _ => continue,
+        };
+        let Some(name) = element
+            .tag
+            .attributes
+            .get(name_attr)
+            .filter(|n| !n.is_empty())
+        else {
+            continue;
+        };
+        #[allow(deprecated)]
+        let symbol = DocumentSymbol {
+            name: name.clone(),
+            detail: detail_attr.and_then(|attr| element.tag.attributes.get(attr).cloned()),
+            kind,
+            tags: None,
+            deprecated: None,
+            range: element.range,
+            selection_range: element
+                .attribute_ranges
+                .get(name_attr)
+                .copied()
+                .unwrap_or(element.range),
+            children: None,
+        };
+        if kind == SymbolKind::FUNCTION {
+            if let Some(parent) = result.last_mut().filter(|s| s.kind == SymbolKind::CLASS) {
+                parent.children.get_or_insert_with(Vec::new).push(symbol);
+            }
+        } else {
+            result.push(symbol);
+        }
+    }
+    result
+}
+
+pub fn get_parent_type_name(
+    tree: &Tree,
+    content: &str,
+    pos: Position,
+    encoding: &PositionEncodingKind,
+) -> Option<String> {
+    get_xml_elements_at_pos(tree, content, pos, encoding)
+        .into_iter()
+        .find(|element| element.path == "/config/type")
+        .and_then(|element| element.tag.attributes.get("name").cloned())
+}
+
+// string items of `deps` or `components` arrays are js component names
+fn is_in_components_array(
+    tree: &Tree,
+    content: &str,
+    pos: Position,
+    encoding: &PositionEncodingKind,
+) -> bool {
+    let elements = get_xml_elements_at_pos(tree, content, pos, encoding);
+    elements.len() > 1
+        && elements.get(elements.len() - 2).is_some_and(|parent| {
+            parent
+                .tag
+                .attributes
+                .get("xsi:type")
+                .is_some_and(|t| t == "array")
+                && parent
+                    .tag
+                    .attributes
+                    .get("name")
+                    .is_some_and(|name| name == "deps" || name == "components")
+        })
+}
+
+pub fn get_parent_argument_name(
+    tree: &Tree,
+    content: &str,
+    pos: Position,
+    encoding: &PositionEncodingKind,
+) -> Option<String> {
+    get_xml_elements_at_pos(tree, content, pos, encoding)
+        .into_iter()
+        .find(|element| element.path == "/config/type/arguments/argument")
+        .and_then(|element| element.tag.attributes.get("name").cloned())
+}
+
+// elements containing position, from outermost to innermost
+fn get_xml_elements_at_pos(
+    tree: &Tree,
+    content: &str,
+    pos: Position,
+    encoding: &PositionEncodingKind,
+) -> Vec<XmlElement> {
+    get_xml_elements(tree, content, encoding)
+        .into_iter()
+        .filter(|element| element.range.start <= pos && pos <= element.range.end)
+        .collect()
+}
+
+fn get_xml_elements(
+    tree: &Tree,
+    content: &str,
+    encoding: &PositionEncodingKind,
+) -> Vec<XmlElement> {
+    let mut result = vec![];
+    collect_xml_elements(tree.root_node(), content, "", encoding, &mut result);
+    result
+}
+
+fn collect_xml_elements(
+    node: Node,
+    content: &str,
+    parent_path: &str,
+    encoding: &PositionEncodingKind,
+    result: &mut Vec<XmlElement>,
+) {
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        if child.kind() != "element" {
+            continue;
+        }
+        let Some(start_tag) = child.child(0) else {
+            continue;
+        };
+        if start_tag.kind() != "start_tag" && start_tag.kind() != "self_closing_tag" {
+            continue;
+        }
+
+        let mut tag = XmlTag::new();
+        let mut attribute_ranges = HashMap::new();
+        let mut tag_cursor = start_tag.walk();
+        for part in start_tag.children(&mut tag_cursor) {
+            match part.kind() {
+                "tag_name" => tag.name = get_node_str(part, content).into(),
+                "attribute" => {
+                    let name = part.child(0).map_or("", |n| get_node_str(n, content));
+                    let value_node = part
+                        .child(2)
+                        .and_then(|n| n.child(1))
+                        .filter(|n| n.kind() == "attribute_value");
+                    let value = value_node.map_or("", |n| get_node_str(n, content));
+                    if let Some(node) = value_node.or_else(|| part.child(2)) {
+                        attribute_ranges.insert(
+                            name.to_string(),
+                            get_range_from_node(node, content, encoding),
+                        );
+                    }
+                    tag.attributes.insert(name.into(), value.into());
+                }
+                _ => (),
+            }
+        }
+        let mut text_cursor = child.walk();
+        if let Some(text) = child
+            .children(&mut text_cursor)
+            .find(|n| n.kind() == "text")
+        {
+            tag.text = get_node_str(text, content).into();
+        }
+
+        let path = format!("{}/{}", parent_path, tag.name);
+        result.push(XmlElement {
+            path: path.clone(),
+            tag,
+            range: get_range_from_node(child, content, encoding),
+            attribute_ranges,
+        });
+        collect_xml_elements(child, content, &path, encoding, result);
+    }
+}
+
+pub fn get_current_position_path(
+    tree: &Tree,
+    content: &str,
+    pos: Position,
+    encoding: &PositionEncodingKind,
+) -> Option<XmlCompletion> {
     let query = queries::xml_current_position_path();
     let mut cursor = QueryCursor::new();
     let captures = cursor.captures(query, tree.root_node(), content.as_bytes());
     for (m, i) in captures {
         let node = m.captures[i].node;
-        if node_at_position(node, pos) {
-            let mut text = get_node_text_before_pos(node, content, pos);
+        if node_at_position(node, content, pos, encoding) {
+            let mut text = get_node_text_before_pos(node, content, pos, encoding);
             if node.kind() == ">" && text.is_empty() {
                 // this is end of tag node but if text is empty
                 // the tag is not really closed yet, just should be
                 continue;
             }
-            let mut start_col = node.start_position().column as u32;
+            let mut start_col = get_range_from_node(node, content, encoding).start.character;
             if node.kind() == "quoted_attribute_value" {
                 if text == "\"" {
                     start_col += 1;
@@ -95,7 +621,7 @@ pub fn get_current_position_path(content: &str, pos: Position) -> Option<XmlComp
                 text = String::new();
             }
             let path = node_to_path(node, content)?;
-            let tag = node_to_tag(node, content);
+            let tag = node_to_tag(node, content, encoding);
             let range = Range {
                 start: Position {
                     line: node.start_position().row as u32,
@@ -135,7 +661,7 @@ fn node_walk_back(node: Node) -> Option<Node> {
     node.prev_sibling().map_or_else(|| node.parent(), Some)
 }
 
-fn node_to_tag(node: Node, content: &str) -> Option<XmlTag> {
+fn node_to_tag(node: Node, content: &str, encoding: &PositionEncodingKind) -> Option<XmlTag> {
     let mut current_node = node;
     while let Some(node) = node_walk_back(current_node) {
         current_node = node;
@@ -144,12 +670,15 @@ fn node_to_tag(node: Node, content: &str) -> Option<XmlTag> {
             if text.chars().last()? != '>' {
                 return None;
             }
+            let tag_tree = tree_sitter_parsers::parse(text, "html");
             return get_xml_tag_at_pos(
+                &tag_tree,
                 text,
                 Position {
                     line: 0,
                     character: 0,
                 },
+                encoding,
             );
         }
     }
@@ -226,44 +755,91 @@ fn node_to_path(node: Node, content: &str) -> Option<String> {
     Some(result)
 }
 
-pub fn get_item_from_position(state: &State, path: &PathBuf, pos: Position) -> Option<M2Item> {
-    let content = state.get_file(path)?;
-    get_item_from_pos(state, content, path, pos)
+pub fn get_item_from_position(
+    state: &State,
+    path: &PathBuf,
+    pos: Position,
+    trace: &mut Trace,
+) -> Option<M2Item> {
+    let content = state.get_file_or_read(path)?;
+    let tree = state.get_tree(path, &content)?;
+    get_item_from_pos(state, &content, &tree, path, pos, trace)
 }
 
 fn get_item_from_pos(
     state: &State,
     content: &str,
+    tree: &Tree,
     path: &PathBuf,
     pos: Position,
+    trace: &mut Trace,
 ) -> Option<M2Item> {
-    let tag = get_xml_tag_at_pos(content, pos)?;
+    let tag = get_xml_tag_at_pos(tree, content, pos, state.position_encoding())?;
+    trace.step(|| format!("xml tag: {}", tag.name));
+    trace.step(|| format!("hover on: {:?}", tag.hover_on));
 
     match tag.hover_on {
         XmlPart::Attribute(ref attr_name) => match attr_name.as_str() {
-            "method" | "instance" | "class" => try_method_item_from_tag(&tag).or_else(|| {
+            // route method is http verb, only service one points to php method
+            "method" if path.ends_with("webapi.xml") => {
+                try_method_item_from_tag(&tag).filter(|_| tag.name == "service")
+            }
+            "method" => try_method_item_from_tag(&tag).or_else(|| {
+                m2::try_any_item_from_str(tag.attributes.get(attr_name)?, &path.get_area())
+            }),
+            "instance" | "class" => try_observer_item_from_tag(&tag, path).or_else(|| {
                 m2::try_any_item_from_str(tag.attributes.get(attr_name)?, &path.get_area())
             }),
             "template" => {
                 m2::try_phtml_item_from_str(tag.attributes.get(attr_name)?, &path.get_area())
             }
+            "ifconfig"
+                if path.has_components(&["layout"]) || path.has_components(&["ui_component"]) =>
+            {
+                Some(M2Item::ConfigPath(tag.attributes.get(attr_name)?.into()))
+            }
+            "src" if ["css", "link", "script"].contains(&tag.name.as_str()) => {
+                php::text_to_view_file(state, tag.attributes.get(attr_name)?)
+            }
+            "ref" if tag.name == "resource" => {
+                Some(M2Item::AclResource(tag.attributes.get(attr_name)?.into()))
+            }
+            "element" | "destination" if tag.name == "move" => {
+                Some(M2Item::LayoutElement(tag.attributes.get(attr_name)?.into()))
+            }
+            "name" if tag.name == "referenceBlock" || tag.name == "referenceContainer" => {
+                Some(M2Item::LayoutElement(tag.attributes.get(attr_name)?.into()))
+            }
+            "name" if tag.name == "plugin" && path.ends_with("di.xml") => {
+                try_plugin_item_from_tag(state, content, tree, &tag, path, pos)
+            }
             _ => m2::try_any_item_from_str(tag.attributes.get(attr_name)?, &path.get_area()),
         },
+        XmlPart::Text if tag.name == "parent" && path.ends_with("theme.xml") => {
+            Some(M2Item::Theme(path.get_area(), tag.text.trim().into()))
+        }
         XmlPart::Text => {
             let text = tag.text.trim_matches('\\');
             let empty = String::new();
             let xsi_type = tag.attributes.get("xsi:type").unwrap_or(&empty);
+            trace.step(|| format!("xsi:type: {}", xsi_type));
 
             match xsi_type.as_str() {
                 "object" => Some(m2::get_class_item_from_str(text)),
-                "init_parameter" => m2::try_const_item_from_str(text),
-                "string" => {
-                    if tag.attributes.get("name").is_some_and(|s| s == "component") {
+                "init_parameter" | "const" => m2::try_const_item_from_str(text),
+                "string" => match tag.attributes.get("name").map(String::as_str) {
+                    Some("component") => js::text_to_component(state, text, path),
+                    Some("template" | "templateFile" | "defaultTemplate")
+                        if !text.contains("::") =>
+                    {
+                        js::text_to_template(state, text, path)
+                    }
+                    Some("elementTmpl") => js::text_to_template(state, text, path),
+                    _ if is_in_components_array(tree, content, pos, state.position_encoding()) => {
                         js::text_to_component(state, text, path)
-                    } else {
-                        m2::try_any_item_from_str(text, &path.get_area())
                     }
-                }
+                    _ => m2::try_any_item_from_str(text, &path.get_area()),
+                },
                 _ => m2::try_any_item_from_str(text, &path.get_area()),
             }
         }
@@ -271,8 +847,12 @@ fn get_item_from_pos(
     }
 }
 
-fn get_xml_tag_at_pos(content: &str, pos: Position) -> Option<XmlTag> {
-    let tree = tree_sitter_parsers::parse(content, "html");
+fn get_xml_tag_at_pos(
+    tree: &Tree,
+    content: &str,
+    pos: Position,
+    encoding: &PositionEncodingKind,
+) -> Option<XmlTag> {
     let query = queries::xml_tag_at_pos();
 
     let mut cursor = QueryCursor::new();
@@ -285,7 +865,9 @@ fn get_xml_tag_at_pos(content: &str, pos: Position) -> Option<XmlTag> {
     for (m, i) in captures {
         let first = m.captures[0].node; // always (self)opening tag
         let last = m.captures[m.captures.len() - 1].node;
-        if !node_at_position(first, pos) && !node_at_position(last, pos) {
+        if !node_at_position(first, content, pos, encoding)
+            && !node_at_position(last, content, pos, encoding)
+        {
             continue;
         }
         let id = m.captures[0].node.id(); // id of tag name
@@ -294,7 +876,7 @@ fn get_xml_tag_at_pos(content: &str, pos: Position) -> Option<XmlTag> {
             tag = XmlTag::new();
         }
         let node = m.captures[i].node;
-        let hovered = node_at_position(node, pos);
+        let hovered = node_at_position(node, content, pos, encoding);
         match node.kind() {
             "tag_name" => {
                 tag.name = get_node_str(node, content).into();
@@ -330,13 +912,29 @@ fn get_xml_tag_at_pos(content: &str, pos: Position) -> Option<XmlTag> {
     Some(tag)
 }
 
+fn try_plugin_item_from_tag(
+    state: &State,
+    content: &str,
+    tree: &Tree,
+    tag: &XmlTag,
+    path: &PathBuf,
+    pos: Position,
+) -> Option<M2Item> {
+    if let Some(class) = tag.attributes.get("type") {
+        return Some(m2::get_class_item_from_str(class));
+    }
+    let type_name = get_parent_type_name(tree, content, pos, state.position_encoding())?;
+    let plugin = state.get_plugin(&path.get_area(), &type_name, tag.attributes.get("name")?)?;
+    Some(m2::get_class_item_from_str(&plugin.class?))
+}
+
 fn try_method_item_from_tag(tag: &XmlTag) -> Option<M2Item> {
-    if tag.attributes.get("instance").is_some() && tag.attributes.get("method").is_some() {
+    if tag.attributes.contains_key("instance") && tag.attributes.contains_key("method") {
         Some(M2Item::Method(
             tag.attributes.get("instance")?.into(),
             tag.attributes.get("method")?.into(),
         ))
-    } else if tag.attributes.get("class").is_some() && tag.attributes.get("method").is_some() {
+    } else if tag.attributes.contains_key("class") && tag.attributes.contains_key("method") {
         Some(M2Item::Method(
             tag.attributes.get("class")?.into(),
             tag.attributes.get("method")?.into(),
@@ -346,40 +944,47 @@ fn try_method_item_from_tag(tag: &XmlTag) -> Option<M2Item> {
     }
 }
 
+fn try_observer_item_from_tag(tag: &XmlTag, path: &Path) -> Option<M2Item> {
+    if tag.name == "observer" && path.ends_with("events.xml") {
+        Some(M2Item::Method(
+            tag.attributes.get("instance")?.into(),
+            "execute".into(),
+        ))
+    } else {
+        None
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::lsp::definition::resolve_item;
+    use crate::m2::M2Area;
+    use crate::test_utils::{module_path, split_cursor, TempDir};
     use std::path::PathBuf;
 
-    fn get_position_from_test_xml(xml: &str) -> Position {
-        let mut character = 0;
-        let mut line = 0;
-        for l in xml.lines() {
-            if l.contains('|') {
-                character = l.find('|').expect("Test has to have a | character") as u32;
-                break;
-            }
-            line += 1;
-        }
-        Position { line, character }
-    }
-
     fn get_test_position_path(xml: &str) -> Option<XmlCompletion> {
-        let pos = get_position_from_test_xml(xml);
-        get_current_position_path(&xml.replace('|', ""), pos)
+        let (content, pos) = split_cursor(xml);
+        let tree = tree_sitter_parsers::parse(&content, "html");
+        get_current_position_path(&tree, &content, pos, &PositionEncodingKind::UTF16)
     }
 
     fn get_test_item_from_pos(xml: &str, path: &str) -> Option<M2Item> {
+        get_test_item_from_pos_with_state(&State::new(), xml, path)
+    }
+
+    fn get_test_item_from_pos_with_state(state: &State, xml: &str, path: &str) -> Option<M2Item> {
         let win_path = format!("c:{}", path.replace('/', "\\"));
-        let pos = get_position_from_test_xml(xml);
+        let (content, pos) = split_cursor(xml);
         let uri = PathBuf::from(if cfg!(windows) { &win_path } else { path });
-        let state = State::new();
-        get_item_from_pos(&state, &xml.replace('|', ""), &uri, pos)
+        let tree = tree_sitter_parsers::parse(&content, "html");
+        get_item_from_pos(state, &content, &tree, &uri, pos, &mut Trace::default())
     }
 
     fn get_test_xml_tag_at_pos(xml: &str) -> Option<XmlTag> {
-        let pos = get_position_from_test_xml(xml);
-        get_xml_tag_at_pos(&xml.replace('|', ""), pos)
+        let (content, pos) = split_cursor(xml);
+        let tree = tree_sitter_parsers::parse(&content, "html");
+        get_xml_tag_at_pos(&tree, &content, pos, &PositionEncodingKind::UTF16)
     }
 
     #[test]
@@ -443,16 +1048,46 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_get_item_from_pos_service_method_in_webapi() {
+        let xml = r#"<?xml version="1.0"?>
+            <routes>
+                <route url="/V1/some" method="G|ET">
+                    <service class="Some\Module\Api\SomeInterface" method="getL|ist"/>
+                </route>
+            </routes>"#;
+        let service = xml.replacen('|', "", 1);
+        let route = xml.replacen("getL|ist", "getList", 1);
+
+        assert_eq!(
+            get_test_item_from_pos(&service, "/a/b/c/etc/webapi.xml"),
+            Some(M2Item::Method(
+                "Some\\Module\\Api\\SomeInterface".into(),
+                "getList".into()
+            ))
+        );
+        assert_eq!(
+            get_test_item_from_pos(&route, "/a/b/c/etc/webapi.xml"),
+            None
+        );
+    }
+
     #[test]
     fn test_get_item_from_pos_class_in_service_tag_attribute() {
         let item = get_test_item_from_pos(
             r#"<?xml version="1.0"?><service class="\|A\B\C" method="metHod">xx</service>"#,
             "/a/a/c",
         );
-        assert_eq!(
-            item,
-            Some(M2Item::Method("A\\B\\C".into(), "metHod".into()))
+        assert_eq!(item, Some(M2Item::Class("A\\B\\C".into())));
+    }
+
+    #[test]
+    fn test_get_item_from_pos_instance_in_job_tag_attribute() {
+        let item = get_test_item_from_pos(
+            r#"<?xml version="1.0"?><job instance="A\B|\C" method="metHod"></job>"#,
+            "/a/a/c",
         );
+        assert_eq!(item, Some(M2Item::Class("A\\B\\C".into())));
     }
 
     #[test]
@@ -684,6 +1319,23 @@ mod test {
         assert!(item.tag.is_none());
     }
 
+    #[test]
+    fn test_get_current_position_path_after_multibyte_char() {
+        let item = get_test_position_path(
+            r#"<?xml version=\"1.0\"?>
+            <config>
+                <!-- Żółw 🐢 --><block template="Some_|"/>
+            </config>
+            "#,
+        );
+
+        let item = item.unwrap();
+        assert_eq!(item.path, "/config/block[@template]");
+        assert_eq!(item.text, "Some_");
+        assert_eq!(item.range.start, Position::new(2, 49));
+        assert_eq!(item.range.end, Position::new(2, 54));
+    }
+
     #[test]
     fn test_get_current_position_path_between_start_and_end_tag() {
         let item = get_test_position_path(
@@ -713,8 +1365,8 @@ mod test {
 
         let item = item.unwrap();
         assert_eq!(item.name, "item");
-        assert!(item.attributes.get("name").is_some());
-        assert!(item.attributes.get("attribute").is_some());
+        assert!(item.attributes.contains_key("name"));
+        assert!(item.attributes.contains_key("attribute"));
     }
 
     #[test]
@@ -900,4 +1552,872 @@ mod test {
         assert!(item.match_path("/source[$text]"));
         assert!(item.attribute_eq("_model", ""));
     }
+
+    #[test]
+    fn test_update_index_from_theme_layout() {
+        let workspace = TempDir::new("theme-layout");
+        workspace.write(
+            &[
+                "app",
+                "design",
+                "frontend",
+                "Some",
+                "theme",
+                "Magento_Theme",
+                "layout",
+                "default.xml",
+            ],
+            r#"<page><body><block name="theme.block"/></body></page>"#,
+        );
+        let state = State::new().into_arc();
+
+        update_index(
+            &state,
+            &workspace.path(&[]),
+            &Progress::begin(&state, "XML Indexing"),
+        );
+
+        let state = state.lock();
+        assert_eq!(state.get_layout_handle_locations("default").len(), 1);
+        assert_eq!(state.get_layout_element_locations("theme.block").len(), 1);
+    }
+
+    #[test]
+    fn test_update_index_from_cache_config() {
+        let mut state = State::new();
+        let content = r#"<?xml version="1.0"?>
+            <config xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" xsi:noNamespaceSchemaLocation="urn:magento:framework:Cache/etc/cache.xsd">
+                <type name="full_page" translate="label,description" instance="Magento\PageCache\Model\Cache\Type">
+                    <label>Page Cache</label>
+                    <description>Full page caching</description>
+                </type>
+                <type name="some_cache" instance="Some\Module\Model\Cache\Type"/>
+            </config>
+            "#;
+        let tree = tree_sitter_parsers::parse(content, "html");
+        update_index_from_cache_config(
+            &mut state,
+            content,
+            &tree,
+            &PathBuf::from("/a/b/c/etc/cache.xml"),
+        );
+
+        let mut cache_types = state.get_cache_types();
+        cache_types.sort_unstable();
+        assert_eq!(cache_types, vec!["full_page", "some_cache"]);
+    }
+
+    #[test]
+    fn test_get_item_from_pos_cache_type_instance() {
+        let item = get_test_item_from_pos(
+            r#"<?xml version="1.0"?>
+            <config>
+                <type name="full_page" instance="Magento\PageCache\Model\Cache\Ty|pe"/>
+            </config>
+            "#,
+            "/a/b/c/etc/cache.xml",
+        );
+        assert_eq!(
+            item,
+            Some(M2Item::Class(
+                "Magento\\PageCache\\Model\\Cache\\Type".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_get_item_from_pos_observer_instance_in_events_xml() {
+        let item = get_test_item_from_pos(
+            r#"<?xml version="1.0"?>
+            <config>
+                <event name="some_event">
+                    <observer name="some_observer" instance="\Some\Module\Observer\Some|Observer"/>
+                </event>
+            </config>
+            "#,
+            "/a/b/c/etc/events.xml",
+        );
+        assert_eq!(
+            item,
+            Some(M2Item::Method(
+                "Some\\Module\\Observer\\SomeObserver".into(),
+                "execute".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_get_item_from_pos_observer_instance_outside_events_xml() {
+        let item = get_test_item_from_pos(
+            r#"<?xml version="1.0"?><observer name="some" instance="Some\Module\Some|Observer"/>"#,
+            "/a/b/c/etc/di.xml",
+        );
+        assert_eq!(
+            item,
+            Some(M2Item::Class("Some\\Module\\SomeObserver".into()))
+        );
+    }
+
+    #[test]
+    fn test_get_item_from_pos_template_with_placeholder() {
+        let item = get_test_item_from_pos(
+            r#"<?xml version="1.0"?><block template="Some_Module::{{te|mplate}}.phtml"></block>"#,
+            "/a/view/frontend/c",
+        );
+        assert_eq!(item, None);
+    }
+
+    #[test]
+    fn test_get_item_from_pos_const_on_interface_in_const_argument() {
+        let item = get_test_item_from_pos(
+            r#"<?xml version="1.0"?>
+            <type name="Some\Module\Model\Foo">
+                <arguments>
+                    <argument name="bar" xsi:type="const">Vendor\Module\Api\FooInterface::B|AR</argument>
+                </arguments>
+            </type>
+            "#,
+            "/a/b/c/etc/di.xml",
+        );
+        assert_eq!(
+            item,
+            Some(M2Item::Const(
+                "Vendor\\Module\\Api\\FooInterface".into(),
+                "BAR".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_get_item_from_pos_trace() {
+        let xml = r#"<?xml version="1.0"?><item xsi:type="object">|A\B\C</item>"#;
+        let (content, pos) = split_cursor(xml);
+        let path = PathBuf::from("/a/b/c/etc/di.xml");
+        let mut state = State::new();
+        state.set_file(&path, content);
+        let mut trace = Trace::new(true);
+
+        resolve_item(&state, &path, pos, &mut trace);
+
+        assert_eq!(
+            trace.steps(),
+            &[
+                "resolving \"/a/b/c/etc/di.xml\" at 0:45",
+                "xml tag: item",
+                "hover on: Text",
+                "xsi:type: object",
+                "resolved item: Some(Class(\"A\\\\B\\\\C\"))",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_get_item_from_pos_ui_template_through_indexed_requirejs_config() {
+        let mut state = State::new();
+        state.add_module_path("Magento_Ui", PathBuf::from("/a/b/Magento/Ui"));
+        let config_path = PathBuf::from("/a/b/Magento/Ui/view/base/requirejs-config.js");
+        let config = "var config = { paths: { 'ui/template': 'Magento_Ui/templates' } };";
+        let tree = tree_sitter_parsers::parse(config, "javascript");
+        js::maybe_index_file(&mut state, config, &tree, &config_path);
+        let item = get_test_item_from_pos_with_state(
+            &state,
+            r#"<?xml version="1.0"?>
+            <listing>
+                <item name="template" xsi:type="string">ui/grid/lis|ting</item>
+            </listing>
+            "#,
+            "/a/b/c/view/adminhtml/ui_component/some_listing.xml",
+        );
+        assert_eq!(
+            item,
+            Some(M2Item::ModHtml(
+                "Magento_Ui".into(),
+                "templates/grid/listing.html".into(),
+                PathBuf::from("/a/b/Magento/Ui")
+            ))
+        );
+    }
+
+    #[test]
+    fn test_get_item_from_pos_ui_template_through_requirejs_paths() {
+        let mut state = State::new();
+        state.add_module_path("Magento_Ui", PathBuf::from("/a/b/Magento/Ui"));
+        state.add_component_path("ui/template", "Magento_Ui/templates", &M2Area::Base);
+        let item = get_test_item_from_pos_with_state(
+            &state,
+            r#"<?xml version="1.0"?>
+            <listing>
+                <item name="template" xsi:type="string">ui/grid/lis|ting</item>
+            </listing>
+            "#,
+            "/a/b/c/view/base/ui_component/some_listing.xml",
+        );
+        assert_eq!(
+            item,
+            Some(M2Item::ModHtml(
+                "Magento_Ui".into(),
+                "templates/grid/listing.html".into(),
+                PathBuf::from("/a/b/Magento/Ui")
+            ))
+        );
+    }
+
+    #[test]
+    fn test_get_item_from_pos_ui_template_with_module_prefix() {
+        let mut state = State::new();
+        state.add_module_path("Some_Module", PathBuf::from("/a/b/Some/Module"));
+        let item = get_test_item_from_pos_with_state(
+            &state,
+            r#"<?xml version="1.0"?>
+            <listing>
+                <item name="template" xsi:type="string">Some_Module/grid/lis|ting</item>
+            </listing>
+            "#,
+            "/a/b/c/view/adminhtml/ui_component/some_listing.xml",
+        );
+        assert_eq!(
+            item,
+            Some(M2Item::ModHtml(
+                "Some_Module".into(),
+                "template/grid/listing.html".into(),
+                PathBuf::from("/a/b/Some/Module")
+            ))
+        );
+    }
+
+    #[test]
+    fn test_get_item_from_pos_template_file_argument() {
+        let item = get_test_item_from_pos(
+            r#"<?xml version="1.0"?>
+            <config>
+                <type name="Some\Module\Block\Widget">
+                    <arguments>
+                        <argument name="templateFile" xsi:type="string">Some_Module::wid|get.phtml</argument>
+                    </arguments>
+                </type>
+            </config>
+            "#,
+            "/a/b/c/etc/adminhtml/di.xml",
+        );
+        assert_eq!(
+            item,
+            Some(M2Item::AdminPhtml(
+                "Some_Module".into(),
+                "widget.phtml".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_get_item_from_pos_default_template_argument() {
+        let mut state = State::new();
+        state.add_module_path("Some_Module", PathBuf::from("/a/b/Some/Module"));
+        let item = get_test_item_from_pos_with_state(
+            &state,
+            r#"<?xml version="1.0"?>
+            <listing>
+                <item name="defaultTemplate" xsi:type="string">Some_Module/grid/lis|ting</item>
+            </listing>
+            "#,
+            "/a/b/c/view/adminhtml/ui_component/some_listing.xml",
+        );
+        assert_eq!(
+            item,
+            Some(M2Item::ModHtml(
+                "Some_Module".into(),
+                "template/grid/listing.html".into(),
+                PathBuf::from("/a/b/Some/Module")
+            ))
+        );
+    }
+
+    #[test]
+    fn test_get_item_from_pos_class_in_view_xml_var() {
+        let item = get_test_item_from_pos(
+            r#"<?xml version="1.0"?>
+            <view xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
+                <vars module="Some_Module">
+                    <var name="renderer">Some\Module\Block\Ren|derer</var>
+                </vars>
+            </view>
+            "#,
+            "/a/b/c/design/frontend/Some/theme/etc/view.xml",
+        );
+        assert_eq!(
+            item,
+            Some(M2Item::Class("Some\\Module\\Block\\Renderer".into()))
+        );
+    }
+
+    #[test]
+    fn test_get_item_from_pos_image_in_view_xml() {
+        let item = get_test_item_from_pos(
+            r#"<?xml version="1.0"?>
+            <view xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
+                <media>
+                    <images module="Magento_Catalog">
+                        <image id="category_page_|grid" type="small_image">
+                            <width>240</width>
+                        </image>
+                    </images>
+                </media>
+            </view>
+            "#,
+            "/a/b/c/design/frontend/Some/theme/etc/view.xml",
+        );
+        assert_eq!(item, None);
+    }
+
+    #[test]
+    fn test_get_item_from_pos_parent_in_theme_xml() {
+        let item = get_test_item_from_pos(
+            r#"<?xml version="1.0"?>
+            <theme xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
+                <parent>Some/par|ent</parent>
+            </theme>
+            "#,
+            "/a/b/app/design/frontend/Some/child/theme.xml",
+        );
+        assert_eq!(
+            item,
+            Some(M2Item::Theme(M2Area::Frontend, "Some/parent".into()))
+        );
+    }
+
+    #[test]
+    fn test_update_index_from_system_config() {
+        let file_path = module_path(&["etc", "adminhtml", "system.xml"]);
+        let content = std::fs::read_to_string(&file_path).unwrap();
+        let mut state = State::new();
+
+        let tree = tree_sitter_parsers::parse(&content, "html");
+        update_index_from_system_config(&mut state, &content, &tree, &file_path);
+
+        let mut config_paths = state.get_config_paths();
+        config_paths.sort_unstable();
+        assert_eq!(
+            config_paths,
+            vec![
+                "some_section/general/advanced/debug",
+                "some_section/general/enabled"
+            ]
+        );
+        assert_eq!(
+            state.get_config_path_locations("some_section/general/enabled"),
+            vec![Location {
+                uri: Url::from_file_path(&file_path).unwrap(),
+                range: Range {
+                    start: Position {
+                        line: 5,
+                        character: 16
+                    },
+                    end: Position {
+                        line: 7,
+                        character: 24
+                    },
+                },
+            }]
+        );
+    }
+
+    #[test]
+    fn test_get_item_from_pos_ifconfig_in_layout() {
+        let item = get_test_item_from_pos(
+            r#"<?xml version="1.0"?>
+            <page>
+                <update handle="some_handle" ifconfig="some_section/gen|eral/enabled"/>
+            </page>
+            "#,
+            "/a/b/c/view/frontend/layout/default.xml",
+        );
+        assert_eq!(
+            item,
+            Some(M2Item::ConfigPath("some_section/general/enabled".into()))
+        );
+    }
+
+    #[test]
+    fn test_get_item_from_pos_ifconfig_outside_layout() {
+        let item = get_test_item_from_pos(
+            r#"<?xml version="1.0"?>
+            <config>
+                <item ifconfig="some_section/gen|eral/enabled"/>
+            </config>
+            "#,
+            "/a/b/c/etc/some.xml",
+        );
+        assert_eq!(item, None);
+    }
+
+    #[test]
+    fn test_get_item_from_pos_init_parameter_argument() {
+        let item = get_test_item_from_pos(
+            r#"<?xml version="1.0"?>
+            <config>
+                <type name="Some\Module\Model\Service">
+                    <arguments>
+                        <argument name="param" xsi:type="init_parameter">Some\Module\Model\Config::PA|RAM</argument>
+                    </arguments>
+                </type>
+            </config>
+            "#,
+            "/a/b/c/etc/di.xml",
+        );
+        assert_eq!(
+            item,
+            Some(M2Item::Const(
+                "Some\\Module\\Model\\Config".into(),
+                "PARAM".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_update_index_from_di_config_per_area() {
+        let mut state = State::new();
+        let content = r#"<?xml version="1.0"?>
+            <config>
+                <type name="Some\Module\Model\Service">
+                    <plugin name="some_plugin" type="Some\Module\Plugin\ServicePlugin" disabled="true"/>
+                </type>
+            </config>
+            "#;
+        let tree = tree_sitter_parsers::parse(content, "html");
+        update_index_from_di_config(
+            &mut state,
+            content,
+            &tree,
+            &PathBuf::from("/a/b/c/etc/di.xml"),
+        );
+        let content = r#"<?xml version="1.0"?>
+            <config>
+                <type name="Some\Module\Model\Service">
+                    <plugin name="some_plugin" disabled="false"/>
+                </type>
+            </config>
+            "#;
+        let tree = tree_sitter_parsers::parse(content, "html");
+        update_index_from_di_config(
+            &mut state,
+            content,
+            &tree,
+            &PathBuf::from("/a/b/c/etc/frontend/di.xml"),
+        );
+
+        let type_name = "Some\\Module\\Model\\Service";
+        assert_eq!(
+            state.get_enabled_plugin_names(&M2Area::Frontend, type_name),
+            vec!["some_plugin"]
+        );
+        assert!(state
+            .get_enabled_plugin_names(&M2Area::Base, type_name)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_get_item_from_pos_plugin_name_in_area_di() {
+        let mut state = State::new();
+        state.add_plugin(
+            &M2Area::Base,
+            "Some\\Module\\Model\\Service",
+            "some_plugin",
+            DiPlugin {
+                class: Some("Some\\Module\\Plugin\\ServicePlugin".into()),
+                disabled: None,
+            },
+        );
+        let item = get_test_item_from_pos_with_state(
+            &state,
+            r#"<?xml version="1.0"?>
+            <config>
+                <type name="Some\Module\Model\Service">
+                    <plugin name="some_pl|ugin" disabled="true"/>
+                </type>
+            </config>
+            "#,
+            "/a/b/c/etc/frontend/di.xml",
+        );
+        assert_eq!(
+            item,
+            Some(M2Item::Class("Some\\Module\\Plugin\\ServicePlugin".into()))
+        );
+    }
+
+    #[test]
+    fn test_update_index_from_di_config_types() {
+        let mut state = State::new();
+        let content = r#"<?xml version="1.0"?>
+            <config>
+                <type name="\Some\Module\Model\Service"/>
+                <virtualType name="someVirtualType" type="Some\Module\Model\Service"/>
+            </config>
+            "#;
+        let tree = tree_sitter_parsers::parse(content, "html");
+        update_index_from_di_config(
+            &mut state,
+            content,
+            &tree,
+            &PathBuf::from("/a/b/c/etc/di.xml"),
+        );
+
+        let mut types = state.get_di_types();
+        types.sort_unstable();
+        assert_eq!(
+            types,
+            vec!["Some\\Module\\Model\\Service", "someVirtualType"]
+        );
+    }
+
+    #[test]
+    fn test_get_item_from_pos_class_in_comment() {
+        let item = get_test_item_from_pos(
+            r#"<?xml version="1.0"?>
+            <config>
+                <!-- <preference for="A\B\C" type="D\E|\F"/> -->
+                <type name="G\H\I"/>
+            </config>
+            "#,
+            "/a/b/c/etc/di.xml",
+        );
+        assert_eq!(item, None);
+    }
+
+    #[test]
+    fn test_get_item_from_pos_class_text_in_comment() {
+        let item = get_test_item_from_pos(
+            r#"<?xml version="1.0"?>
+            <config>
+                <item name="a" xsi:type="object"><!-- A\B|\C --></item>
+            </config>
+            "#,
+            "/a/b/c/etc/di.xml",
+        );
+        assert_eq!(item, None);
+    }
+
+    #[test]
+    fn test_get_current_position_path_in_comment() {
+        let item = get_test_position_path(
+            r#"<?xml version="1.0"?>
+            <config>
+                <!-- <preference for="A\B|"/> -->
+            </config>
+            "#,
+        );
+        assert_eq!(item, None);
+    }
+
+    #[test]
+    fn test_get_item_from_pos_component_in_deps_array() {
+        let mut state = State::new();
+        state.add_module_path("Some_Module", PathBuf::from("/a/b/Some/Module"));
+        let item = get_test_item_from_pos_with_state(
+            &state,
+            r#"<?xml version="1.0"?>
+            <page>
+                <block class="Magento\Framework\View\Element\Template" name="some.block">
+                    <arguments>
+                        <argument name="deps" xsi:type="array">
+                            <item name="0" xsi:type="string">Some_Module/js/f|oo</item>
+                        </argument>
+                    </arguments>
+                </block>
+            </page>
+            "#,
+            "/a/b/c/view/frontend/layout/default.xml",
+        );
+        assert_eq!(
+            item,
+            Some(M2Item::ModComponent(
+                "Some_Module".into(),
+                "js/foo".into(),
+                PathBuf::from("/a/b/Some/Module")
+            ))
+        );
+    }
+
+    #[test]
+    fn test_get_item_from_pos_string_in_other_array() {
+        let mut state = State::new();
+        state.add_module_path("Some_Module", PathBuf::from("/a/b/Some/Module"));
+        let item = get_test_item_from_pos_with_state(
+            &state,
+            r#"<?xml version="1.0"?>
+            <page>
+                <argument name="labels" xsi:type="array">
+                    <item name="0" xsi:type="string">Some_Module/js/f|oo</item>
+                </argument>
+            </page>
+            "#,
+            "/a/b/c/view/frontend/layout/default.xml",
+        );
+        assert!(!matches!(
+            item,
+            Some(M2Item::ModComponent(..) | M2Item::Component(_))
+        ));
+    }
+
+    #[test]
+    fn test_get_item_from_pos_ui_element_tmpl() {
+        let mut state = State::new();
+        state.add_module_path("Some_Module", PathBuf::from("/a/b/Some/Module"));
+        let item = get_test_item_from_pos_with_state(
+            &state,
+            r#"<?xml version="1.0"?>
+            <form>
+                <field name="title">
+                    <argument name="data" xsi:type="array">
+                        <item name="config" xsi:type="array">
+                            <item name="elementTmpl" xsi:type="string">Some_Module/form/element/in|put</item>
+                        </item>
+                    </argument>
+                </field>
+            </form>
+            "#,
+            "/a/b/c/view/adminhtml/ui_component/some_form.xml",
+        );
+        assert_eq!(
+            item,
+            Some(M2Item::ModHtml(
+                "Some_Module".into(),
+                "template/form/element/input.html".into(),
+                PathBuf::from("/a/b/Some/Module")
+            ))
+        );
+    }
+
+    #[test]
+    fn test_get_item_from_pos_proxy_class_argument() {
+        let item = get_test_item_from_pos(
+            r#"<?xml version="1.0"?>
+            <config>
+                <type name="Some\Module\Block\Widget">
+                    <arguments>
+                        <argument name="config" xsi:type="object">Some\Module\Model\Config\Pro|xy</argument>
+                    </arguments>
+                </type>
+            </config>
+            "#,
+            "/a/b/c/etc/di.xml",
+        );
+        assert_eq!(
+            item,
+            Some(M2Item::Class("Some\\Module\\Model\\Config\\Proxy".into()))
+        );
+    }
+
+    #[test]
+    fn test_get_item_from_pos_move_element_and_destination() {
+        let xml = r#"<?xml version="1.0"?>
+            <page>
+                <move element="some.b|lock" destination="other.container"/>
+            </page>
+            "#;
+        let item = get_test_item_from_pos(xml, "/a/b/c/view/frontend/layout/default.xml");
+        assert_eq!(item, Some(M2Item::LayoutElement("some.block".into())));
+
+        let xml = r#"<?xml version="1.0"?>
+            <page>
+                <move element="some.block" destination="other.con|tainer"/>
+            </page>
+            "#;
+        let item = get_test_item_from_pos(xml, "/a/b/c/view/frontend/layout/default.xml");
+        assert_eq!(item, Some(M2Item::LayoutElement("other.container".into())));
+    }
+
+    #[test]
+    fn test_update_index_from_layout() {
+        let mut state = State::new();
+        let path = PathBuf::from("/a/b/c/view/frontend/layout/default.xml");
+        state.set_file(
+            &path,
+            r#"<?xml version="1.0"?>
+            <page>
+                <body>
+                    <container name="other.container">
+                        <block class="Some\Module\Block\Widget" name="some.block"/>
+                    </container>
+                </body>
+            </page>
+            "#,
+        );
+
+        let locations = state.get_layout_element_locations("some.block");
+        assert_eq!(
+            locations
+                .iter()
+                .map(|l| (l.uri.clone(), l.range.start.line))
+                .collect::<Vec<_>>(),
+            vec![(Url::from_file_path(&path).unwrap(), 4)]
+        );
+        assert_eq!(
+            state.get_layout_element_locations("other.container").len(),
+            1
+        );
+
+        state.set_file(&path, "<page/>");
+        assert!(state.get_layout_element_locations("some.block").is_empty());
+    }
+
+    #[test]
+    fn test_get_item_from_pos_head_css_src() {
+        let mut state = State::new();
+        state.add_module_path("Some_Module", PathBuf::from("/a/b/Some/Module"));
+        let item = get_test_item_from_pos_with_state(
+            &state,
+            r#"<?xml version="1.0"?>
+            <page>
+                <head>
+                    <css src="Some_Module::css/sty|les.css"/>
+                </head>
+            </page>
+            "#,
+            "/a/b/c/view/frontend/layout/default.xml",
+        );
+        assert_eq!(
+            item,
+            Some(M2Item::ModHtml(
+                "Some_Module".into(),
+                "css/styles.css".into(),
+                PathBuf::from("/a/b/Some/Module")
+            ))
+        );
+    }
+
+    #[test]
+    fn test_update_index_from_acl_config() {
+        let mut state = State::new();
+        let path = PathBuf::from("/a/b/c/etc/acl.xml");
+        state.set_file(
+            &path,
+            r#"<?xml version="1.0"?>
+            <config>
+                <acl>
+                    <resources>
+                        <resource id="Magento_Backend::admin">
+                            <resource id="Some_Module::manage" title="Manage"/>
+                        </resource>
+                    </resources>
+                </acl>
+            </config>
+            "#,
+        );
+
+        let mut resources = state.get_acl_resources();
+        resources.sort_unstable();
+        assert_eq!(
+            resources,
+            vec!["Magento_Backend::admin", "Some_Module::manage"]
+        );
+        assert_eq!(
+            state
+                .get_acl_resource_locations("Some_Module::manage")
+                .iter()
+                .map(|l| l.range.start.line)
+                .collect::<Vec<_>>(),
+            vec![5]
+        );
+
+        let other_path = PathBuf::from("/a/b/d/etc/acl.xml");
+        state.set_file(
+            &other_path,
+            r#"<config><acl><resources><resource id="Magento_Backend::admin"/></resources></acl></config>"#,
+        );
+        state.set_file(&path, "<config/>");
+        assert_eq!(state.get_acl_resources(), vec!["Magento_Backend::admin"]);
+        assert_eq!(
+            state
+                .get_acl_resource_locations("Magento_Backend::admin")
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_get_item_from_pos_resource_ref_in_webapi() {
+        let item = get_test_item_from_pos(
+            r#"<?xml version="1.0"?>
+            <routes>
+                <route url="/V1/some" method="GET">
+                    <service class="Some\Module\Api\SomeInterface" method="get"/>
+                    <resources>
+                        <resource ref="Some_Module::man|age"/>
+                    </resources>
+                </route>
+            </routes>
+            "#,
+            "/a/b/c/etc/webapi.xml",
+        );
+        assert_eq!(
+            item,
+            Some(M2Item::AclResource("Some_Module::manage".into()))
+        );
+    }
+
+    #[test]
+    fn test_get_di_symbols() {
+        let content = r#"<?xml version="1.0"?>
+<config>
+    <preference for="Some\Api\ConfigInterface" type="Some\Model\Config"/>
+    <type name="Some\Model\Service">
+        <plugin name="some_plugin" type="Some\Plugin\Service"/>
+    </type>
+    <virtualType name="someVirtualType" type="Some\Model\Config"/>
+</config>
+"#;
+        let tree = tree_sitter_parsers::parse(content, "html");
+        let symbols = get_di_symbols(&tree, content, &PositionEncodingKind::UTF16);
+
+        let outline: Vec<(String, SymbolKind, Option<String>, Vec<String>)> = symbols
+            .iter()
+            .map(|s| {
+                (
+                    s.name.clone(),
+                    s.kind,
+                    s.detail.clone(),
+                    s.children
+                        .iter()
+                        .flatten()
+                        .map(|c| c.name.clone())
+                        .collect(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            outline,
+            vec![
+                (
+                    "Some\\Api\\ConfigInterface".into(),
+                    SymbolKind::INTERFACE,
+                    Some("Some\\Model\\Config".into()),
+                    vec![]
+                ),
+                (
+                    "Some\\Model\\Service".into(),
+                    SymbolKind::CLASS,
+                    None,
+                    vec!["some_plugin".into()]
+                ),
+                (
+                    "someVirtualType".into(),
+                    SymbolKind::OBJECT,
+                    Some("Some\\Model\\Config".into()),
+                    vec![]
+                ),
+            ]
+        );
+        assert_eq!(
+            symbols[0].selection_range,
+            Range {
+                start: Position {
+                    line: 2,
+                    character: 21
+                },
+                end: Position {
+                    line: 2,
+                    character: 45
+                },
+            }
+        );
+    }
 }
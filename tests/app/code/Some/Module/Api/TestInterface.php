@@ -0,0 +1,12 @@
+<?php
+
+namespace Some\Module\Api;
+
+interface TestInterface
+{
+    const BAR = 'bar';
+
+    public function getBar();
+
+    function getFoo();
+}
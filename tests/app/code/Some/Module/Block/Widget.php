@@ -0,0 +1,18 @@
+<?php
+
+namespace Some\Module\Block;
+
+use Magento\Framework\View\Element\Template;
+
+class Widget extends Template
+{
+    protected $_template = 'Some_Module::widget.phtml';
+
+    public function __construct(
+        Template\Context $context,
+        private readonly \Some\Module\Model\Config $config,
+        array $data = []
+    ) {
+        parent::__construct($context, $data);
+    }
+}
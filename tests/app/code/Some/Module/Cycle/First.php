@@ -0,0 +1,7 @@
+<?php
+
+namespace Some\Module\Cycle;
+
+class First extends Second
+{
+}
@@ -0,0 +1,11 @@
+<?php
+
+namespace Some\Module\Model;
+
+use Some\Module\Api\TestInterface as ApiInterface;
+use Some\Module\Traits\HasLimit;
+
+class Child extends Config implements ApiInterface, \Some\Module\Api\MissingInterface
+{
+    use HasLimit;
+}
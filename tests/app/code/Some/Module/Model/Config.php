@@ -0,0 +1,15 @@
+<?php
+
+namespace Some\Module\Model;
+
+class Config
+{
+    public const PARAM = 'some_param';
+
+    protected const int LIMIT = 10;
+
+    public function getParam()
+    {
+        return self::PARAM;
+    }
+}
@@ -0,0 +1,12 @@
+<?php
+
+namespace Some\Module\Model;
+
+/**
+ * Stable model other modules can depend on
+ *
+ * @api
+ */
+class Stable
+{
+}
@@ -0,0 +1,7 @@
+<?php
+
+namespace Some\Module\Observer;
+
+class ChildObserver extends TestObserver
+{
+}
@@ -0,0 +1,13 @@
+<?php
+
+namespace Some\Module\Observer;
+
+use Magento\Framework\Event\Observer;
+use Magento\Framework\Event\ObserverInterface;
+
+class TestObserver implements ObserverInterface
+{
+    public function execute(Observer $observer)
+    {
+    }
+}
@@ -0,0 +1,13 @@
+<?php
+
+namespace Some\Module\Source;
+
+use Magento\Framework\Data\OptionSourceInterface;
+
+class Yesno implements OptionSourceInterface
+{
+    public function toOptionArray()
+    {
+        return [['value' => 1, 'label' => __('Yes')], ['value' => 0, 'label' => __('No')]];
+    }
+}
@@ -0,0 +1,8 @@
+<?php
+
+namespace Some\Module\Traits;
+
+trait HasLimit
+{
+    public const MAX_LIMIT = 100;
+}
@@ -0,0 +1,7 @@
+<?php
+
+namespace Some\Module\Ui\DataProvider;
+
+class ListingDataProvider
+{
+}
@@ -0,0 +1,7 @@
+var config = {
+    map: {
+        '*': {
+            someModuleComponent: 'Some_Module/js/component'
+        }
+    }
+};
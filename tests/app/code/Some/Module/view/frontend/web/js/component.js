@@ -0,0 +1,5 @@
+define(['jquery'], function ($) {
+    'use strict';
+
+    return function () {};
+});